@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE user_auth_tokens
+    DROP COLUMN IF EXISTS last_used_at,
+    DROP COLUMN IF EXISTS last_used_ip;
@@ -0,0 +1,5 @@
+-- Add up migration script here
+-- Tracks when and where an auth token was last used. Used to find stale tokens.
+ALTER TABLE user_auth_tokens
+    ADD COLUMN IF NOT EXISTS last_used_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS last_used_ip TEXT;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE project_versions
+    DROP COLUMN IF EXISTS yanked_at,
+    DROP COLUMN IF EXISTS yanked_reason;
@@ -0,0 +1,5 @@
+-- Add up migration script here
+-- Yanked versions keep their files but are hidden from generated metadata.
+ALTER TABLE project_versions
+    ADD COLUMN IF NOT EXISTS yanked_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS yanked_reason TEXT;
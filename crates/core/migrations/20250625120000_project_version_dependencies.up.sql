@@ -0,0 +1,28 @@
+-- Add up migration script here
+-- Dependencies declared by a project version. Such as the dependencies of a Maven POM
+create TABLE IF NOT EXISTS project_version_dependencies
+(
+    id                    serial
+        constraint project_version_dependencies_pk
+            primary key,
+    project_version_id    UUID                                               not null
+        constraint fk_project_version
+            references project_versions
+            on delete cascade,
+    group_id              TEXT                                               not null,
+    artifact_id           TEXT                                               not null,
+    version_range         TEXT,
+    scope                 VARCHAR(255)                                       not null,
+    optional              boolean                  default false             not null,
+    dependency_project_id UUID
+        constraint fk_dependency_project
+            references projects
+            on delete set null,
+    created_at            TIMESTAMP WITH TIME ZONE default CURRENT_TIMESTAMP not null
+);
+CREATE INDEX IF NOT EXISTS project_version_dependencies_version
+    ON project_version_dependencies (project_version_id);
+CREATE INDEX IF NOT EXISTS project_version_dependencies_artifact
+    ON project_version_dependencies (group_id, artifact_id);
+CREATE INDEX IF NOT EXISTS project_version_dependencies_project
+    ON project_version_dependencies (dependency_project_id);
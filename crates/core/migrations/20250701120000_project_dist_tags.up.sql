@@ -0,0 +1,19 @@
+-- Add up migration script here
+-- Named pointers to a version of a project. Such as NPM dist-tags
+create TABLE IF NOT EXISTS project_dist_tags
+(
+    id         serial
+        constraint project_dist_tags_pk
+            primary key,
+    project_id UUID                                               not null
+        constraint fk_project
+            references projects
+            on delete cascade,
+    tag        TEXT                                               not null,
+    version_id UUID                                               not null
+        constraint fk_project_version
+            references project_versions
+            on delete cascade,
+    CONSTRAINT unique_project_dist_tag unique (project_id, tag),
+    updated_at TIMESTAMP WITH TIME ZONE default CURRENT_TIMESTAMP not null
+);
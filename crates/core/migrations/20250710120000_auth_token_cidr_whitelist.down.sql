@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE user_auth_tokens
+    DROP COLUMN IF EXISTS cidr_whitelist;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- Restricts the IP addresses an auth token can be used from. An empty list allows all addresses.
+ALTER TABLE user_auth_tokens
+    ADD COLUMN IF NOT EXISTS cidr_whitelist TEXT[] NOT NULL DEFAULT '{}';
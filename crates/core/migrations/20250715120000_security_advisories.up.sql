@@ -0,0 +1,22 @@
+-- Add up migration script here
+-- Security advisories imported from an offline advisory database. Such as an OSV dump of the GitHub Advisory Database.
+-- One row per affected package. So an advisory that affects two packages has two rows.
+CREATE TABLE IF NOT EXISTS security_advisories (
+    id SERIAL PRIMARY KEY,
+    -- The id in the source database. Such as GHSA-xxxx-xxxx-xxxx
+    advisory_id TEXT NOT NULL,
+    ecosystem TEXT NOT NULL,
+    package_name TEXT NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT,
+    severity TEXT NOT NULL,
+    cwe TEXT[] NOT NULL DEFAULT '{}',
+    cvss_vector TEXT,
+    -- The affected version ranges and versions
+    affected JSONB NOT NULL,
+    published_at TIMESTAMP WITH TIME ZONE,
+    modified_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT unique_security_advisory_package UNIQUE (advisory_id, ecosystem, package_name)
+);
+CREATE INDEX IF NOT EXISTS security_advisories_package_idx ON security_advisories (ecosystem, package_name);
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use utoipa::ToSchema;
+
+use crate::database::prelude::*;
+/// A security advisory for a single package.
+///
+/// Imported with `nitro_repo import-advisories`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow, TableType)]
+#[table(name = "security_advisories")]
+pub struct DBSecurityAdvisory {
+    pub id: i32,
+    /// The id in the source database. Such as `GHSA-xxxx-xxxx-xxxx`
+    pub advisory_id: String,
+    /// The OSV ecosystem. Such as `npm`
+    pub ecosystem: String,
+    pub package_name: String,
+    pub title: String,
+    pub url: Option<String>,
+    /// One of `low`, `moderate`, `high` or `critical`
+    pub severity: String,
+    pub cwe: Vec<String>,
+    pub cvss_vector: Option<String>,
+    pub affected: Json<AffectedVersions>,
+    pub published_at: Option<DateTime<FixedOffset>>,
+    pub modified_at: Option<DateTime<FixedOffset>>,
+    pub created_at: DateTime<FixedOffset>,
+}
+/// The versions of the package that are affected by the advisory
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+pub struct AffectedVersions {
+    #[serde(default)]
+    pub ranges: Vec<AffectedRange>,
+    /// Versions that are affected outside of the ranges
+    #[serde(default)]
+    pub versions: Vec<String>,
+}
+/// A range of affected versions. Versions are compared with the ecosystem's version rules
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+pub struct AffectedRange {
+    /// The first affected version. None or `0` means all versions before `fixed` or `last_affected`
+    pub introduced: Option<String>,
+    /// The first version that is no longer affected
+    pub fixed: Option<String>,
+    /// The last affected version. Used when there is no fix
+    pub last_affected: Option<String>,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewSecurityAdvisory {
+    pub advisory_id: String,
+    pub ecosystem: String,
+    pub package_name: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub severity: String,
+    pub cwe: Vec<String>,
+    pub cvss_vector: Option<String>,
+    pub affected: AffectedVersions,
+    pub published_at: Option<DateTime<FixedOffset>>,
+    pub modified_at: Option<DateTime<FixedOffset>>,
+}
+impl DBSecurityAdvisory {
+    /// The advisories for the packages
+    #[instrument(skip(database))]
+    pub async fn find_by_packages(
+        ecosystem: &str,
+        package_names: &[String],
+        database: &PgPool,
+    ) -> DBResult<Vec<Self>> {
+        let advisories = sqlx::query_as(
+            r#"SELECT * FROM security_advisories WHERE ecosystem = $1 AND package_name = ANY($2) ORDER BY id"#,
+        )
+        .bind(ecosystem)
+        .bind(package_names)
+        .fetch_all(database)
+        .await?;
+        Ok(advisories)
+    }
+    /// Replaces every row of the advisory.
+    ///
+    /// Importing a newer version of an advisory can change the packages it affects. Withdrawn advisories are replaced with nothing
+    #[instrument(skip(advisories, database))]
+    pub async fn replace_advisory(
+        advisory_id: &str,
+        advisories: &[NewSecurityAdvisory],
+        database: &PgPool,
+    ) -> DBResult<()> {
+        let mut transaction = database.begin().await?;
+        sqlx::query("DELETE FROM security_advisories WHERE advisory_id = $1")
+            .bind(advisory_id)
+            .execute(&mut *transaction)
+            .await?;
+        for advisory in advisories {
+            sqlx::query(
+                r#"INSERT INTO security_advisories
+                (advisory_id, ecosystem, package_name, title, url, severity, cwe, cvss_vector, affected, published_at, modified_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                ON CONFLICT (advisory_id, ecosystem, package_name) DO NOTHING"#,
+            )
+            .bind(&advisory.advisory_id)
+            .bind(&advisory.ecosystem)
+            .bind(&advisory.package_name)
+            .bind(&advisory.title)
+            .bind(&advisory.url)
+            .bind(&advisory.severity)
+            .bind(&advisory.cwe)
+            .bind(&advisory.cvss_vector)
+            .bind(Json(&advisory.affected))
+            .bind(advisory.published_at)
+            .bind(advisory.modified_at)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+    /// The number of advisories imported for the ecosystem
+    #[instrument(skip(database))]
+    pub async fn count_by_ecosystem(ecosystem: &str, database: &PgPool) -> DBResult<i64> {
+        let count = sqlx::query_scalar(
+            r#"SELECT COUNT(DISTINCT advisory_id) FROM security_advisories WHERE ecosystem = $1"#,
+        )
+        .bind(ecosystem)
+        .fetch_one(database)
+        .await?;
+        Ok(count)
+    }
+}
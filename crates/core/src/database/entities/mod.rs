@@ -1,3 +1,4 @@
+pub mod advisory;
 pub mod project;
 pub mod repository;
 pub mod stages;
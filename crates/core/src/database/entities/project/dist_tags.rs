@@ -0,0 +1,61 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::database::prelude::*;
+/// A named pointer to a version of a project. NPM calls these dist-tags
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow, ToSchema)]
+pub struct DBProjectDistTag {
+    pub project_id: Uuid,
+    pub tag: String,
+    pub version_id: Uuid,
+    /// The version the tag points to
+    pub version: String,
+    pub updated_at: DateTime<FixedOffset>,
+}
+impl DBProjectDistTag {
+    #[instrument(skip(database))]
+    pub async fn find_by_project(project_id: Uuid, database: &PgPool) -> DBResult<Vec<Self>> {
+        let tags = sqlx::query_as(
+            r#"SELECT project_dist_tags.project_id, project_dist_tags.tag, project_dist_tags.version_id,
+                project_versions.version, project_dist_tags.updated_at
+            FROM project_dist_tags
+            INNER JOIN project_versions ON project_versions.id = project_dist_tags.version_id
+            WHERE project_dist_tags.project_id = $1 ORDER BY project_dist_tags.tag"#,
+        )
+        .bind(project_id)
+        .fetch_all(database)
+        .await?;
+        Ok(tags)
+    }
+    /// Points the tag at the version. Creating the tag if it does not exist
+    #[instrument(skip(database))]
+    pub async fn set(
+        project_id: Uuid,
+        tag: &str,
+        version_id: Uuid,
+        database: &PgPool,
+    ) -> DBResult<()> {
+        sqlx::query(
+            r#"INSERT INTO project_dist_tags (project_id, tag, version_id) VALUES ($1, $2, $3)
+            ON CONFLICT (project_id, tag) DO UPDATE SET version_id = $3, updated_at = CURRENT_TIMESTAMP"#,
+        )
+        .bind(project_id)
+        .bind(tag)
+        .bind(version_id)
+        .execute(database)
+        .await?;
+        Ok(())
+    }
+    /// Returns true if the tag existed
+    #[instrument(skip(database))]
+    pub async fn delete(project_id: Uuid, tag: &str, database: &PgPool) -> DBResult<bool> {
+        let result =
+            sqlx::query(r#"DELETE FROM project_dist_tags WHERE project_id = $1 AND tag = $2"#)
+                .bind(project_id)
+                .bind(tag)
+                .execute(database)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -16,4 +16,18 @@ pub struct DBProjectMember {
     pub can_manage: bool,
     pub added: chrono::DateTime<chrono::FixedOffset>,
 }
-impl DBProjectMember {}
+impl DBProjectMember {
+    pub async fn find_by_project_and_user(
+        project_id: Uuid,
+        user_id: i32,
+        database: &PgPool,
+    ) -> DBResult<Option<Self>> {
+        let member =
+            sqlx::query_as("SELECT * FROM project_members WHERE project_id = $1 AND user_id = $2")
+                .bind(project_id)
+                .bind(user_id)
+                .fetch_optional(database)
+                .await?;
+        Ok(member)
+    }
+}
@@ -8,8 +8,10 @@ mod new;
 pub mod utils;
 use crate::{database::prelude::*, repository::project::ReleaseType};
 pub use new::*;
+pub mod dist_tags;
 pub mod info;
 pub mod members;
+pub mod search;
 pub mod update;
 pub mod versions;
 /// Implemented on different types of Project query result. Such as ProjectLookupResult
@@ -63,6 +65,30 @@ pub trait ProjectDBType: for<'r> FromRow<'r, PgRow> + Unpin + Send + Sync + Tabl
         Ok(project)
     }
 
+    #[instrument(skip(database))]
+    async fn find_by_repository(repository: Uuid, database: &PgPool) -> DBResult<Vec<Self>> {
+        let projects = SelectQueryBuilder::with_columns(DBProject::table_name(), Self::columns())
+            .filter(DBProjectColumn::RepositoryId.equals(repository.value()))
+            .query_as()
+            .fetch_all(database)
+            .await?;
+        Ok(projects)
+    }
+    /// Finds all projects with the scope. Maven uses the groupId as the scope
+    #[instrument(skip(database))]
+    async fn find_by_scope(
+        scope: &str,
+        repository: Uuid,
+        database: &PgPool,
+    ) -> DBResult<Vec<Self>> {
+        let projects = SelectQueryBuilder::with_columns(DBProject::table_name(), Self::columns())
+            .filter(DBProjectColumn::RepositoryId.equals(repository.value()))
+            .filter(DBProjectColumn::Scope.equals(scope.value()))
+            .query_as()
+            .fetch_all(database)
+            .await?;
+        Ok(projects)
+    }
     /// Finds a Project by the directory of the version
     async fn find_by_version_directory(
         directory: &str,
@@ -174,6 +200,17 @@ impl ProjectDBType for DBProject {
         self.id
     }
 }
+impl DBProject {
+    /// Deletes the project. Versions and members are deleted with it
+    #[instrument(skip(database))]
+    pub async fn delete_by_id(id: Uuid, database: &PgPool) -> DBResult<()> {
+        sqlx::query(r#"DELETE FROM projects WHERE id = $1"#)
+            .bind(id)
+            .execute(database)
+            .await?;
+        Ok(())
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow)]
 pub struct ProjectIds {
@@ -0,0 +1,281 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::versions::DBProjectVersion;
+use crate::database::prelude::*;
+/// The latest version of every project in the repository and the lowercase text that is searched.
+///
+/// The latest version is the one the `latest` dist-tag points to. Otherwise the highest version that is not yanked.
+/// Releases are picked over prereleases. Prereleases of the same version are ordered by when they were published.
+///
+/// The key uses the `C` collation because substring searches do not support the case insensitive collation.
+/// The description and keywords are read from the manifest the version was published with. Stored in [crate::repository::project::VersionData::extra]
+const SEARCH_CANDIDATES: &str = r#"SELECT project_versions.*, projects.key AS project_key,
+    LOWER(projects.key COLLATE "C") AS search_name,
+    REGEXP_REPLACE(LOWER(projects.key COLLATE "C"), '^.*/', '') AS search_short_name,
+    LOWER(COALESCE(project_versions.extra->'extra'->>'description', project_versions.extra->>'description', projects.description, '')) AS search_description,
+    CASE JSONB_TYPEOF(project_versions.extra->'extra'->'keywords')
+        WHEN 'array' THEN ARRAY(
+            SELECT LOWER(keyword) FROM JSONB_ARRAY_ELEMENTS_TEXT(project_versions.extra->'extra'->'keywords') AS keyword
+        )
+        WHEN 'string' THEN ARRAY(
+            SELECT LOWER(TRIM(keyword)) FROM REGEXP_SPLIT_TO_TABLE(project_versions.extra->'extra'->>'keywords', ',') AS keyword
+            WHERE TRIM(keyword) <> ''
+        )
+        ELSE ARRAY[]::TEXT[]
+    END AS search_keywords
+FROM projects
+INNER JOIN LATERAL (
+    SELECT project_versions.* FROM project_versions
+    LEFT JOIN project_dist_tags ON project_dist_tags.version_id = project_versions.id AND project_dist_tags.tag = 'latest'
+    WHERE project_versions.project_id = projects.id
+        AND (project_dist_tags.version_id IS NOT NULL OR project_versions.yanked_at IS NULL)
+    ORDER BY project_dist_tags.version_id IS NULL,
+        SPLIT_PART(project_versions.version, '+', 1) LIKE '%-%',
+        (REGEXP_MATCH(project_versions.version, '^(\d+)\.(\d+)\.(\d+)'))::NUMERIC[] DESC NULLS LAST,
+        project_versions.created_at DESC
+    LIMIT 1
+) project_versions ON TRUE
+WHERE projects.repository_id = "#;
+/// A text search over the latest version of every project in a repository
+///
+/// Every term must match the name, a keyword or the description. Matches on the name score the highest
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectSearch {
+    /// Lowercase terms
+    pub terms: Vec<String>,
+    /// Lowercase keywords the project must have all of
+    pub keywords: Vec<String>,
+    /// The scope of the project key without the `@`
+    pub scope: Option<String>,
+    pub limit: i64,
+    pub offset: i64,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct DBProjectSearchResult {
+    #[sqlx(flatten)]
+    pub version: DBProjectVersion,
+    pub project_key: String,
+    /// From 0 to 1. How well the project matched
+    pub relevance: f64,
+    /// The number of projects that matched. Ignoring the limit and offset
+    pub total: i64,
+}
+impl ProjectSearch {
+    /// The score of a single term. NULL if it does not match
+    fn push_term_score(query: &mut QueryBuilder<'_, Postgres>, term: &str) {
+        query
+            .push("CASE WHEN candidates.search_name = ")
+            .push_bind(term.to_owned())
+            .push(" OR candidates.search_short_name = ")
+            .push_bind(term.to_owned())
+            .push(" THEN 1.0 WHEN STARTS_WITH(candidates.search_short_name, ")
+            .push_bind(term.to_owned())
+            .push(") THEN 0.8 WHEN STRPOS(candidates.search_name, ")
+            .push_bind(term.to_owned())
+            .push(") > 0 THEN 0.6 WHEN ")
+            .push_bind(term.to_owned())
+            .push(" = ANY(candidates.search_keywords) THEN 0.5 WHEN STRPOS(candidates.search_description, ")
+            .push_bind(term.to_owned())
+            .push(") > 0 THEN 0.3 END");
+    }
+    fn build_query(&self, repository: Uuid) -> QueryBuilder<'static, Postgres> {
+        let mut query = QueryBuilder::new("SELECT candidates.*, ");
+        if self.terms.is_empty() {
+            query.push("1.0::FLOAT8");
+        } else {
+            query.push("((");
+            for (index, term) in self.terms.iter().enumerate() {
+                if index > 0 {
+                    query.push(") + (");
+                }
+                Self::push_term_score(&mut query, term);
+            }
+            query
+                .push("))::FLOAT8 / ")
+                .push_bind(self.terms.len() as f64);
+        }
+        query
+            .push(" AS relevance, COUNT(*) OVER () AS total FROM (")
+            .push(SEARCH_CANDIDATES)
+            .push_bind(repository)
+            .push(") candidates WHERE TRUE");
+        if let Some(scope) = &self.scope {
+            query
+                .push(" AND STARTS_WITH(candidates.search_name, ")
+                .push_bind(format!("@{scope}/"))
+                .push(")");
+        }
+        for keyword in &self.keywords {
+            query
+                .push(" AND ")
+                .push_bind(keyword.clone())
+                .push(" = ANY(candidates.search_keywords)");
+        }
+        for term in &self.terms {
+            query.push(" AND (");
+            Self::push_term_score(&mut query, term);
+            query.push(") IS NOT NULL");
+        }
+        query
+            .push(" ORDER BY relevance DESC, candidates.search_name LIMIT ")
+            .push_bind(self.limit)
+            .push(" OFFSET ")
+            .push_bind(self.offset);
+        query
+    }
+    /// Searches the latest version of every project in the repository. Ordered by relevance then name
+    #[instrument(skip(database))]
+    pub async fn search(
+        &self,
+        repository: Uuid,
+        database: &PgPool,
+    ) -> DBResult<Vec<DBProjectSearchResult>> {
+        let results = self
+            .build_query(repository)
+            .build_query_as()
+            .fetch_all(database)
+            .await?;
+        Ok(results)
+    }
+}
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        database::entities::project::{
+            NewProject, dist_tags::DBProjectDistTag, versions::NewVersion,
+        },
+        repository::project::{ReleaseType, VersionData},
+        testing::TestCore,
+    };
+    async fn insert_version(
+        project_id: Uuid,
+        version: &str,
+        description: &str,
+        keywords: &[&str],
+        database: &PgPool,
+    ) -> sqlx::Result<DBProjectVersion> {
+        NewVersion {
+            project_id,
+            version: version.to_owned(),
+            release_type: ReleaseType::release_type_from_version(version),
+            version_path: format!("{project_id}/{version}"),
+            publisher: None,
+            version_page: None,
+            extra: VersionData {
+                extra: Some(json!({ "description": description, "keywords": keywords })),
+                ..Default::default()
+            },
+        }
+        .insert(database)
+        .await
+    }
+    #[ignore = "Requires a database"]
+    #[tokio::test]
+    pub async fn search_latest_versions() {
+        let (core, entry) = TestCore::new(format!("{}::search_latest_versions", module_path!()))
+            .await
+            .unwrap();
+        let database = &core.db;
+        let repository = core.create_test_repository("npm").await.unwrap();
+        let mut projects = Vec::new();
+        for key in ["@nr/left-pad", "right-pad", "unrelated"] {
+            let project = NewProject {
+                scope: None,
+                project_key: key.to_owned(),
+                name: key.to_owned(),
+                description: None,
+                repository,
+                storage_path: key.to_owned(),
+            }
+            .insert(database)
+            .await
+            .unwrap();
+            projects.push(project.id);
+        }
+        let left_pad = projects[0];
+        insert_version(left_pad, "1.0.0", "Pads a string", &["string"], database)
+            .await
+            .unwrap();
+        insert_version(
+            left_pad,
+            "1.10.0",
+            "Pads the left side of a string",
+            &["string"],
+            database,
+        )
+        .await
+        .unwrap();
+        insert_version(left_pad, "2.0.0-beta.1", "Beta", &[], database)
+            .await
+            .unwrap();
+        let right_pad_latest = insert_version(projects[1], "1.0.0", "Pads a string", &[], database)
+            .await
+            .unwrap();
+        insert_version(projects[1], "2.0.0", "Pads a string", &[], database)
+            .await
+            .unwrap();
+        DBProjectDistTag::set(projects[1], "latest", right_pad_latest.id, database)
+            .await
+            .unwrap();
+        insert_version(projects[2], "1.0.0", "Something else", &["other"], database)
+            .await
+            .unwrap();
+
+        let search = |terms: &[&str], keywords: &[&str], scope: Option<&str>| ProjectSearch {
+            terms: terms.iter().map(|term| (*term).to_owned()).collect(),
+            keywords: keywords
+                .iter()
+                .map(|keyword| (*keyword).to_owned())
+                .collect(),
+            scope: scope.map(ToOwned::to_owned),
+            limit: 20,
+            offset: 0,
+        };
+        let results = search(&["pad"], &[], None)
+            .search(repository, database)
+            .await
+            .unwrap();
+        let found: Vec<(&str, &str)> = results
+            .iter()
+            .map(|result| (result.project_key.as_str(), result.version.version.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("@nr/left-pad", "1.10.0"), ("right-pad", "1.0.0")]
+        );
+        assert!(results.iter().all(|result| result.total == 2));
+
+        let results = search(&["left", "side"], &[], None)
+            .search(repository, database)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].relevance, 0.55);
+
+        let results = search(&[], &["string"], Some("nr"))
+            .search(repository, database)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].project_key, "@nr/left-pad");
+
+        let mut paged = search(&["pad"], &[], None);
+        paged.limit = 1;
+        paged.offset = 1;
+        let results = paged.search(repository, database).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].project_key, "right-pad");
+        assert_eq!(results[0].total, 2);
+
+        sqlx::query("DELETE FROM storages WHERE id = $1")
+            .bind(storage_id)
+            .execute(database)
+            .await
+            .unwrap();
+        entry.set_success(database).await.unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::database::prelude::*;
+/// A dependency declared by a project version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow, ToSchema, TableType)]
+#[table(name = "project_version_dependencies")]
+pub struct DBProjectVersionDependency {
+    pub id: i32,
+    pub project_version_id: Uuid,
+    pub group_id: String,
+    pub artifact_id: String,
+    /// The declared version or version range
+    pub version_range: Option<String>,
+    pub scope: String,
+    pub optional: bool,
+    /// The hosted project the dependency resolved to. None if no hosted repository contains it
+    pub dependency_project_id: Option<Uuid>,
+    pub created_at: DateTime<FixedOffset>,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewProjectVersionDependency {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version_range: Option<String>,
+    pub scope: String,
+    pub optional: bool,
+}
+impl DBProjectVersionDependency {
+    #[instrument(skip(database))]
+    pub async fn find_by_version(
+        project_version_id: Uuid,
+        database: &PgPool,
+    ) -> DBResult<Vec<Self>> {
+        let dependencies = sqlx::query_as(
+            "SELECT * FROM project_version_dependencies WHERE project_version_id = $1 ORDER BY id",
+        )
+        .bind(project_version_id)
+        .fetch_all(database)
+        .await?;
+        Ok(dependencies)
+    }
+    /// Replaces every dependency of the version.
+    ///
+    /// Dependencies are linked to the project with the same key in `repositories`. A project in `preferred_repository` is picked first
+    #[instrument(skip(dependencies, database))]
+    pub async fn replace_for_version(
+        project_version_id: Uuid,
+        dependencies: &[NewProjectVersionDependency],
+        preferred_repository: Uuid,
+        repositories: &[Uuid],
+        database: &PgPool,
+    ) -> DBResult<()> {
+        let mut transaction = database.begin().await?;
+        sqlx::query("DELETE FROM project_version_dependencies WHERE project_version_id = $1")
+            .bind(project_version_id)
+            .execute(&mut *transaction)
+            .await?;
+        for dependency in dependencies {
+            sqlx::query(
+                r#"INSERT INTO project_version_dependencies
+                (project_version_id, group_id, artifact_id, version_range, scope, optional)
+                VALUES ($1, $2, $3, $4, $5, $6)"#,
+            )
+            .bind(project_version_id)
+            .bind(&dependency.group_id)
+            .bind(&dependency.artifact_id)
+            .bind(&dependency.version_range)
+            .bind(&dependency.scope)
+            .bind(dependency.optional)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        sqlx::query(
+            r#"UPDATE project_version_dependencies AS dependencies SET dependency_project_id = projects.id
+            FROM (
+                SELECT DISTINCT ON (LOWER(key)) id, LOWER(key) AS key FROM projects
+                WHERE repository_id = ANY($2)
+                ORDER BY LOWER(key), repository_id = $3 DESC, created_at
+            ) AS projects
+            WHERE dependencies.project_version_id = $1
+                AND projects.key = LOWER(dependencies.group_id || ':' || dependencies.artifact_id)"#,
+        )
+        .bind(project_version_id)
+        .bind(repositories)
+        .bind(preferred_repository)
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+    /// Links dependencies on `group_id:artifact_id` that were not resolved to a project yet.
+    ///
+    /// Called when a project is created in a hosted repository
+    #[instrument(skip(database))]
+    pub async fn link_to_project(
+        project_id: Uuid,
+        group_id: &str,
+        artifact_id: &str,
+        database: &PgPool,
+    ) -> DBResult<u64> {
+        let result = sqlx::query(
+            r#"UPDATE project_version_dependencies SET dependency_project_id = $1
+            WHERE dependency_project_id IS NULL AND LOWER(group_id) = LOWER($2) AND LOWER(artifact_id) = LOWER($3)"#,
+        )
+        .bind(project_id)
+        .bind(group_id)
+        .bind(artifact_id)
+        .execute(database)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+/// A project version that depends on another project
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow, ToSchema)]
+pub struct ProjectDependent {
+    pub project_id: Uuid,
+    pub project_key: String,
+    pub repository_id: Uuid,
+    pub version_id: Uuid,
+    pub version: String,
+    /// The version or version range the dependent requires
+    pub version_range: Option<String>,
+    pub scope: String,
+    pub optional: bool,
+}
+impl ProjectDependent {
+    /// `filter` is the condition on the `dependencies` table.
+    ///
+    /// If `latest_only` only the newest version of each project that is not yanked is checked
+    fn query(filter: &str, latest_only: bool) -> String {
+        let latest_filter = if latest_only {
+            r#"AND project_versions.id IN (
+                SELECT DISTINCT ON (project_id) id FROM project_versions
+                WHERE yanked_at IS NULL ORDER BY project_id, created_at DESC
+            )"#
+        } else {
+            ""
+        };
+        format!(
+            r#"SELECT projects.id AS project_id, projects.key AS project_key, projects.repository_id,
+                project_versions.id AS version_id, project_versions.version,
+                dependencies.version_range, dependencies.scope, dependencies.optional
+            FROM project_version_dependencies AS dependencies
+            INNER JOIN project_versions ON project_versions.id = dependencies.project_version_id
+            INNER JOIN projects ON projects.id = project_versions.project_id
+            WHERE {filter} {latest_filter}
+            ORDER BY projects.key, project_versions.created_at DESC"#
+        )
+    }
+    /// Versions that depend on `group_id:artifact_id`
+    #[instrument(skip(database))]
+    pub async fn find_by_artifact(
+        group_id: &str,
+        artifact_id: &str,
+        latest_only: bool,
+        database: &PgPool,
+    ) -> DBResult<Vec<Self>> {
+        let query = Self::query(
+            "dependencies.group_id = $1 AND dependencies.artifact_id = $2",
+            latest_only,
+        );
+        let dependents = sqlx::query_as(&query)
+            .bind(group_id)
+            .bind(artifact_id)
+            .fetch_all(database)
+            .await?;
+        Ok(dependents)
+    }
+    /// Versions with a dependency that was linked to the project
+    #[instrument(skip(database))]
+    pub async fn find_by_dependency_project(
+        project_id: Uuid,
+        latest_only: bool,
+        database: &PgPool,
+    ) -> DBResult<Vec<Self>> {
+        let query = Self::query("dependencies.dependency_project_id = $1", latest_only);
+        let dependents = sqlx::query_as(&query)
+            .bind(project_id)
+            .fetch_all(database)
+            .await?;
+        Ok(dependents)
+    }
+}
@@ -17,6 +17,7 @@ pub struct VersionHistoryItem {
     pub id: Uuid,
     pub release_type: ReleaseType,
     pub version: String,
+    pub yanked_at: Option<DateTime<FixedOffset>>,
     pub updated_at: DateTime<FixedOffset>,
     pub created_at: DateTime<FixedOffset>,
 }
@@ -31,6 +32,7 @@ impl TableQuery for VersionHistoryItem {
             DBProjectVersionColumn::Id,
             DBProjectVersionColumn::ReleaseType,
             DBProjectVersionColumn::Version,
+            DBProjectVersionColumn::YankedAt,
             DBProjectVersionColumn::UpdatedAt,
             DBProjectVersionColumn::CreatedAt,
         ]
@@ -8,6 +8,7 @@ use crate::{
     database::prelude::*,
     repository::project::{ReleaseType, VersionData},
 };
+pub mod dependencies;
 pub mod history;
 pub use update::*;
 pub trait ProjectVersionType:
@@ -34,6 +35,10 @@ pub struct DBProjectVersion {
     /// The version data. More data can be added in the future and the data can be repository dependent
     #[schema(value_type = VersionData)]
     pub extra: Json<VersionData>,
+    /// When the version was yanked. Yanked versions keep their files but are hidden from generated metadata
+    pub yanked_at: Option<DateTime<FixedOffset>>,
+    /// Why the version was yanked
+    pub yanked_reason: Option<String>,
     /// When the version was created
     pub updated_at: DateTime<FixedOffset>,
     pub created_at: DateTime<FixedOffset>,
@@ -60,6 +65,7 @@ impl DBProjectVersion {
         .await?;
         Ok(version)
     }
+    /// The version of a project in the repository with the directory
     #[instrument(skip(database))]
     pub async fn find_by_version_directory(
         directory: &str,
@@ -67,7 +73,9 @@ impl DBProjectVersion {
         database: &PgPool,
     ) -> Result<Option<Self>, sqlx::Error> {
         let version = sqlx::query_as::<_, Self>(
-            r#"SELECT project_versions.* FROM project_versions FULL JOIN projects ON projects.id = project_versions.project_id AND projects.repository_id = $1 WHERE LOWER(project_versions.path) = $2"#,
+            r#"SELECT project_versions.* FROM project_versions
+            INNER JOIN projects ON projects.id = project_versions.project_id
+            WHERE projects.repository_id = $1 AND LOWER(project_versions.path) = $2"#,
         )
         .bind(repository_id)
         .bind(directory.to_lowercase())
@@ -82,7 +90,9 @@ impl DBProjectVersion {
         database: &PgPool,
     ) -> Result<Option<ProjectIds>, sqlx::Error> {
         let version = sqlx::query_as::<_, ProjectIds>(
-            r#"SELECT project_versions.id as version_id, project_versions.project_id as project_id FROM project_versions FULL JOIN projects ON projects.id = project_versions.project_id AND projects.repository_id = $1 WHERE LOWER(project_versions.path) = $2"#,
+            r#"SELECT project_versions.id as version_id, project_versions.project_id as project_id FROM project_versions
+            INNER JOIN projects ON projects.id = project_versions.project_id
+            WHERE projects.repository_id = $1 AND LOWER(project_versions.path) = $2"#,
         )
         .bind(repository_id)
         .bind(directory.to_lowercase())
@@ -102,4 +112,111 @@ impl DBProjectVersion {
                 .await?;
         Ok(versions)
     }
+    #[instrument(skip(database))]
+    pub async fn delete_by_id(id: Uuid, database: &PgPool) -> DBResult<()> {
+        sqlx::query(r#"DELETE FROM project_versions WHERE id = $1"#)
+            .bind(id)
+            .execute(database)
+            .await?;
+        Ok(())
+    }
+    /// Marks the version as yanked. The files are kept
+    #[instrument(skip(database))]
+    pub async fn yank(id: Uuid, reason: Option<&str>, database: &PgPool) -> DBResult<()> {
+        sqlx::query(
+            r#"UPDATE project_versions SET yanked_at = NOW(), yanked_reason = $2, updated_at = NOW() WHERE id = $1"#,
+        )
+        .bind(id)
+        .bind(reason)
+        .execute(database)
+        .await?;
+        Ok(())
+    }
+    #[instrument(skip(database))]
+    pub async fn unyank(id: Uuid, database: &PgPool) -> DBResult<()> {
+        sqlx::query(
+            r#"UPDATE project_versions SET yanked_at = NULL, yanked_reason = NULL, updated_at = NOW() WHERE id = $1"#,
+        )
+        .bind(id)
+        .execute(database)
+        .await?;
+        Ok(())
+    }
+}
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{database::entities::project::NewProject, testing::TestCore};
+    async fn insert_version(
+        repository: Uuid,
+        path: &str,
+        database: &PgPool,
+    ) -> sqlx::Result<DBProjectVersion> {
+        let project = NewProject {
+            scope: None,
+            project_key: "com.example:lib".to_owned(),
+            name: "lib".to_owned(),
+            description: None,
+            repository,
+            storage_path: "com/example/lib".to_owned(),
+        }
+        .insert(database)
+        .await?;
+        NewVersion {
+            project_id: project.id,
+            version: "1.0.0".to_owned(),
+            release_type: ReleaseType::Stable,
+            version_path: path.to_owned(),
+            publisher: None,
+            version_page: None,
+            extra: VersionData::default(),
+        }
+        .insert(database)
+        .await
+    }
+    #[ignore = "Requires a database"]
+    #[tokio::test]
+    pub async fn version_directory_is_scoped_to_repository() {
+        let (core, entry) = TestCore::new(format!(
+            "{}::version_directory_is_scoped_to_repository",
+            module_path!()
+        ))
+        .await
+        .unwrap();
+        let database = &core.db;
+        let first = core.create_test_repository("maven").await.unwrap();
+        let second = core.create_test_repository("maven").await.unwrap();
+        let path = "com/example/lib/1.0.0";
+        let first_version = insert_version(first, path, database).await.unwrap();
+        let second_version = insert_version(second, path, database).await.unwrap();
+
+        for (repository, version) in [(first, &first_version), (second, &second_version)] {
+            let found = DBProjectVersion::find_by_version_directory(path, repository, database)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(found.id, version.id);
+            let ids = DBProjectVersion::find_ids_by_version_dir(path, repository, database)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(ids.version_id, version.id);
+        }
+        let other_repository = insert_repository(storage_id, database).await.unwrap();
+        assert!(
+            DBProjectVersion::find_by_version_directory(path, other_repository, database)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        sqlx::query("DELETE FROM storages WHERE id = $1")
+            .bind(storage_id)
+            .execute(database)
+            .await
+            .unwrap();
+        entry.set_success(database).await.unwrap();
+    }
 }
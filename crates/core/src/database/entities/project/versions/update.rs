@@ -62,7 +62,7 @@ impl UpdateProjectVersion {
     pub async fn update(&self, version_id: Uuid, database: &PgPool) -> DBResult<()> {
         let mut update = UpdateQueryBuilder::new(DBProjectVersion::table_name());
         update
-            .set(DBProjectVersionColumn::Id, version_id)
+            .filter(DBProjectVersionColumn::Id.equals(version_id.value()))
             .set(DBProjectVersionColumn::UpdatedAt, SqlFunctionBuilder::now());
 
         if let Some(release_type) = &self.release_type {
@@ -7,6 +7,7 @@ use uuid::Uuid;
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow, ToSchema)]
 pub struct DBStage {
     pub id: Uuid,
+    #[sqlx(rename = "repository_id")]
     pub repository: Uuid,
     #[schema(value_type = crate::utils::utopia::AnyType)]
     pub stage_state: Json<Value>,
@@ -20,7 +21,7 @@ impl DBStage {
         repository: Uuid,
         database: &sqlx::PgPool,
     ) -> Result<Option<DBStage>, sqlx::Error> {
-        let query = "SELECT * FROM stages WHERE id = $1 AND repository = $2".to_string();
+        let query = "SELECT * FROM stages WHERE id = $1 AND repository_id = $2".to_string();
         let stage = sqlx::query_as(&query)
             .bind(id)
             .bind(repository)
@@ -48,13 +49,25 @@ impl DBStage {
         repository: Uuid,
         database: &sqlx::PgPool,
     ) -> Result<Vec<DBStage>, sqlx::Error> {
-        let query = "SELECT * FROM stages WHERE repository = $1".to_string();
+        let query = "SELECT * FROM stages WHERE repository_id = $1".to_string();
         let stages = sqlx::query_as(&query)
             .bind(repository)
             .fetch_all(database)
             .await?;
         Ok(stages)
     }
+    /// Stages of every repository that were created before `created_before`
+    pub async fn get_stages_created_before(
+        created_before: chrono::DateTime<chrono::FixedOffset>,
+        database: &sqlx::PgPool,
+    ) -> Result<Vec<DBStage>, sqlx::Error> {
+        let query = "SELECT * FROM stages WHERE created_at < $1".to_string();
+        let stages = sqlx::query_as(&query)
+            .bind(created_before)
+            .fetch_all(database)
+            .await?;
+        Ok(stages)
+    }
 }
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow, ToSchema)]
 pub struct DBStageFile {
@@ -71,7 +84,7 @@ pub struct NewDBStage {
 }
 impl NewDBStage {
     pub async fn insert(&self, database: &sqlx::PgPool) -> Result<DBStage, sqlx::Error> {
-        let query = "INSERT INTO stages (repository, stage_state, created_by) VALUES ($1, $2, $3) RETURNING *".to_string();
+        let query = "INSERT INTO stages (repository_id, stage_state, created_by) VALUES ($1, $2, $3) RETURNING *".to_string();
         let stage = sqlx::query_as(&query)
             .bind(self.repository)
             .bind(Json(self.stage_state.clone()))
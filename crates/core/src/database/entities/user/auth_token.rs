@@ -1,9 +1,14 @@
+use std::net::IpAddr;
+
 use pg_extended_sqlx_queries::prelude::*;
 use sqlx::{PgPool, prelude::FromRow};
 use tracing::instrument;
 use uuid::Uuid;
 
-use crate::user::{permissions::RepositoryActions, scopes::NRScope};
+use crate::{
+    user::{permissions::RepositoryActions, scopes::NRScope},
+    utils::cidr::Cidr,
+};
 
 use super::ReferencesUser;
 mod repository_scope;
@@ -24,6 +29,10 @@ pub struct AuthToken {
     pub active: bool,
     pub source: String,
     pub expires_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub last_used_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub last_used_ip: Option<String>,
+    /// CIDR ranges the token can be used from. Empty allows all addresses
+    pub cidr_whitelist: Vec<String>,
     pub created_at: chrono::DateTime<chrono::FixedOffset>,
 }
 impl ReferencesUser for AuthToken {
@@ -43,14 +52,59 @@ impl ReferencesUser for AuthToken {
     }
 }
 impl AuthToken {
+    /// Gets an active token that has not expired
     pub async fn get_by_token(token: &str, database: &PgPool) -> sqlx::Result<Option<Self>> {
-        let token =
-            sqlx::query_as(r#"SELECT * FROM user_auth_tokens WHERE token = $1 AND active = true"#)
-                .bind(hash_token(token))
-                .fetch_optional(database)
-                .await?;
+        let token = sqlx::query_as(
+            r#"SELECT * FROM user_auth_tokens WHERE token = $1 AND active = true AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)"#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(database)
+        .await?;
         Ok(token)
     }
+    /// Checks the IP address against the CIDR whitelist.
+    ///
+    /// If the token has a whitelist and the IP address is unknown the token is rejected
+    pub fn allows_ip_address(&self, ip_address: Option<IpAddr>) -> bool {
+        if self.cidr_whitelist.is_empty() {
+            return true;
+        }
+        let Some(ip_address) = ip_address else {
+            return false;
+        };
+        self.cidr_whitelist
+            .iter()
+            .filter_map(|cidr| cidr.parse::<Cidr>().ok())
+            .any(|cidr| cidr.contains(ip_address))
+    }
+    /// Records that the token was used
+    #[instrument(skip(database))]
+    pub async fn update_last_used(
+        id: i32,
+        ip_address: Option<&str>,
+        database: &PgPool,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            r#"UPDATE user_auth_tokens SET last_used_at = CURRENT_TIMESTAMP, last_used_ip = COALESCE($2, last_used_ip) WHERE id = $1"#,
+        )
+        .bind(id)
+        .bind(ip_address)
+        .execute(database)
+        .await?;
+        Ok(())
+    }
+    /// Deactivates all tokens that have expired.
+    ///
+    /// Returns the number of tokens deactivated
+    #[instrument(skip(database))]
+    pub async fn deactivate_expired(database: &PgPool) -> sqlx::Result<u64> {
+        let result = sqlx::query(
+            r#"UPDATE user_auth_tokens SET active = false WHERE active = true AND expires_at IS NOT NULL AND expires_at <= CURRENT_TIMESTAMP"#,
+        )
+        .execute(database)
+        .await?;
+        Ok(result.rows_affected())
+    }
     pub async fn has_scope(&self, scope: NRScope, database: &PgPool) -> sqlx::Result<bool> {
         let can_read: i64 = sqlx::query_scalar(
             r#"SELECT COUNT(id) FROM user_auth_token_scopes WHERE user_auth_token_id = $1 AND scope = $2"#,
@@ -122,6 +176,7 @@ pub struct NewAuthToken {
     pub source: String,
     pub scopes: Vec<NRScope>,
     pub repositories: Vec<(Uuid, Vec<RepositoryActions>)>,
+    pub expires_at: Option<chrono::DateTime<chrono::FixedOffset>>,
 }
 impl NewAuthToken {
     pub async fn insert(self, database: &PgPool) -> sqlx::Result<(i32, String)> {
@@ -133,16 +188,18 @@ impl NewAuthToken {
             source,
             scopes,
             repositories,
+            expires_at,
         } = self;
 
         let token_id: i32 = sqlx::query_scalar(
-            r#"INSERT INTO user_auth_tokens (user_id, name, description, token, source) VALUES ($1, $2, $3, $4, $5) RETURNING id"#,
+            r#"INSERT INTO user_auth_tokens (user_id, name, description, token, source, expires_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id"#,
         )
         .bind(user_id)
         .bind(name)
         .bind(description)
         .bind(hashed_token)
         .bind(source)
+        .bind(expires_at)
         .fetch_one(database)
         .await?;
 
@@ -6,7 +6,7 @@ use uuid::Uuid;
 
 use crate::{database::DateTime, user::permissions::RepositoryActions};
 
-use super::create_token;
+use super::{AuthToken, create_token};
 /// Table Name: user_auth_token_repository_scopes
 /// Represents the actions that can be taken on a repository
 ///
@@ -37,7 +37,34 @@ impl AuthTokenRepositoryScope {
         Ok(scopes)
     }
 }
-
+/// A token with the actions it has on a single repository
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct RepositoryAuthToken {
+    #[sqlx(flatten)]
+    pub token: AuthToken,
+    pub actions: Vec<RepositoryActions>,
+}
+impl RepositoryAuthToken {
+    /// The tokens of the user that have a scope for the repository
+    #[instrument(name = "RepositoryAuthToken::get_by_user_and_repository")]
+    pub async fn get_by_user_and_repository(
+        user_id: i32,
+        repository_id: Uuid,
+        database: &PgPool,
+    ) -> sqlx::Result<Vec<Self>> {
+        let tokens = sqlx::query_as(
+            r#"SELECT user_auth_tokens.*, user_auth_token_repository_scopes.actions FROM user_auth_tokens
+                INNER JOIN user_auth_token_repository_scopes ON user_auth_token_repository_scopes.user_auth_token_id = user_auth_tokens.id
+                WHERE user_auth_tokens.user_id = $1 AND user_auth_token_repository_scopes.repository_id = $2
+                ORDER BY user_auth_tokens.created_at DESC"#,
+        )
+        .bind(user_id)
+        .bind(repository_id)
+        .fetch_all(database)
+        .await?;
+        Ok(tokens)
+    }
+}
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NewRepositoryToken {
     pub user_id: i32,
@@ -45,6 +72,8 @@ pub struct NewRepositoryToken {
 
     pub repositories: Vec<(Uuid, Vec<RepositoryActions>)>,
     pub expires_at: Option<DateTime>,
+    /// CIDR ranges the token can be used from. Empty allows all addresses
+    pub cidr_whitelist: Vec<String>,
 }
 impl NewRepositoryToken {
     pub fn new(
@@ -58,8 +87,13 @@ impl NewRepositoryToken {
             source,
             repositories: vec![(repository, actions)],
             expires_at: None,
+            cidr_whitelist: Vec::new(),
         }
     }
+    pub fn with_cidr_whitelist(mut self, cidr_whitelist: Vec<String>) -> Self {
+        self.cidr_whitelist = cidr_whitelist;
+        self
+    }
     pub fn add_repository(mut self, repository: Uuid, actions: Vec<RepositoryActions>) -> Self {
         self.repositories.push((repository, actions));
         self
@@ -72,14 +106,16 @@ impl NewRepositoryToken {
             source,
             repositories,
             expires_at,
+            cidr_whitelist,
         } = self;
 
         let token_id: i32 =  sqlx::query_scalar(
-            r#"INSERT INTO user_auth_tokens (user_id, token, source, expires_at) VALUES ($1, $2, $3, $4) RETURNING id"#,
+            r#"INSERT INTO user_auth_tokens (user_id, token, source, expires_at, cidr_whitelist) VALUES ($1, $2, $3, $4, $5) RETURNING id"#,
         ).bind(user_id)
         .bind(hashed_token)
         .bind(source)
         .bind(expires_at)
+        .bind(cidr_whitelist)
         .fetch_one(database).await?;
         let span = span!(tracing::Level::DEBUG, "inserting scopes");
         let _guard = span.enter();
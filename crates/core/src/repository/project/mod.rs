@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use pg_extended_sqlx_queries::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -90,8 +92,48 @@ pub struct VersionData {
     pub description: Option<String>,
     pub source: Option<ProjectSource>,
     pub licence: Option<Licence>,
+    /// Where issues for the project are reported
+    pub issues_url: Option<String>,
+    /// The name of the upstream the version was proxied from.
+    ///
+    /// None if the version was published to this repository
+    pub proxied_from: Option<String>,
+    /// Tags set by the publisher. Such as the ones sent with Nitro Deploy
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Variants of the version. Such as the variants in a Gradle Module
+    #[serde(default)]
+    pub variants: Vec<VersionVariant>,
     pub extra: Option<Value>,
 }
+/// A variant of a version. Gradle uses variants for different JVM versions or Kotlin Multiplatform targets
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub struct VersionVariant {
+    pub name: String,
+    /// Attributes used to select the variant. Such as `org.gradle.jvm.version`
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    #[serde(default)]
+    pub capabilities: Vec<VariantCapability>,
+    #[serde(default)]
+    pub files: Vec<VariantFile>,
+    /// The variant is published by another module. The path is relative to this version
+    #[serde(default)]
+    pub available_at: Option<String>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub struct VariantCapability {
+    pub group: String,
+    pub name: String,
+    pub version: Option<String>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub struct VariantFile {
+    pub name: String,
+    /// The path of the file relative to the version
+    pub url: String,
+    pub size: Option<u64>,
+}
 /// Author of the project
 ///
 /// All data is optional as artifact types may not have all the data
@@ -4,6 +4,7 @@ use env_file::EnvFile;
 use sqlx::PgPool;
 use tracing::debug;
 use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
+use uuid::Uuid;
 pub mod env_file;
 pub mod logging;
 use crate::{
@@ -84,6 +85,26 @@ impl TestCore {
             Ok(Some(user.into()))
         }
     }
+    /// Inserts a repository of the type with its own storage. Returns the id of the repository
+    ///
+    /// Only the database rows are created. Nothing is written to the storage
+    pub async fn create_test_repository(&self, repository_type: &str) -> anyhow::Result<Uuid> {
+        let storage_id: Uuid = sqlx::query_scalar(
+            r#"INSERT INTO storages (storage_type, name, config) VALUES ('Local', $1, '{}') RETURNING id"#,
+        )
+        .bind(format!("storage-{}", Uuid::new_v4().simple()))
+        .fetch_one(&self.db)
+        .await?;
+        let repository_id = sqlx::query_scalar(
+            r#"INSERT INTO repositories (storage_id, name, repository_type) VALUES ($1, $2, $3) RETURNING id"#,
+        )
+        .bind(storage_id)
+        .bind(format!("repo-{}", Uuid::new_v4().simple()))
+        .bind(repository_type)
+        .fetch_one(&self.db)
+        .await?;
+        Ok(repository_id)
+    }
 }
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct TestInfoEntry {
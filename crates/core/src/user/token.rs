@@ -13,6 +13,12 @@ pub struct AuthTokenResponse {
     pub active: bool,
     pub source: String,
     pub expires_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    /// The last time the token was used to authenticate a request
+    pub last_used_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    /// The IP address of the last request that used the token
+    pub last_used_ip: Option<String>,
+    /// CIDR ranges the token can be used from. Empty allows all addresses
+    pub cidr_whitelist: Vec<String>,
     pub created_at: chrono::DateTime<chrono::FixedOffset>,
 }
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
@@ -29,7 +35,7 @@ impl AuthTokenFullResponse {
         database: &sqlx::PgPool,
     ) -> Result<Option<AuthTokenFullResponse>, sqlx::Error> {
         let Some(base) = sqlx::query_as::<_, AuthTokenResponse>(
-            r#"SELECT * FROM user_auth_tokens WHERE id = $1 user_id = $2"#,
+            r#"SELECT * FROM user_auth_tokens WHERE id = $1 AND user_id = $2"#,
         )
         .bind(id)
         .bind(user_id)
@@ -73,7 +79,7 @@ impl AuthTokenFullResponse {
         database: &sqlx::PgPool,
     ) -> Result<Vec<AuthTokenFullResponse>, sqlx::Error> {
         let tokens = sqlx::query_as::<_, AuthTokenResponse>(
-            r#"SELECT * FROM user_auth_tokens WHERE user_id = $1"#,
+            r#"SELECT * FROM user_auth_tokens WHERE user_id = $1 ORDER BY created_at DESC"#,
         )
         .bind(user_id)
         .fetch_all(database)
@@ -1,3 +1,4 @@
+pub mod cidr;
 pub mod time;
 pub mod utopia;
 pub mod base64_utils {
@@ -0,0 +1,136 @@
+//! CIDR ranges such as `192.168.1.0/24` or `2001:db8::/32`. Used to restrict where auth tokens can be used from
+use std::{
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use thiserror::Error;
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum InvalidCidr {
+    #[error("Invalid IP address in CIDR `{0}`")]
+    InvalidAddress(String),
+    #[error("Invalid prefix length in CIDR `{0}`")]
+    InvalidPrefix(String),
+}
+/// An IP address and the number of leading bits that must match.
+///
+/// An address without a prefix length is a range that only contains itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    address: IpAddr,
+    prefix: u8,
+}
+impl Cidr {
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+    /// IPv4 mapped IPv6 addresses (`::ffff:10.0.0.1`) are compared as IPv4
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6
+                .to_ipv4_mapped()
+                .map(IpAddr::V4)
+                .unwrap_or(IpAddr::V6(v6)),
+            v4 => v4,
+        };
+        match (self.address, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+impl FromStr for Cidr {
+    type Err = InvalidCidr;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value, None),
+        };
+        let address =
+            IpAddr::from_str(address).map_err(|_| InvalidCidr::InvalidAddress(value.to_owned()))?;
+        let max_prefix = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= max_prefix)
+                .ok_or_else(|| InvalidCidr::InvalidPrefix(value.to_owned()))?,
+            None => max_prefix,
+        };
+        Ok(Self { address, prefix })
+    }
+}
+impl Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+impl From<Ipv4Addr> for Cidr {
+    fn from(address: Ipv4Addr) -> Self {
+        Self {
+            address: IpAddr::V4(address),
+            prefix: 32,
+        }
+    }
+}
+impl From<Ipv6Addr> for Cidr {
+    fn from(address: Ipv6Addr) -> Self {
+        Self {
+            address: IpAddr::V6(address),
+            prefix: 128,
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+    #[test]
+    fn parse() {
+        let cidr: Cidr = "192.168.1.0/24".parse().unwrap();
+        assert_eq!(cidr.prefix(), 24);
+        assert_eq!(cidr.to_string(), "192.168.1.0/24");
+        assert_eq!("10.0.0.1".parse::<Cidr>().unwrap().prefix(), 32);
+        assert_eq!("2001:db8::/32".parse::<Cidr>().unwrap().prefix(), 32);
+        assert!("192.168.1.0/33".parse::<Cidr>().is_err());
+        assert!("192.168.1/24".parse::<Cidr>().is_err());
+        assert!("192.168.1.0/".parse::<Cidr>().is_err());
+    }
+    #[test]
+    fn contains() {
+        let cidr: Cidr = "192.168.1.0/24".parse().unwrap();
+        assert!(cidr.contains(ip("192.168.1.42")));
+        assert!(cidr.contains(ip("::ffff:192.168.1.42")));
+        assert!(!cidr.contains(ip("192.168.2.1")));
+        assert!(!cidr.contains(ip("2001:db8::1")));
+
+        let all: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains(ip("8.8.8.8")));
+
+        let single: Cidr = "10.0.0.1".parse().unwrap();
+        assert!(single.contains(ip("10.0.0.1")));
+        assert!(!single.contains(ip("10.0.0.2")));
+
+        let v6: Cidr = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(ip("2001:db8:1::1")));
+        assert!(!v6.contains(ip("2001:db9::1")));
+    }
+}
@@ -2,7 +2,7 @@ use nr_core::storage::StoragePath;
 use uuid::Uuid;
 
 use crate::{
-    FileContent, FileType, Storage, StorageError, StorageFactory, StorageTypeConfig,
+    FileContent, FileRange, FileType, Storage, StorageError, StorageFactory, StorageTypeConfig,
     local::{LocalStorage, LocalStorageFactory},
     meta::RepositoryMeta,
     s3::{S3Storage, S3StorageFactory},
@@ -105,6 +105,24 @@ impl Storage for DynStorage {
         }
     }
 
+    async fn open_file_range(
+        &self,
+        repository: Uuid,
+        location: &StoragePath,
+        range: FileRange,
+    ) -> Result<Option<crate::StorageFile>, StorageError> {
+        match self {
+            DynStorage::Local(storage) => storage
+                .open_file_range(repository, location, range)
+                .await
+                .map_err(Into::into),
+            DynStorage::S3(storage) => storage
+                .open_file_range(repository, location, range)
+                .await
+                .map_err(Into::into),
+        }
+    }
+
     async fn validate_config_change(&self, config: StorageTypeConfig) -> Result<(), StorageError> {
         match self {
             DynStorage::Local(storage) => storage
@@ -32,13 +32,35 @@ pub fn is_hidden_file(path: &Path) -> bool {
     }
 }
 
+/// Generates the hashes for a file without reading the entire file into memory.
 pub fn generate_hashes_from_path(path: impl AsRef<Path>) -> Result<FileHashes, io::Error> {
-    let mut buffer = Vec::new();
-    {
-        let mut file = std::fs::File::open(path)?;
-        file.read_to_end(&mut buffer)?;
+    use md5::Md5;
+    use sha1::Sha1;
+    use sha2::Sha256;
+    use sha3::Sha3_256;
+    let mut file = std::fs::File::open(path)?;
+    let mut md5 = Md5::new();
+    let mut sha1 = Sha1::new();
+    let mut sha2_256 = Sha256::new();
+    let mut sha3_256 = Sha3_256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &buffer[..read];
+        md5.update(chunk);
+        sha1.update(chunk);
+        sha2_256.update(chunk);
+        sha3_256.update(chunk);
     }
-    Ok(generate_from_bytes(&buffer))
+    Ok(FileHashes {
+        md5: Some(base64_utils::encode(md5.finalize())),
+        sha1: Some(base64_utils::encode(sha1.finalize())),
+        sha2_256: Some(base64_utils::encode(sha2_256.finalize())),
+        sha3_256: Some(base64_utils::encode(sha3_256.finalize())),
+    })
 }
 #[instrument(skip(buffer))]
 pub fn generate_from_bytes(buffer: &[u8]) -> FileHashes {
@@ -340,6 +362,16 @@ mod tests {
         meta
     }
     #[test]
+    pub fn hashes_from_path_match_bytes() {
+        let content: Vec<u8> = (0..200_000u32).map(|v| v as u8).collect();
+        let path = std::env::temp_dir().join(format!("{}.hash_test", Uuid::new_v4()));
+        std::fs::write(&path, &content).unwrap();
+        let from_path = super::generate_hashes_from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(from_path, super::generate_from_bytes(&content));
+    }
+    #[test]
     pub fn post_card_compatible_meta_directory() {
         let meta = LocationMeta {
             created: chrono::Local::now().fixed_offset(),
@@ -2,6 +2,7 @@ mod content;
 mod err;
 mod file;
 mod file_meta;
+mod range;
 
 pub(crate) mod path;
 pub(crate) mod utils;
@@ -9,6 +10,7 @@ pub use content::*;
 pub use file::*;
 pub use file_meta::*;
 pub use path::{ExtensionError, ParentDirectoryDoesNotExist};
+pub use range::FileRange;
 mod file_reader;
 pub use file_reader::*;
 use nr_core::storage::StoragePath;
@@ -0,0 +1,22 @@
+/// A range of bytes within a file.
+///
+/// Both `start` and `end` are inclusive. Matching the HTTP `Range` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileRange {
+    pub start: u64,
+    pub end: u64,
+}
+impl FileRange {
+    /// Returns None if `start` is greater than `end`
+    pub fn new(start: u64, end: u64) -> Option<Self> {
+        if start > end {
+            return None;
+        }
+        Some(Self { start, end })
+    }
+    /// The number of bytes within the range
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
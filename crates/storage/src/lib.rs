@@ -80,6 +80,17 @@ pub trait Storage: Send + Sync {
         repository: Uuid,
         location: &StoragePath,
     ) -> impl Future<Output = Result<Option<StorageFile>, Self::Error>> + Send;
+    /// Gets the File Information and only the content within the range
+    ///
+    /// The meta data describes the entire file. The range must be within the file.
+    ///
+    /// If the location is a directory the result is the same as [Storage::open_file]
+    fn open_file_range(
+        &self,
+        repository: Uuid,
+        location: &StoragePath,
+        range: FileRange,
+    ) -> impl Future<Output = Result<Option<StorageFile>, Self::Error>> + Send;
     /// Streams a list of files in a directory
     ///
     /// # Note
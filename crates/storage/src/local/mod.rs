@@ -1,6 +1,6 @@
 use std::{
     fs::{self},
-    io::{self, ErrorKind},
+    io::{self, ErrorKind, SeekFrom},
     ops::Deref,
     path::PathBuf,
     sync::Arc,
@@ -11,7 +11,11 @@ mod stream;
 use error::LocalStorageError;
 use nr_core::storage::StoragePath;
 use serde::{Deserialize, Serialize};
-use tokio::{sync::Mutex, task::JoinSet};
+use tokio::{
+    io::{AsyncReadExt, AsyncSeekExt},
+    sync::Mutex,
+    task::JoinSet,
+};
 use tracing::{
     Level, Span, debug, debug_span, error, event,
     field::{Empty, debug},
@@ -190,6 +194,21 @@ impl LocalStorageInner {
             content: StorageFileReader::from(file),
         })
     }
+    /// Opens the file and seeks to the start of the range.
+    #[instrument]
+    pub async fn open_file_range(
+        &self,
+        path: PathBuf,
+        range: FileRange,
+    ) -> Result<StorageFile, LocalStorageError> {
+        let meta = StorageFileMeta::read_from_file(&path)?;
+        let mut file = tokio::fs::File::open(&path).await?;
+        file.seek(SeekFrom::Start(range.start)).await?;
+        Ok(StorageFile::File {
+            meta,
+            content: StorageFileReader::AsyncReader(Box::pin(file.take(range.len()))),
+        })
+    }
     #[instrument(skip(path), fields(entries.read, entries.skipped))]
     pub async fn open_folder(&self, path: PathBuf) -> Result<StorageFile, LocalStorageError> {
         let mut set = JoinSet::<Result<StorageFileMeta<FileType>, LocalStorageError>>::new();
@@ -462,6 +481,32 @@ impl Storage for LocalStorage {
         ),
         skip(self)
     )]
+    async fn open_file_range(
+        &self,
+        repository: Uuid,
+        location: &StoragePath,
+        range: FileRange,
+    ) -> Result<Option<StorageFile>, LocalStorageError> {
+        let path = self.get_path(&repository, location);
+        if !path.exists() {
+            debug!(?path, "File does not exist");
+            return Ok(None);
+        }
+        let file = if path.is_dir() {
+            self.open_folder(path).await?
+        } else {
+            self.0.open_file_range(path, range).await?
+        };
+        Ok(Some(file))
+    }
+    #[instrument(
+        fields(
+            storage.type = "local",
+            storage.id = %self.storage_config.storage_id,
+            storage.config = ?self.config,
+        ),
+        skip(self)
+    )]
     async fn unload(&self) -> Result<(), LocalStorageError> {
         info!(?self, "Unloading Local Storage");
         let shutdown_signal = self.0.shutdown_signal.lock().await.take();
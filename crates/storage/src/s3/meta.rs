@@ -0,0 +1,85 @@
+use chrono::{DateTime, FixedOffset, Local};
+use nr_core::storage::FileHashes;
+use serde::{Deserialize, Serialize};
+
+use crate::{NITRO_REPO_META_EXTENSION, NITRO_REPO_META_FILE, meta::RepositoryMeta};
+
+/// The S3 equivalent of [LocationMeta](crate::LocationMeta).
+///
+/// Stored as a hidden sidecar object next to the object it describes.
+/// - Files: `{key}.nr-meta`
+/// - Directories: `{prefix}/.nr-meta`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct S3LocationMeta {
+    pub created: DateTime<FixedOffset>,
+    pub modified: DateTime<FixedOffset>,
+    /// None if the location is a directory
+    pub hashes: Option<FileHashes>,
+    pub repository_meta: RepositoryMeta,
+}
+impl Default for S3LocationMeta {
+    fn default() -> Self {
+        let now = Local::now().fixed_offset();
+        Self {
+            created: now,
+            modified: now,
+            hashes: None,
+            repository_meta: RepositoryMeta::default(),
+        }
+    }
+}
+impl S3LocationMeta {
+    pub fn new_file(hashes: FileHashes) -> Self {
+        Self {
+            hashes: Some(hashes),
+            ..Default::default()
+        }
+    }
+}
+/// The key of the sidecar object for a file
+pub fn file_meta_key(key: &str) -> String {
+    format!("{}.{}", key, NITRO_REPO_META_EXTENSION)
+}
+/// The key of the sidecar object for a directory
+///
+/// The prefix may or may not end with a `/`
+pub fn directory_meta_key(prefix: &str) -> String {
+    format!("{}/{}", prefix.trim_end_matches('/'), NITRO_REPO_META_FILE)
+}
+/// Checks if the key is a sidecar object. These are never returned in listings.
+pub fn is_hidden_key(key: &str) -> bool {
+    let file_name = key.rsplit('/').next().unwrap_or(key);
+    file_name == NITRO_REPO_META_FILE
+        || file_name
+            .rsplit_once('.')
+            .is_some_and(|(_, extension)| extension == NITRO_REPO_META_EXTENSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    pub fn hidden_keys() {
+        assert!(is_hidden_key("repo/a/b.jar.nr-meta"));
+        assert!(is_hidden_key("repo/a/.nr-meta"));
+        assert!(!is_hidden_key("repo/a/b.jar"));
+        assert!(!is_hidden_key("repo/nr-meta/b.jar"));
+        assert_eq!(file_meta_key("repo/a/b.jar"), "repo/a/b.jar.nr-meta");
+        assert_eq!(directory_meta_key("repo/a/"), "repo/a/.nr-meta");
+        assert_eq!(directory_meta_key("repo/a"), "repo/a/.nr-meta");
+    }
+    #[test]
+    pub fn post_card_compatible_meta() {
+        let mut meta = S3LocationMeta::new_file(FileHashes {
+            md5: Some("md5".to_string()),
+            sha1: Some("sha1".to_string()),
+            sha2_256: Some("sha2_256".to_string()),
+            sha3_256: Some("sha3_256".to_string()),
+        });
+        meta.repository_meta.insert("test", "map");
+
+        let bytes = postcard::to_allocvec(&meta).unwrap();
+        let from_bytes: S3LocationMeta = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(meta, from_bytes);
+    }
+}
@@ -1,7 +1,9 @@
-use std::{borrow::Cow, ops::Deref, str::FromStr, sync::Arc};
+use std::{borrow::Cow, io, ops::Deref, str::FromStr, sync::Arc};
 
-use chrono::Local;
-use futures::future::BoxFuture;
+use bytes::Bytes;
+use chrono::{DateTime, Local};
+use futures::{StreamExt, TryStreamExt, future::BoxFuture};
+use meta::{S3LocationMeta, is_hidden_key};
 use mime::Mime;
 use nr_core::storage::{FileHashes, SerdeMime, StoragePath};
 use regions::{CustomRegion, S3StorageRegion};
@@ -9,14 +11,21 @@ use s3::{
     Bucket, Region, Tag,
     creds::{Credentials, Rfc3339OffsetDateTime},
     error::S3Error,
-    serde_types::ListBucketResult,
+    serde_types::{HeadObjectResult, ListBucketResult},
 };
 
+pub mod meta;
 pub mod regions;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::StreamReader;
 use tracing::{debug, error, info, instrument, warn};
 use utoipa::ToSchema;
+mod stream;
 pub mod tags;
+pub use stream::S3DirectoryListStream;
+use stream::{RangeChunk, RangeChunkWriter};
 use uuid::Uuid;
 #[derive(Debug, thiserror::Error)]
 pub enum S3StorageError {
@@ -38,21 +47,29 @@ pub enum S3StorageError {
 
     #[error("Missing Tag: {0}")]
     MissingTag(Cow<'static, str>),
+    #[error("Metadata Error {0}")]
+    Postcard(#[from] postcard::Error),
 
     #[error(transparent)]
     PathCollision(#[from] PathCollisionError),
 }
+/// Bodies larger than this are uploaded with a multipart upload.
+///
+/// Files saved from a path are always uploaded with a multipart upload.
+pub const MULTIPART_UPLOAD_THRESHOLD: usize = 8 * 1024 * 1024;
+/// The number of chunks buffered between the S3 connection and the response for ranged reads.
+const RANGE_CHANNEL_CAPACITY: usize = 16;
 impl S3StorageError {
     pub fn static_missing_tag(tag: &'static str) -> Self {
         S3StorageError::MissingTag(tag.into())
     }
 }
 use crate::{
-    BorrowedStorageConfig, BorrowedStorageTypeConfig, DirectoryFileType, DynStorage, FileContent,
-    FileContentBytes, FileFileType, FileType, InvalidConfigType, PathCollisionError,
-    StaticStorageFactory, Storage, StorageConfig, StorageConfigInner, StorageError, StorageFactory,
-    StorageFile, StorageFileMeta, StorageTypeConfig, StorageTypeConfigTrait, meta::RepositoryMeta,
-    streaming::VecDirectoryListStream, utils::new_type_arc_type,
+    BorrowedStorageConfig, BorrowedStorageTypeConfig, DirectoryFileType, DynStorage,
+    FILE_META_MIME, FileContent, FileContentBytes, FileFileType, FileRange, FileType,
+    InvalidConfigType, PathCollisionError, StaticStorageFactory, Storage, StorageConfig,
+    StorageConfigInner, StorageError, StorageFactory, StorageFile, StorageFileMeta,
+    StorageTypeConfig, StorageTypeConfigTrait, meta::RepositoryMeta, utils::new_type_arc_type,
 };
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 pub struct S3Credentials {
@@ -144,9 +161,18 @@ impl S3StorageInner {
 
         Ok(bucket)
     }
+    /// The key of the object. Trailing slashes are removed.
     pub fn s3_path(&self, repository: &Uuid, path: &StoragePath) -> String {
-        format!("{}/{}", repository, path)
+        let path = path.to_string();
+        format!("{}/{}", repository, path.trim_end_matches('/'))
     }
+    /// The prefix used to list the contents of a directory. Always ends with a `/`
+    pub fn s3_directory_prefix(key: &str) -> String {
+        format!("{}/", key.trim_end_matches('/'))
+    }
+    /// Gets the key for a file to be created.
+    ///
+    /// S3 does not have directories. So the only thing to check is that no parent of the path is an object.
     pub async fn get_path_for_creation(
         &self,
         repository: Uuid,
@@ -154,11 +180,12 @@ impl S3StorageInner {
     ) -> Result<String, S3StorageError> {
         let mut path = repository.to_string();
         let mut conflicting_path = StoragePath::default();
-        for part in location.clone().into_iter() {
+        let mut iter = location.clone().into_iter().peekable();
+        while let Some(part) = iter.next() {
             path.push('/');
             path.push_str(part.as_ref());
             conflicting_path.push_mut(part.as_ref());
-            if !self.is_directory(&path).await? {
+            if iter.peek().is_some() && self.does_path_exist(&path).await? {
                 return Err(PathCollisionError {
                     path: location.clone(),
                     conflicts_with: conflicting_path,
@@ -170,136 +197,218 @@ impl S3StorageInner {
     }
     #[instrument]
     async fn does_path_exist(&self, path: &str) -> Result<bool, S3StorageError> {
-        match self.bucket.head_object(path).await.map(|(_, code)| code) {
-            Ok(code) => Ok(code == 200),
-            Err(S3Error::HttpFailWithBody(code, _)) => {
-                if code == 404 {
-                    Ok(false)
-                } else {
-                    Err(S3StorageError::UnexpectedStatusCode {
-                        expected: 200,
-                        got: code,
-                    })
-                }
+        Ok(self.head_object(path).await?.is_some())
+    }
+    /// Returns None if the object does not exist
+    #[instrument]
+    async fn head_object(&self, path: &str) -> Result<Option<HeadObjectResult>, S3StorageError> {
+        match self.bucket.head_object(path).await {
+            Ok((result, 200)) => Ok(Some(result)),
+            Ok((_, 404)) | Err(S3Error::HttpFailWithBody(404, _)) => Ok(None),
+            Ok((_, code)) | Err(S3Error::HttpFailWithBody(code, _)) => {
+                Err(S3StorageError::UnexpectedStatusCode {
+                    expected: 200,
+                    got: code,
+                })
             }
             Err(e) => Err(e.into()),
         }
     }
+    /// Requests a single page of a delimited listing.
     #[instrument]
-    fn is_directory_from_result<'result>(
+    pub(crate) async fn list_page(
         &self,
-        result: &'result ListBucketResult,
-        path: &str,
-    ) -> (bool, Option<&'result str>) {
-        if result.contents.is_empty() && result.common_prefixes.is_none() {
-            return (true, None);
-        }
-        if path.ends_with("/") && !result.contents.is_empty() {
-            return (true, None);
+        prefix: String,
+        continuation_token: Option<String>,
+    ) -> Result<ListBucketResult, S3StorageError> {
+        let (result, status_code) = self
+            .bucket
+            .list_page(prefix, Some("/".to_owned()), continuation_token, None, None)
+            .await?;
+        if status_code != 200 {
+            return Err(S3StorageError::UnexpectedStatusCode {
+                expected: 200,
+                got: status_code,
+            });
         }
-        let path_with_slash = format!("{}/", path);
-
-        if let Some(common) = &result.common_prefixes {
-            if let Some(directory) = common
-                .iter()
-                .find(|prefix| prefix.prefix == path_with_slash)
-            {
-                return (true, Some(directory.prefix.as_str()));
+        Ok(result)
+    }
+    /// Lists every key under the prefix. Including hidden keys and keys in sub directories.
+    #[instrument]
+    async fn list_all_keys(&self, prefix: String) -> Result<Vec<String>, S3StorageError> {
+        let pages = self.bucket.list(prefix, None).await?;
+        Ok(pages
+            .into_iter()
+            .flat_map(|page| page.contents.into_iter().map(|object| object.key))
+            .collect())
+    }
+    /// Reads the sidecar meta object.
+    ///
+    /// Returns None if the meta object does not exist or is corrupted.
+    #[instrument]
+    async fn read_location_meta(
+        &self,
+        meta_key: &str,
+    ) -> Result<Option<S3LocationMeta>, S3StorageError> {
+        let response = match self.bucket.get_object(meta_key).await {
+            Ok(response) if response.status_code() == 404 => return Ok(None),
+            Ok(response) if response.status_code() != 200 => {
+                return Err(S3StorageError::UnexpectedStatusCode {
+                    expected: 200,
+                    got: response.status_code(),
+                });
+            }
+            Ok(response) => response,
+            Err(S3Error::HttpFailWithBody(404, _)) => return Ok(None),
+            Err(S3Error::HttpFailWithBody(code, _)) => {
+                return Err(S3StorageError::UnexpectedStatusCode {
+                    expected: 200,
+                    got: code,
+                });
+            }
+            Err(e) => return Err(e.into()),
+        };
+        match postcard::from_bytes(response.as_slice()) {
+            Ok(meta) => Ok(Some(meta)),
+            Err(err) => {
+                error!(?err, ?meta_key, "Meta Object is corrupted. Ignoring");
+                Ok(None)
             }
         }
-        (false, None)
     }
-
-    #[instrument]
-    async fn is_directory(&self, path: &str) -> Result<bool, S3StorageError> {
-        let list = self
+    #[instrument(skip(meta))]
+    async fn write_location_meta(
+        &self,
+        meta_key: &str,
+        meta: &S3LocationMeta,
+    ) -> Result<(), S3StorageError> {
+        let bytes = postcard::to_allocvec(meta)?;
+        let response = self
             .bucket
-            .list(path.to_owned(), Some("/".to_owned()))
+            .put_object_with_content_type(meta_key, &bytes, FILE_META_MIME.as_ref())
             .await?;
-        let Some(first) = list.first() else {
-            return Ok(false);
-        };
-
-        Ok(self.is_directory_from_result(first, path).0)
+        if response.status_code() != 200 {
+            return Err(S3StorageError::UnexpectedStatusCode {
+                expected: 200,
+                got: response.status_code(),
+            });
+        }
+        Ok(())
     }
-
-    /// Returns None if the path is not a directory
     #[instrument]
-    async fn index_directory(&self, path: &str) -> Result<Option<StorageFile>, S3StorageError> {
-        let path = if !path.ends_with("/") {
-            format!("{}/", path)
-        } else {
-            path.to_owned()
-        };
-
-        let mut list = self.bucket.list(path.clone(), Some("/".to_owned())).await?;
-
-        if list.is_empty() {
-            return Ok(None);
-        }
-        let first = list.remove(0);
-
-        let mut files = Vec::new();
-        for file in first.contents {
-            let meta = self.get_meta(&file.key).await?;
-            if let Some(meta) = meta {
-                files.push(meta);
-            }
+    async fn delete_object(&self, path: &str) -> Result<(), S3StorageError> {
+        let response_data = self.bucket.delete_object(path).await?;
+        debug!(?response_data, "Object Deleted");
+        if response_data.status_code() != 204 {
+            return Err(S3StorageError::UnexpectedStatusCode {
+                expected: 204,
+                got: response_data.status_code(),
+            });
         }
-        if let Some(common_prefixes) = first.common_prefixes {
-            for sub_directory in common_prefixes {
-                let meta = self.get_directory_meta(&sub_directory.prefix).await?;
-                if let Some(meta) = meta {
-                    files.push(meta);
+        Ok(())
+    }
+    /// Builds the file meta from the response of a HEAD request and the sidecar meta object.
+    async fn file_meta(
+        &self,
+        key: &str,
+        head: HeadObjectResult,
+    ) -> Result<StorageFileMeta<FileFileType>, S3StorageError> {
+        let last_modified = head
+            .last_modified
+            .as_deref()
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+        let (created, modified, file_hash) =
+            match self.read_location_meta(&meta::file_meta_key(key)).await? {
+                Some(meta) => (meta.created, meta.modified, meta.hashes.unwrap_or_default()),
+                None => {
+                    let modified = last_modified.unwrap_or_else(|| Local::now().fixed_offset());
+                    (modified, modified, FileHashes::default())
                 }
-            }
-        }
-
-        Ok(Some(StorageFile::Directory {
-            meta: StorageFileMeta {
-                name: path.to_owned(),
-                file_type: DirectoryFileType {
-                    file_count: files.len() as u64,
-                },
-                modified: Local::now().fixed_offset(),
-                created: Local::now().fixed_offset(),
+            };
+        let mime_type = head
+            .content_type
+            .as_deref()
+            .and_then(|mime| Mime::from_str(mime).ok())
+            .unwrap_or_else(|| mime_guess::from_path(key).first_or_octet_stream());
+        Ok(StorageFileMeta {
+            name: stream::key_file_name(key).to_owned(),
+            file_type: FileFileType {
+                file_size: head.content_length.unwrap_or_default().max(0) as u64,
+                mime_type: Some(SerdeMime::from(mime_type)),
+                file_hash,
             },
-            files,
-        }))
+            modified,
+            created,
+        })
     }
-    async fn get_meta(
+    /// Builds the directory meta from the first page of the listing and the sidecar meta object.
+    async fn directory_meta(
         &self,
-        path: &str,
-    ) -> Result<Option<StorageFileMeta<FileType>>, S3StorageError> {
-        let file_file = FileType::File(FileFileType {
-            file_size: 0,
-            mime_type: None,
-            file_hash: FileHashes::default(),
-        });
-
-        let meta = StorageFileMeta {
-            name: path.to_owned(),
-            file_type: file_file,
-            modified: Local::now().fixed_offset(),
-            created: Local::now().fixed_offset(),
-        };
-
-        Ok(Some(meta))
+        key: &str,
+        first_page: &ListBucketResult,
+    ) -> Result<StorageFileMeta<DirectoryFileType>, S3StorageError> {
+        let file_count = first_page
+            .contents
+            .iter()
+            .filter(|object| !is_hidden_key(&object.key) && !object.key.ends_with('/'))
+            .count()
+            + first_page.common_prefixes.as_ref().map_or(0, Vec::len);
+        let meta = self
+            .read_location_meta(&meta::directory_meta_key(key))
+            .await?
+            .unwrap_or_default();
+        Ok(StorageFileMeta {
+            name: stream::key_file_name(key).to_owned(),
+            file_type: DirectoryFileType {
+                file_count: file_count as u64,
+            },
+            modified: meta.modified,
+            created: meta.created,
+        })
     }
-    async fn get_directory_meta(
+    /// Returns None if the path is not a directory
+    #[instrument]
+    async fn first_directory_page(
         &self,
-        path: &str,
-    ) -> Result<Option<StorageFileMeta<FileType>>, S3StorageError> {
-        let file_file = FileType::Directory(DirectoryFileType { file_count: 0 });
+        key: &str,
+    ) -> Result<Option<ListBucketResult>, S3StorageError> {
+        let page = self.list_page(Self::s3_directory_prefix(key), None).await?;
+        let is_empty = page
+            .contents
+            .iter()
+            .all(|object| is_hidden_key(&object.key))
+            && page.common_prefixes.as_ref().is_none_or(Vec::is_empty);
+        if is_empty {
+            return Ok(None);
+        }
+        Ok(Some(page))
+    }
 
-        let meta = StorageFileMeta {
-            name: path.to_owned(),
-            file_type: file_file,
-            modified: Local::now().fixed_offset(),
-            created: Local::now().fixed_offset(),
+    /// Returns None if the path is not a directory
+    #[instrument]
+    async fn index_directory(&self, key: &str) -> Result<Option<StorageFile>, S3StorageError> {
+        let Some(first_page) = self.first_directory_page(key).await? else {
+            return Ok(None);
         };
+        let mut continuation_token = first_page
+            .is_truncated
+            .then(|| first_page.next_continuation_token.clone())
+            .flatten();
+        let mut meta = self.directory_meta(key, &first_page).await?;
+        let mut files = stream::page_entries(first_page);
+        while let Some(token) = continuation_token.take() {
+            let page = self
+                .list_page(Self::s3_directory_prefix(key), Some(token))
+                .await?;
+            continuation_token = page
+                .is_truncated
+                .then(|| page.next_continuation_token.clone())
+                .flatten();
+            files.extend(stream::page_entries(page));
+        }
+        meta.file_type.file_count = files.len() as u64;
 
-        Ok(Some(meta))
+        Ok(Some(StorageFile::Directory { meta, files }))
     }
     #[instrument]
     async fn get_object_tagging(&self, path: &str) -> Result<Option<Vec<Tag>>, S3StorageError> {
@@ -353,7 +462,7 @@ pub struct S3Storage(Arc<S3StorageInner>);
 new_type_arc_type!(S3Storage(S3StorageInner));
 impl Storage for S3Storage {
     type Error = S3StorageError;
-    type DirectoryStream = VecDirectoryListStream;
+    type DirectoryStream = S3DirectoryListStream;
     fn storage_type_name(&self) -> &'static str {
         "s3"
     }
@@ -382,19 +491,59 @@ impl Storage for S3Storage {
             debug!("File already exists, overwriting");
         }
         let content_type = if location.is_directory() {
-            "application/x-directory"
+            "application/x-directory".to_owned()
         } else {
-            "application/octet-stream"
+            mime_guess::from_path(&path)
+                .first_or_octet_stream()
+                .to_string()
         };
-        let file_as_bytes: FileContentBytes = file.try_into()?;
-
-        let response_data = self
-            .bucket
-            .put_object_with_content_type(path, file_as_bytes.as_ref(), content_type)
-            .await?;
-        debug!(?response_data, "File Saved");
-        // TODO: Check if the file was created
-        Ok((file_as_bytes.len(), !already_exists))
+        let hashes = file.generate_hashes()?;
+        let (bytes_written, status_code) = match file {
+            FileContent::Path(file_path) => {
+                // Multipart upload. Parts are read from the file as they are uploaded
+                let mut reader = tokio::fs::File::open(file_path).await?;
+                let response = self
+                    .bucket
+                    .put_object_stream_with_content_type(&mut reader, &path, &content_type)
+                    .await?;
+                (response.uploaded_bytes(), response.status_code())
+            }
+            content => {
+                let file_as_bytes: FileContentBytes = content.try_into()?;
+                if file_as_bytes.len() > MULTIPART_UPLOAD_THRESHOLD {
+                    let mut reader = file_as_bytes.as_ref();
+                    let response = self
+                        .bucket
+                        .put_object_stream_with_content_type(&mut reader, &path, &content_type)
+                        .await?;
+                    (response.uploaded_bytes(), response.status_code())
+                } else {
+                    let response_data = self
+                        .bucket
+                        .put_object_with_content_type(&path, file_as_bytes.as_ref(), &content_type)
+                        .await?;
+                    (file_as_bytes.len(), response_data.status_code())
+                }
+            }
+        };
+        debug!(?bytes_written, ?status_code, "File Saved");
+        if status_code != 200 {
+            return Err(S3StorageError::UnexpectedStatusCode {
+                expected: 200,
+                got: status_code,
+            });
+        }
+        let meta_key = meta::file_meta_key(&path);
+        let location_meta = match self.read_location_meta(&meta_key).await? {
+            Some(mut meta) if already_exists => {
+                meta.modified = Local::now().fixed_offset();
+                meta.hashes = Some(hashes);
+                meta
+            }
+            _ => S3LocationMeta::new_file(hashes),
+        };
+        self.write_location_meta(&meta_key, &location_meta).await?;
+        Ok((bytes_written, !already_exists))
     }
     #[instrument(name = "Storage::put_repository_meta", fields(storage_type = "s3"))]
     async fn put_repository_meta(
@@ -403,7 +552,23 @@ impl Storage for S3Storage {
         location: &StoragePath,
         value: RepositoryMeta,
     ) -> Result<(), S3StorageError> {
-        todo!()
+        let path = self.s3_path(&repository, location);
+        let meta_key = if self.does_path_exist(&path).await? {
+            meta::file_meta_key(&path)
+        } else if self.first_directory_page(&path).await?.is_some() {
+            meta::directory_meta_key(&path)
+        } else {
+            return Err(S3StorageError::IOError(io::Error::new(
+                io::ErrorKind::NotFound,
+                "File not found",
+            )));
+        };
+        let mut meta = self
+            .read_location_meta(&meta_key)
+            .await?
+            .unwrap_or_default();
+        meta.repository_meta = value;
+        self.write_location_meta(&meta_key, &meta).await
     }
     #[instrument(name = "Storage::get_repository_meta", fields(storage_type = "s3"))]
     async fn get_repository_meta(
@@ -411,7 +576,20 @@ impl Storage for S3Storage {
         repository: uuid::Uuid,
         location: &StoragePath,
     ) -> Result<Option<RepositoryMeta>, S3StorageError> {
-        todo!()
+        let path = self.s3_path(&repository, location);
+        let meta_key = if self.does_path_exist(&path).await? {
+            meta::file_meta_key(&path)
+        } else if self.first_directory_page(&path).await?.is_some() {
+            meta::directory_meta_key(&path)
+        } else {
+            return Ok(None);
+        };
+        let meta = self
+            .read_location_meta(&meta_key)
+            .await?
+            .map(|meta| meta.repository_meta)
+            .unwrap_or_default();
+        Ok(Some(meta))
     }
     #[instrument(name = "Storage::delete_file", fields(storage_type = "s3"))]
     async fn delete_file(
@@ -420,17 +598,23 @@ impl Storage for S3Storage {
         location: &StoragePath,
     ) -> Result<bool, S3StorageError> {
         let path = self.s3_path(&repository, location);
-        let exists = self.does_path_exist(&path).await?;
-        if !exists {
+        if self.does_path_exist(&path).await? {
+            self.delete_object(&path).await?;
+            let meta_key = meta::file_meta_key(&path);
+            if self.does_path_exist(&meta_key).await? {
+                self.delete_object(&meta_key).await?;
+            }
+            return Ok(true);
+        }
+        let keys = self
+            .list_all_keys(S3StorageInner::s3_directory_prefix(&path))
+            .await?;
+        if keys.is_empty() {
             return Ok(false);
         }
-        let response_data = self.bucket.delete_object(path).await?;
-        debug!(?response_data, "File Deleted");
-        if response_data.status_code() != 204 {
-            return Err(S3StorageError::UnexpectedStatusCode {
-                expected: 204,
-                got: response_data.status_code(),
-            });
+        info!(?path, objects = keys.len(), "Deleting Directory");
+        for key in keys {
+            self.delete_object(&key).await?;
         }
         Ok(true)
     }
@@ -440,7 +624,16 @@ impl Storage for S3Storage {
         repository: uuid::Uuid,
         location: &StoragePath,
     ) -> Result<Option<crate::StorageFileMeta<FileType>>, S3StorageError> {
-        todo!()
+        let path = self.s3_path(&repository, location);
+        if let Some(head) = self.head_object(&path).await? {
+            let meta = self.file_meta(&path, head).await?;
+            return Ok(Some(meta.map_type(FileType::File)));
+        }
+        let Some(first_page) = self.first_directory_page(&path).await? else {
+            return Ok(None);
+        };
+        let meta = self.directory_meta(&path, &first_page).await?;
+        Ok(Some(meta.map_type(FileType::Directory)))
     }
     #[instrument(name = "Storage::open_file", fields(storage_type = "s3"))]
     async fn open_file(
@@ -449,26 +642,25 @@ impl Storage for S3Storage {
         location: &StoragePath,
     ) -> Result<Option<crate::StorageFile>, S3StorageError> {
         let path = self.s3_path(&repository, location);
-        let response_data = match self.bucket.get_object(&path).await {
-            Ok(response_data) => {
-                if response_data.status_code() == 404 {
-                    // Attempt to index the directory
-                    debug!("File not found, attempting to index directory");
-                    return self.index_directory(&path).await;
-                }
-
-                if response_data.status_code() != 200 {
-                    return Err(S3StorageError::UnexpectedStatusCode {
-                        expected: 200,
-                        got: response_data.status_code(),
-                    });
-                }
-                response_data
+        let Some(head) = self.head_object(&path).await? else {
+            debug!("File not found, attempting to index directory");
+            return self.index_directory(&path).await;
+        };
+        if head.content_type.as_deref() == Some("application/x-directory") {
+            return self.index_directory(&path).await;
+        }
+        let meta = self.file_meta(&path, head).await?;
+        let response = match self.bucket.get_object_stream(&path).await {
+            Ok(response) if response.status_code == 404 => return Ok(None),
+            Ok(response) if response.status_code != 200 => {
+                return Err(S3StorageError::UnexpectedStatusCode {
+                    expected: 200,
+                    got: response.status_code,
+                });
             }
+            Ok(response) => response,
+            Err(S3Error::HttpFailWithBody(404, _)) => return Ok(None),
             Err(S3Error::HttpFailWithBody(code, _)) => {
-                if code == 404 {
-                    return self.index_directory(&path).await;
-                }
                 return Err(S3StorageError::UnexpectedStatusCode {
                     expected: 200,
                     got: code,
@@ -476,33 +668,73 @@ impl Storage for S3Storage {
             }
             Err(e) => return Err(e.into()),
         };
+        // The body is read from the connection as the response is written. Nothing is buffered.
+        let reader = StreamReader::new(response.bytes.map_err(io::Error::other));
+        let result = StorageFile::File {
+            meta,
+            content: crate::StorageFileReader::AsyncReader(Box::pin(reader)),
+        };
 
-        let headers = response_data.headers();
-        if let Some(content_type) = headers.get("content-type") {
-            if content_type == "application/x-directory" {
-                return self.index_directory(&path).await;
-            }
+        Ok(Some(result))
+    }
+    #[instrument(name = "Storage::open_file_range", fields(storage_type = "s3"))]
+    async fn open_file_range(
+        &self,
+        repository: uuid::Uuid,
+        location: &StoragePath,
+        range: FileRange,
+    ) -> Result<Option<crate::StorageFile>, S3StorageError> {
+        let path = self.s3_path(&repository, location);
+        let Some(head) = self.head_object(&path).await? else {
+            return self.index_directory(&path).await;
+        };
+        if head.content_type.as_deref() == Some("application/x-directory") {
+            return self.index_directory(&path).await;
         }
-        let meta = StorageFileMeta::<FileFileType> {
-            name: location.to_string(),
-            file_type: FileFileType {
-                file_size: headers
-                    .get("content-length")
-                    .and_then(|len| len.parse().ok())
-                    .unwrap_or(0),
-                mime_type: headers
-                    .get("content-type")
-                    .and_then(|mime| Mime::from_str(mime.as_str()).ok().map(SerdeMime::from)),
-                file_hash: FileHashes::default(),
-            },
-            modified: Local::now().fixed_offset(),
-            created: Local::now().fixed_offset(),
+        let meta = self.file_meta(&path, head).await?;
+        let (sender, mut receiver) = mpsc::channel::<RangeChunk>(RANGE_CHANNEL_CAPACITY);
+        let storage = self.clone();
+        let request_path = path.clone();
+        tokio::spawn(async move {
+            let mut writer = RangeChunkWriter::new(sender.clone());
+            let result = storage
+                .bucket
+                .get_object_range_to_writer(
+                    &request_path,
+                    range.start,
+                    Some(range.end),
+                    &mut writer,
+                )
+                .await;
+            let error = match result {
+                Ok(200 | 206) => return,
+                Ok(code) => S3Error::HttpFailWithBody(code, String::new()),
+                Err(err) => err,
+            };
+            error!(?error, path = ?request_path, "Failed to stream object range");
+            // The reader fails instead of ending early
+            let _ = sender.send(Err(error)).await;
+        });
+        // Wait for the request to start. So a failed request is an error instead of an empty response
+        let first_chunk = match receiver.recv().await {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(S3Error::HttpFailWithBody(404, _))) => return Ok(None),
+            Some(Err(S3Error::HttpFailWithBody(code, _))) => {
+                return Err(S3StorageError::UnexpectedStatusCode {
+                    expected: 206,
+                    got: code,
+                });
+            }
+            Some(Err(err)) => return Err(err.into()),
+            None => Bytes::new(),
         };
+        let chunks = futures::stream::once(async move { Ok(first_chunk) })
+            .chain(ReceiverStream::new(receiver))
+            .map_err(io::Error::other);
         let result = StorageFile::File {
             meta,
-            content: crate::StorageFileReader::Bytes(response_data.into_bytes().into()),
+            content: crate::StorageFileReader::AsyncReader(Box::pin(StreamReader::new(chunks))),
         };
-
         Ok(Some(result))
     }
     #[instrument(name = "Storage::validate_config_change", fields(storage_type = "s3"))]
@@ -524,13 +756,30 @@ impl Storage for S3Storage {
         let path = self.s3_path(&repository, location);
         self.does_path_exist(&path).await
     }
-
+    #[instrument(name = "Storage::stream_directory", fields(storage_type = "s3"))]
     async fn stream_directory(
         &self,
-        _repository: Uuid,
-        _location: &StoragePath,
+        repository: Uuid,
+        location: &StoragePath,
     ) -> Result<Option<Self::DirectoryStream>, Self::Error> {
-        todo!()
+        let path = self.s3_path(&repository, location);
+        if let Some(head) = self.head_object(&path).await? {
+            if is_hidden_key(&path) {
+                return Ok(None);
+            }
+            let meta = self.file_meta(&path, head).await?;
+            return Ok(Some(S3DirectoryListStream::new_file(meta)));
+        }
+        let Some(first_page) = self.first_directory_page(&path).await? else {
+            return Ok(None);
+        };
+        let meta = self.directory_meta(&path, &first_page).await?;
+        Ok(Some(S3DirectoryListStream::new_directory(
+            self.clone(),
+            S3StorageInner::s3_directory_prefix(&path),
+            first_page,
+            meta,
+        )))
     }
 }
 #[derive(Debug, Default)]
@@ -0,0 +1,228 @@
+use std::{collections::VecDeque, fmt::Debug, future::Future, io, pin::Pin, task::Poll};
+
+use bytes::Bytes;
+use chrono::{DateTime, Local};
+use futures::{Stream, future::BoxFuture};
+use nr_core::storage::{FileHashes, SerdeMime};
+use s3::{
+    error::S3Error,
+    serde_types::{ListBucketResult, Object},
+};
+use tokio::{io::AsyncWrite, sync::mpsc};
+use tokio_util::sync::PollSender;
+use tracing::trace;
+
+use crate::{
+    DirectoryFileType, FileFileType, FileType, StorageError, StorageFileMeta,
+    streaming::DirectoryListStream,
+};
+
+use super::{S3Storage, S3StorageError, meta::is_hidden_key};
+
+/// A [DirectoryListStream] backed by paginated ListObjectsV2 requests.
+///
+/// The first page is requested when the stream is created. Every following page is only requested once the
+/// previous page has been consumed.
+pub struct S3DirectoryListStream {
+    storage: Option<S3Storage>,
+    prefix: String,
+    files: VecDeque<StorageFileMeta<FileType>>,
+    continuation_token: Option<String>,
+    next_page: Option<BoxFuture<'static, Result<ListBucketResult, S3StorageError>>>,
+    meta: StorageFileMeta<FileType>,
+}
+impl Debug for S3DirectoryListStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3DirectoryListStream")
+            .field("prefix", &self.prefix)
+            .field("files", &self.files)
+            .field("continuation_token", &self.continuation_token)
+            .field("meta", &self.meta)
+            .finish()
+    }
+}
+impl S3DirectoryListStream {
+    pub fn new_directory(
+        storage: S3Storage,
+        prefix: String,
+        first_page: ListBucketResult,
+        meta: StorageFileMeta<DirectoryFileType>,
+    ) -> Self {
+        let continuation_token = next_continuation_token(&first_page);
+        S3DirectoryListStream {
+            storage: Some(storage),
+            prefix,
+            files: page_entries(first_page).into(),
+            continuation_token,
+            next_page: None,
+            meta: meta.map_type(FileType::Directory),
+        }
+    }
+    pub fn new_file(meta: StorageFileMeta<FileFileType>) -> Self {
+        let file = meta.map_type(FileType::File);
+        S3DirectoryListStream {
+            storage: None,
+            prefix: String::new(),
+            files: VecDeque::from([file.clone()]),
+            continuation_token: None,
+            next_page: None,
+            meta: file,
+        }
+    }
+}
+impl DirectoryListStream for S3DirectoryListStream {
+    /// # Note
+    /// S3 does not know how many objects are under a prefix without listing all of them.
+    ///
+    /// If the directory spans multiple pages this is the number of entries on the first page.
+    fn number_of_files(&self) -> u64 {
+        match &self.meta.file_type() {
+            FileType::Directory(dir) => dir.file_count,
+            _ => 1,
+        }
+    }
+}
+impl Stream for S3DirectoryListStream {
+    type Item = Result<Option<StorageFileMeta<FileType>>, StorageError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(file) = this.files.pop_front() {
+                return Poll::Ready(Some(Ok(Some(file))));
+            }
+            if this.next_page.is_none() {
+                let (Some(storage), Some(token)) =
+                    (this.storage.clone(), this.continuation_token.take())
+                else {
+                    return Poll::Ready(None);
+                };
+                trace!(prefix = ?this.prefix, "Requesting next page");
+                let prefix = this.prefix.clone();
+                this.next_page = Some(Box::pin(async move {
+                    storage.list_page(prefix, Some(token)).await
+                }));
+            }
+            let Some(next_page) = this.next_page.as_mut() else {
+                return Poll::Ready(None);
+            };
+            let page = match next_page.as_mut().poll(cx) {
+                Poll::Ready(page) => page,
+                Poll::Pending => return Poll::Pending,
+            };
+            this.next_page = None;
+            match page {
+                Ok(page) => {
+                    this.continuation_token = next_continuation_token(&page);
+                    this.files.extend(page_entries(page));
+                }
+                Err(err) => return Poll::Ready(Some(Err(err.into()))),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.files.len(), None)
+    }
+}
+
+fn next_continuation_token(page: &ListBucketResult) -> Option<String> {
+    if page.is_truncated {
+        page.next_continuation_token.clone()
+    } else {
+        None
+    }
+}
+/// Converts a page of a delimited listing into the entries of the directory.
+///
+/// Objects become files and common prefixes become directories. Hidden objects are skipped.
+///
+/// # Note
+/// Hashes are not included as that would require a request per object.
+pub(super) fn page_entries(page: ListBucketResult) -> Vec<StorageFileMeta<FileType>> {
+    let mut files = Vec::with_capacity(page.contents.len());
+    for object in page.contents {
+        if is_hidden_key(&object.key) {
+            trace!(key = ?object.key, "Skipping Meta Object");
+            continue;
+        }
+        // The directory marker itself
+        if object.key.ends_with('/') {
+            continue;
+        }
+        files.push(file_meta_from_object(object).map_type(FileType::File));
+    }
+    for prefix in page.common_prefixes.into_iter().flatten() {
+        let now = Local::now().fixed_offset();
+        files.push(StorageFileMeta {
+            name: key_file_name(&prefix.prefix).to_owned(),
+            file_type: FileType::Directory(DirectoryFileType { file_count: 0 }),
+            modified: now,
+            created: now,
+        });
+    }
+    files
+}
+fn file_meta_from_object(object: Object) -> StorageFileMeta<FileFileType> {
+    let modified = DateTime::parse_from_rfc3339(&object.last_modified)
+        .unwrap_or_else(|_| Local::now().fixed_offset());
+    StorageFileMeta {
+        name: key_file_name(&object.key).to_owned(),
+        file_type: FileFileType {
+            file_size: object.size,
+            mime_type: Some(SerdeMime(
+                mime_guess::from_path(&object.key).first_or_octet_stream(),
+            )),
+            file_hash: FileHashes::default(),
+        },
+        modified,
+        created: modified,
+    }
+}
+/// Returns the last component of a key. Trailing slashes are ignored.
+pub(super) fn key_file_name(key: &str) -> &str {
+    let key = key.trim_end_matches('/');
+    key.rsplit('/').next().unwrap_or(key)
+}
+/// A chunk of a ranged read. The request failing is sent as the last item
+pub type RangeChunk = Result<Bytes, S3Error>;
+/// Sends everything written to it as chunks of a channel.
+///
+/// rust-s3 can only stream a ranged GET into a writer. The channel lets the error of the request reach the reader
+pub struct RangeChunkWriter {
+    sender: PollSender<RangeChunk>,
+}
+impl RangeChunkWriter {
+    pub fn new(sender: mpsc::Sender<RangeChunk>) -> Self {
+        Self {
+            sender: PollSender::new(sender),
+        }
+    }
+}
+impl AsyncWrite for RangeChunkWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let closed = || io::Error::from(io::ErrorKind::BrokenPipe);
+        if futures::ready!(self.sender.poll_reserve(cx)).is_err() {
+            return Poll::Ready(Err(closed()));
+        }
+        self.sender
+            .send_item(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| closed())?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -109,19 +109,21 @@ impl<ST: Storage> Storage for TestingStorage<ST> {
 
     async fn put_repository_meta(
         &self,
-        _repository: Uuid,
-        _location: &StoragePath,
-        _value: RepositoryMeta,
+        repository: Uuid,
+        location: &StoragePath,
+        value: RepositoryMeta,
     ) -> Result<(), Self::Error> {
-        todo!()
+        self.storage
+            .put_repository_meta(repository, location, value)
+            .await
     }
 
     async fn get_repository_meta(
         &self,
-        _repository: Uuid,
-        _location: &StoragePath,
+        repository: Uuid,
+        location: &StoragePath,
     ) -> Result<Option<RepositoryMeta>, Self::Error> {
-        todo!()
+        self.storage.get_repository_meta(repository, location).await
     }
 
     async fn delete_file(
@@ -158,6 +160,17 @@ impl<ST: Storage> Storage for TestingStorage<ST> {
         Ok(result)
     }
 
+    async fn open_file_range(
+        &self,
+        repository: Uuid,
+        location: &StoragePath,
+        range: crate::FileRange,
+    ) -> Result<Option<crate::StorageFile>, Self::Error> {
+        self.storage
+            .open_file_range(repository, location, range)
+            .await
+    }
+
     async fn validate_config_change(
         &self,
         config: crate::StorageTypeConfig,
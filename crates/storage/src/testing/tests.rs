@@ -1,14 +1,22 @@
-use nr_core::storage::StoragePath;
+use futures::StreamExt;
+use nr_core::storage::{FileTypeCheck, StoragePath};
 use tracing::{debug, info};
 use uuid::Uuid;
 
-use crate::{FileContent, Storage, StorageError, StorageFile};
+use crate::{
+    FileContent, FileRange, FileType, Storage, StorageError, StorageFile, meta::RepositoryMeta,
+};
 
 use super::storage::TestingStorage;
 pub async fn full_test<ST: Storage>(storage: TestingStorage<ST>) -> anyhow::Result<()> {
     write_then_read(&storage).await?;
+    write_large_then_read(&storage).await?;
+    read_range(&storage).await?;
     write_multiple_then_list(&storage).await?;
     should_conflict(&storage).await?;
+    repository_meta(&storage).await?;
+    file_information(&storage).await?;
+    stream_directory(&storage).await?;
     storage.unload().await?;
     Ok(())
 }
@@ -39,6 +47,67 @@ pub async fn write_then_read<ST: Storage>(storage: &TestingStorage<ST>) -> anyho
     Ok(())
 }
 
+/// Large enough to require a multipart upload on S3
+pub async fn write_large_then_read<ST: Storage>(
+    storage: &TestingStorage<ST>,
+) -> anyhow::Result<()> {
+    let repository = Uuid::new_v4();
+    let expected: Vec<u8> = (0..(12 * 1024 * 1024u32)).map(|v| v as u8).collect();
+    let temp_file = std::env::temp_dir().join(format!("{}.large_test", Uuid::new_v4()));
+    std::fs::write(&temp_file, &expected)?;
+
+    let paths = [
+        (
+            StoragePath::from("large/from_path.bin"),
+            FileContent::Path(temp_file.clone()),
+        ),
+        (
+            StoragePath::from("large/from_bytes.bin"),
+            FileContent::Bytes(expected.clone().into()),
+        ),
+    ];
+    for (path, content) in paths {
+        let (written, _) = storage.save_file(repository, content, &path).await?;
+        assert_eq!(written, expected.len(), "{path}");
+
+        let Some(StorageFile::File { meta, content }) =
+            storage.open_file(repository, &path).await?
+        else {
+            panic!("{path} is not a file");
+        };
+        assert_eq!(meta.file_type.file_size, expected.len() as u64, "{path}");
+        let content = content
+            .read_to_vec(meta.file_type.file_size as usize)
+            .await?;
+        assert!(content == expected, "{path} content does not match");
+    }
+    std::fs::remove_file(temp_file)?;
+    Ok(())
+}
+
+pub async fn read_range<ST: Storage>(storage: &TestingStorage<ST>) -> anyhow::Result<()> {
+    let repository = Uuid::new_v4();
+    let path = StoragePath::from("range.txt");
+    let content = b"Hello, World!";
+    storage
+        .save_file(repository, FileContent::from(content), &path)
+        .await?;
+
+    let range = FileRange::new(7, 11).unwrap();
+    let Some(StorageFile::File { meta, content }) =
+        storage.open_file_range(repository, &path, range).await?
+    else {
+        panic!("File is not a file");
+    };
+    assert_eq!(
+        meta.file_type.file_size, 13,
+        "Meta should describe the entire file"
+    );
+    let content = content.read_to_vec(range.len() as usize).await?;
+    assert_eq!(content, b"World");
+    Ok(())
+}
+
 pub async fn write_multiple_then_list<ST: Storage>(
     storage: &TestingStorage<ST>,
 ) -> anyhow::Result<()> {
@@ -102,3 +171,106 @@ pub async fn should_conflict<ST: Storage>(storage: &TestingStorage<ST>) -> anyho
     info!(?storage_error, "Error");
     Ok(())
 }
+
+pub async fn repository_meta<ST: Storage>(storage: &TestingStorage<ST>) -> anyhow::Result<()> {
+    let repository = Uuid::new_v4();
+    let file = StoragePath::from("/meta/project/file.txt");
+    let directory = StoragePath::from("/meta/project/");
+    storage
+        .save_file(repository, FileContent::from("Hello, World!"), &file)
+        .await?;
+
+    let mut meta = RepositoryMeta::default();
+    meta.set_project_id(Uuid::new_v4());
+    meta.insert("key", "value");
+
+    for path in [&file, &directory] {
+        let existing = storage.get_repository_meta(repository, path).await?;
+        assert_eq!(existing, Some(RepositoryMeta::default()), "{path}");
+
+        storage
+            .put_repository_meta(repository, path, meta.clone())
+            .await?;
+        let read = storage.get_repository_meta(repository, path).await?;
+        assert_eq!(read.as_ref(), Some(&meta), "{path}");
+    }
+    let missing = storage
+        .get_repository_meta(repository, &StoragePath::from("/meta/missing.txt"))
+        .await?;
+    assert!(missing.is_none(), "Meta returned for a missing file");
+    Ok(())
+}
+
+pub async fn file_information<ST: Storage>(storage: &TestingStorage<ST>) -> anyhow::Result<()> {
+    let repository = Uuid::new_v4();
+    let path = StoragePath::from("/info/file.txt");
+    let content = "Hello, World!";
+    storage
+        .save_file(repository, FileContent::from(content), &path)
+        .await?;
+
+    let file = storage
+        .get_file_information(repository, &path)
+        .await?
+        .expect("File not found");
+    let FileType::File(file_type) = file.file_type() else {
+        panic!("Expected a file");
+    };
+    assert_eq!(file.name(), "file.txt");
+    assert_eq!(file_type.file_size, content.len() as u64);
+
+    let directory = storage
+        .get_file_information(repository, &StoragePath::from("/info/"))
+        .await?
+        .expect("Directory not found");
+    assert!(directory.is_directory(), "Expected a directory");
+
+    let missing = storage
+        .get_file_information(repository, &StoragePath::from("/info/missing.txt"))
+        .await?;
+    assert!(missing.is_none(), "Information returned for a missing file");
+    Ok(())
+}
+
+pub async fn stream_directory<ST: Storage>(storage: &TestingStorage<ST>) -> anyhow::Result<()> {
+    let repository = Uuid::new_v4();
+    let paths = [
+        StoragePath::from("/stream/a.txt"),
+        StoragePath::from("/stream/b.txt"),
+        StoragePath::from("/stream/sub/c.txt"),
+    ];
+    for path in paths.iter() {
+        storage
+            .save_file(repository, FileContent::from("Hello, World!"), path)
+            .await?;
+    }
+
+    let stream = storage
+        .stream_directory(repository, &StoragePath::from("/stream/"))
+        .await?
+        .expect("Directory not found");
+    let mut stream = Box::pin(stream);
+    let mut names = Vec::new();
+    while let Some(file) = stream.next().await {
+        let file = file?;
+        if let Some(file) = file {
+            names.push(file.name().to_owned());
+        }
+    }
+    names.sort();
+    assert_eq!(names, vec!["a.txt", "b.txt", "sub"]);
+
+    let single = storage
+        .stream_directory(repository, &paths[0])
+        .await?
+        .expect("File not found");
+    let mut single = Box::pin(single);
+    let file = single.next().await.expect("Expected a file")?;
+    assert!(file.is_some_and(|file| file.is_file()));
+
+    let missing = storage
+        .stream_directory(repository, &StoragePath::from("/stream/missing/"))
+        .await?;
+    assert!(missing.is_none(), "Stream returned for a missing directory");
+    Ok(())
+}
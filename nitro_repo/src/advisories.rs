@@ -0,0 +1,140 @@
+//! Imports security advisories from an offline advisory database.
+//!
+//! The advisories are used to answer `npm audit`
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use nr_core::database::entities::advisory::DBSecurityAdvisory;
+use osv::{OsvAdvisory, OsvFile};
+use sqlx::PgPool;
+use zip::ZipArchive;
+
+use crate::app::{NitroRepo, config::load_config};
+mod osv;
+#[derive(Debug, Default)]
+struct ImportSummary {
+    imported: usize,
+    withdrawn: usize,
+    invalid_files: usize,
+}
+/// Imports a JSON file, a directory of JSON files or a zip of JSON files in the OSV format.
+///
+/// Such as the `all.zip` osv.dev publishes for every ecosystem or a checkout of the GitHub Advisory Database
+pub fn import(config: Option<PathBuf>, path: PathBuf, ecosystem: String) -> anyhow::Result<()> {
+    if !path.exists() {
+        anyhow::bail!("{} does not exist", path.display());
+    }
+    let config = load_config(config)?;
+    let tokio = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    tokio.block_on(async move {
+        let database = NitroRepo::load_database(config.database).await?;
+        let mut summary = ImportSummary::default();
+        let mut files = AdvisoryFiles::open(&path)?;
+        for index in 0..files.len() {
+            let Some((name, contents)) = files.read(index)? else {
+                continue;
+            };
+            let advisories: Vec<OsvAdvisory> = match serde_json::from_slice::<OsvFile>(&contents) {
+                Ok(file) => file.into(),
+                Err(err) => {
+                    eprintln!("Skipping {name}: {err}");
+                    summary.invalid_files += 1;
+                    continue;
+                }
+            };
+            for advisory in advisories {
+                import_advisory(&advisory, &ecosystem, &database, &mut summary)
+                    .await
+                    .with_context(|| format!("Failed to import {}", advisory.id))?;
+            }
+        }
+        let total = DBSecurityAdvisory::count_by_ecosystem(&ecosystem, &database).await?;
+        println!(
+            "Imported {} advisories. Removed {} withdrawn advisories. Skipped {} invalid files. {} {} advisories are available",
+            summary.imported, summary.withdrawn, summary.invalid_files, total, ecosystem
+        );
+        Ok(())
+    })
+}
+async fn import_advisory(
+    advisory: &OsvAdvisory,
+    ecosystem: &str,
+    database: &PgPool,
+    summary: &mut ImportSummary,
+) -> anyhow::Result<()> {
+    let advisories = advisory.to_new_advisories(ecosystem);
+    if advisory.is_withdrawn() {
+        summary.withdrawn += 1;
+    } else if advisories.is_empty() {
+        // Full dumps contain every ecosystem. Skipping them avoids a query for every advisory
+        return Ok(());
+    } else {
+        summary.imported += 1;
+    }
+    DBSecurityAdvisory::replace_advisory(&advisory.id, &advisories, database).await?;
+    Ok(())
+}
+/// The JSON files to import.
+///
+/// Files are read one at a time so a full dump is never held in memory
+enum AdvisoryFiles {
+    Files(Vec<PathBuf>),
+    Zip(ZipArchive<File>),
+}
+impl AdvisoryFiles {
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        if path.is_dir() {
+            let mut files = Vec::new();
+            find_json_files(path, &mut files)?;
+            Ok(Self::Files(files))
+        } else if path.extension().is_some_and(|extension| extension == "zip") {
+            Ok(Self::Zip(ZipArchive::new(File::open(path)?)?))
+        } else {
+            Ok(Self::Files(vec![path.to_path_buf()]))
+        }
+    }
+    fn len(&self) -> usize {
+        match self {
+            Self::Files(files) => files.len(),
+            Self::Zip(archive) => archive.len(),
+        }
+    }
+    /// The name and contents of the file. None if the entry is not a JSON file
+    fn read(&mut self, index: usize) -> anyhow::Result<Option<(String, Vec<u8>)>> {
+        match self {
+            Self::Files(files) => {
+                let path = &files[index];
+                Ok(Some((path.display().to_string(), std::fs::read(path)?)))
+            }
+            Self::Zip(archive) => {
+                let mut file = archive.by_index(index)?;
+                if file.is_dir() || !file.name().ends_with(".json") {
+                    return Ok(None);
+                }
+                let mut contents = Vec::with_capacity(file.size() as usize);
+                file.read_to_end(&mut contents)?;
+                Ok(Some((file.name().to_owned(), contents)))
+            }
+        }
+    }
+}
+fn find_json_files(path: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_json_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
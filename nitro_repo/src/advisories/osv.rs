@@ -0,0 +1,350 @@
+//! The [OSV format](https://ossf.github.io/osv-schema/). Used by the GitHub Advisory Database and osv.dev
+use ahash::{HashMap, HashMapExt};
+use chrono::{DateTime, FixedOffset};
+use nr_core::database::entities::advisory::{AffectedRange, AffectedVersions, NewSecurityAdvisory};
+use serde::Deserialize;
+use serde_json::Value;
+/// Used if the advisory does not have a severity
+pub const DEFAULT_SEVERITY: &str = "moderate";
+/// A file can contain a single advisory or an array of them
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum OsvFile {
+    Many(Vec<OsvAdvisory>),
+    One(Box<OsvAdvisory>),
+}
+impl From<OsvFile> for Vec<OsvAdvisory> {
+    fn from(value: OsvFile) -> Self {
+        match value {
+            OsvFile::Many(advisories) => advisories,
+            OsvFile::One(advisory) => vec![*advisory],
+        }
+    }
+}
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvAdvisory {
+    pub id: String,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub details: Option<String>,
+    #[serde(default)]
+    pub published: Option<DateTime<FixedOffset>>,
+    #[serde(default)]
+    pub modified: Option<DateTime<FixedOffset>>,
+    #[serde(default)]
+    pub withdrawn: Option<DateTime<FixedOffset>>,
+    #[serde(default)]
+    pub affected: Vec<OsvAffected>,
+    #[serde(default)]
+    pub severity: Vec<OsvSeverity>,
+    #[serde(default)]
+    pub references: Vec<OsvReference>,
+    /// The GitHub Advisory Database puts the severity and CWEs here
+    #[serde(default)]
+    pub database_specific: Option<Value>,
+}
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvAffected {
+    pub package: OsvPackage,
+    #[serde(default)]
+    pub ranges: Vec<OsvRange>,
+    #[serde(default)]
+    pub versions: Vec<String>,
+}
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvPackage {
+    pub ecosystem: String,
+    pub name: String,
+}
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvRange {
+    /// `SEMVER`, `ECOSYSTEM` or `GIT`
+    #[serde(rename = "type")]
+    pub range_type: String,
+    #[serde(default)]
+    pub events: Vec<OsvEvent>,
+}
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OsvEvent {
+    pub introduced: Option<String>,
+    pub fixed: Option<String>,
+    pub last_affected: Option<String>,
+}
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvSeverity {
+    #[serde(rename = "type")]
+    pub severity_type: String,
+    pub score: String,
+}
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvReference {
+    #[serde(rename = "type")]
+    pub reference_type: String,
+    pub url: String,
+}
+impl OsvRange {
+    /// Pairs the events into ranges. Git ranges use commits and are ignored
+    pub fn affected_ranges(&self) -> Vec<AffectedRange> {
+        if self.range_type.eq_ignore_ascii_case("GIT") {
+            return Vec::new();
+        }
+        let mut ranges = Vec::new();
+        let mut open: Option<AffectedRange> = None;
+        for event in &self.events {
+            if let Some(introduced) = &event.introduced {
+                ranges.extend(open.take());
+                open = Some(AffectedRange {
+                    introduced: Some(introduced.clone()),
+                    ..Default::default()
+                });
+            } else if event.fixed.is_some() || event.last_affected.is_some() {
+                let mut range = open.take().unwrap_or_default();
+                range.fixed = event.fixed.clone();
+                range.last_affected = event.last_affected.clone();
+                ranges.push(range);
+            }
+        }
+        ranges.extend(open);
+        ranges
+    }
+}
+impl OsvAdvisory {
+    pub fn is_withdrawn(&self) -> bool {
+        self.withdrawn.is_some()
+    }
+    pub fn title(&self) -> String {
+        self.summary
+            .as_deref()
+            .or_else(|| {
+                self.details
+                    .as_deref()
+                    .and_then(|details| details.lines().next())
+            })
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or(self.id.as_str())
+            .trim()
+            .to_owned()
+    }
+    /// The advisory reference. Falls back to the public page of the advisory
+    pub fn url(&self) -> String {
+        let reference = ["ADVISORY", "WEB"].iter().find_map(|reference_type| {
+            self.references.iter().find(|reference| {
+                reference
+                    .reference_type
+                    .eq_ignore_ascii_case(reference_type)
+            })
+        });
+        match reference {
+            Some(reference) => reference.url.clone(),
+            None if self.id.starts_with("GHSA-") => {
+                format!("https://github.com/advisories/{}", self.id)
+            }
+            None => format!("https://osv.dev/vulnerability/{}", self.id),
+        }
+    }
+    /// The severity as npm names them. `low`, `moderate`, `high` or `critical`
+    pub fn severity(&self) -> String {
+        let severity = self
+            .database_specific
+            .as_ref()
+            .and_then(|specific| specific.get("severity"))
+            .and_then(Value::as_str)
+            .map(str::to_lowercase);
+        match severity.as_deref() {
+            Some("medium") => "moderate".to_owned(),
+            Some(severity @ ("low" | "moderate" | "high" | "critical")) => severity.to_owned(),
+            _ => DEFAULT_SEVERITY.to_owned(),
+        }
+    }
+    pub fn cwe(&self) -> Vec<String> {
+        self.database_specific
+            .as_ref()
+            .and_then(|specific| specific.get("cwe_ids"))
+            .and_then(Value::as_array)
+            .map(|cwe| {
+                cwe.iter()
+                    .filter_map(Value::as_str)
+                    .map(ToOwned::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    /// The newest CVSS vector
+    pub fn cvss_vector(&self) -> Option<String> {
+        ["CVSS_V4", "CVSS_V3"].iter().find_map(|severity_type| {
+            self.severity
+                .iter()
+                .find(|severity| severity.severity_type.eq_ignore_ascii_case(severity_type))
+                .map(|severity| severity.score.clone())
+        })
+    }
+    /// One advisory for every package of the ecosystem it affects.
+    ///
+    /// Withdrawn advisories return nothing
+    pub fn to_new_advisories(&self, ecosystem: &str) -> Vec<NewSecurityAdvisory> {
+        if self.is_withdrawn() {
+            return Vec::new();
+        }
+        // A package can be listed more than once with different ranges
+        let mut packages: HashMap<&str, AffectedVersions> = HashMap::new();
+        let mut order = Vec::new();
+        for affected in &self.affected {
+            if !affected.package.ecosystem.eq_ignore_ascii_case(ecosystem) {
+                continue;
+            }
+            let name = affected.package.name.as_str();
+            let versions = packages.entry(name).or_insert_with(|| {
+                order.push(name);
+                AffectedVersions::default()
+            });
+            versions
+                .ranges
+                .extend(affected.ranges.iter().flat_map(OsvRange::affected_ranges));
+            versions.versions.extend(affected.versions.iter().cloned());
+        }
+        let title = self.title();
+        let url = self.url();
+        let severity = self.severity();
+        let cwe = self.cwe();
+        let cvss_vector = self.cvss_vector();
+        order
+            .into_iter()
+            .filter_map(|name| {
+                let affected = packages.remove(name)?;
+                Some(NewSecurityAdvisory {
+                    advisory_id: self.id.clone(),
+                    ecosystem: ecosystem.to_owned(),
+                    package_name: name.to_owned(),
+                    title: title.clone(),
+                    url: Some(url.clone()),
+                    severity: severity.clone(),
+                    cwe: cwe.clone(),
+                    cvss_vector: cvss_vector.clone(),
+                    affected,
+                    published_at: self.published,
+                    modified_at: self.modified,
+                })
+            })
+            .collect()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const GHSA: &str = r#"{
+        "schema_version": "1.4.0",
+        "id": "GHSA-35jh-r3h4-6jhm",
+        "modified": "2024-01-10T05:02:06Z",
+        "published": "2021-05-06T16:05:51Z",
+        "aliases": ["CVE-2021-23337"],
+        "summary": "Command Injection in lodash",
+        "details": "`lodash` versions prior to 4.17.21 are vulnerable to Command Injection via the template function.",
+        "severity": [
+            {"type": "CVSS_V3", "score": "CVSS:3.1/AV:N/AC:L/PR:H/UI:N/S:U/C:H/I:H/A:H"}
+        ],
+        "affected": [
+            {
+                "package": {"ecosystem": "npm", "name": "lodash"},
+                "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "0"}, {"fixed": "4.17.21"}]}]
+            },
+            {
+                "package": {"ecosystem": "npm", "name": "lodash-es"},
+                "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "0"}, {"fixed": "4.17.21"}]}]
+            },
+            {
+                "package": {"ecosystem": "npm", "name": "lodash"},
+                "versions": ["5.0.0-pre"]
+            },
+            {
+                "package": {"ecosystem": "PyPI", "name": "lodash"},
+                "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "0"}, {"fixed": "1.0.0"}]}]
+            }
+        ],
+        "references": [
+            {"type": "WEB", "url": "https://github.com/lodash/lodash/commit/3469357"},
+            {"type": "ADVISORY", "url": "https://nvd.nist.gov/vuln/detail/CVE-2021-23337"}
+        ],
+        "database_specific": {
+            "cwe_ids": ["CWE-94"],
+            "severity": "HIGH",
+            "github_reviewed": true
+        }
+    }"#;
+    #[test]
+    fn parse_github_advisory() {
+        let file: OsvFile = serde_json::from_str(GHSA).unwrap();
+        let advisories: Vec<OsvAdvisory> = file.into();
+        assert_eq!(advisories.len(), 1);
+        let new = advisories[0].to_new_advisories("npm");
+        assert_eq!(new.len(), 2);
+        let lodash = &new[0];
+        assert_eq!(lodash.package_name, "lodash");
+        assert_eq!(lodash.title, "Command Injection in lodash");
+        assert_eq!(
+            lodash.url.as_deref(),
+            Some("https://nvd.nist.gov/vuln/detail/CVE-2021-23337")
+        );
+        assert_eq!(lodash.severity, "high");
+        assert_eq!(lodash.cwe, vec!["CWE-94".to_owned()]);
+        assert!(lodash.cvss_vector.is_some());
+        assert_eq!(
+            lodash.affected,
+            AffectedVersions {
+                ranges: vec![AffectedRange {
+                    introduced: Some("0".to_owned()),
+                    fixed: Some("4.17.21".to_owned()),
+                    last_affected: None,
+                }],
+                versions: vec!["5.0.0-pre".to_owned()],
+            }
+        );
+        assert_eq!(new[1].package_name, "lodash-es");
+    }
+    #[test]
+    fn pair_events() {
+        let range = OsvRange {
+            range_type: "SEMVER".to_owned(),
+            events: vec![
+                OsvEvent {
+                    introduced: Some("1.0.0".to_owned()),
+                    ..Default::default()
+                },
+                OsvEvent {
+                    fixed: Some("1.2.0".to_owned()),
+                    ..Default::default()
+                },
+                OsvEvent {
+                    introduced: Some("2.0.0".to_owned()),
+                    ..Default::default()
+                },
+                OsvEvent {
+                    last_affected: Some("2.1.0".to_owned()),
+                    ..Default::default()
+                },
+                OsvEvent {
+                    introduced: Some("3.0.0".to_owned()),
+                    ..Default::default()
+                },
+            ],
+        };
+        let ranges = range.affected_ranges();
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges[0].fixed.as_deref(), Some("1.2.0"));
+        assert_eq!(ranges[1].last_affected.as_deref(), Some("2.1.0"));
+        assert_eq!(ranges[2].introduced.as_deref(), Some("3.0.0"));
+        assert_eq!(ranges[2].fixed, None);
+    }
+    #[test]
+    fn withdrawn_and_defaults() {
+        let advisory: OsvAdvisory = serde_json::from_str(
+            r#"{"id":"OSV-2024-1","withdrawn":"2024-02-01T00:00:00Z","affected":[{"package":{"ecosystem":"npm","name":"a"},"versions":["1.0.0"]}]}"#,
+        )
+        .unwrap();
+        assert!(advisory.to_new_advisories("npm").is_empty());
+        let advisory: OsvAdvisory = serde_json::from_str(r#"{"id":"OSV-2024-2"}"#).unwrap();
+        assert_eq!(advisory.title(), "OSV-2024-2");
+        assert_eq!(advisory.severity(), DEFAULT_SEVERITY);
+        assert_eq!(advisory.url(), "https://osv.dev/vulnerability/OSV-2024-2");
+    }
+}
@@ -1,40 +1,110 @@
+use ahash::HashMap;
 use axum::{
-    extract::{Path, State},
-    response::Response,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
     routing::get,
 };
 use nr_core::{
     database::entities::project::{
         DBProject, ProjectDBType,
-        versions::{DBProjectVersion, history::VersionHistoryItem},
+        versions::{
+            DBProjectVersion,
+            dependencies::{DBProjectVersionDependency, ProjectDependent},
+            history::VersionHistoryItem,
+        },
     },
     repository::project::ProjectResolution,
 };
+use serde::Deserialize;
 use tracing::instrument;
-use utoipa::OpenApi;
+use utoipa::{IntoParams, OpenApi};
 use uuid::Uuid;
 
 use crate::{
-    app::{NitroRepo, authentication::Authentication},
+    app::{NitroRepo, authentication::Authentication, responses::MissingPermission},
     error::InternalError,
+    repository::{Repository, utils::can_read_repository},
     utils::ResponseBuilder,
 };
 
 #[derive(OpenApi)]
 #[openapi(
-    paths(get_project, get_project_versions, get_project_by_key),
-    components(schemas(DBProject, ProjectResolution, DBProjectVersion, VersionHistoryItem))
+    paths(
+        get_project,
+        get_project_versions,
+        get_project_by_key,
+        get_version_dependencies,
+        get_project_dependents,
+        get_artifact_dependents
+    ),
+    components(schemas(
+        DBProject,
+        ProjectResolution,
+        DBProjectVersion,
+        VersionHistoryItem,
+        DBProjectVersionDependency,
+        ProjectDependent
+    ))
 )]
 pub struct ProjectRoutes;
 pub fn project_routes() -> axum::Router<NitroRepo> {
     axum::Router::new()
         .route("/{project_id}", get(get_project))
         .route("/{project_id}/versions", get(get_project_versions))
+        .route(
+            "/{project_id}/versions/{version}/dependencies",
+            get(get_version_dependencies),
+        )
+        .route("/{project_id}/dependents", get(get_project_dependents))
+        .route(
+            "/dependents/{group_id}/{artifact_id}",
+            get(get_artifact_dependents),
+        )
         .route(
             "/by-key/{repository_id}/{project_key}",
             get(get_project_by_key),
         )
 }
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default)]
+pub struct DependentsQuery {
+    /// Include every version of a dependent. By default only the latest version of each project that is not yanked is checked
+    pub all_versions: bool,
+}
+/// Removes dependents in repositories the user can not read
+async fn readable_dependents(
+    dependents: Vec<ProjectDependent>,
+    auth: &Option<Authentication>,
+    site: &NitroRepo,
+) -> Result<Vec<ProjectDependent>, InternalError> {
+    let mut readable: HashMap<Uuid, bool> = HashMap::default();
+    let mut result = Vec::with_capacity(dependents.len());
+    for dependent in dependents {
+        let can_read = match readable.get(&dependent.repository_id) {
+            Some(can_read) => *can_read,
+            None => {
+                let can_read = match site.get_repository(dependent.repository_id) {
+                    Some(repository) => {
+                        can_read_repository(
+                            auth,
+                            repository.visibility(),
+                            repository.id(),
+                            site.as_ref(),
+                        )
+                        .await?
+                    }
+                    None => false,
+                };
+                readable.insert(dependent.repository_id, can_read);
+                can_read
+            }
+        };
+        if can_read {
+            result.push(dependent);
+        }
+    }
+    Ok(result)
+}
 
 #[utoipa::path(
     get,
@@ -114,3 +184,110 @@ pub async fn get_project_by_key(
 
     Ok(ResponseBuilder::ok().json(&project))
 }
+
+#[utoipa::path(
+    get,
+    path = "/{project_id}/versions/{version}/dependencies",
+    summary = "Get the dependencies of a version",
+    params(
+        ("project_id" = Uuid, description = "The project ID"),
+        ("version" = String, description = "The version")
+    ),
+    responses(
+        (status = 200, description = "Dependencies of the version", body = Vec<DBProjectVersionDependency>),
+        (status = 404, description = "Project or version not found"),
+        (status = 403, description = "Missing permission"),
+    ),
+)]
+#[instrument]
+pub async fn get_version_dependencies(
+    Path((project_id, version)): Path<(Uuid, String)>,
+    State(site): State<NitroRepo>,
+    auth: Option<Authentication>,
+) -> Result<Response, InternalError> {
+    let Some(project) = DBProject::find_by_id(project_id, site.as_ref()).await? else {
+        return Ok(ResponseBuilder::not_found().empty());
+    };
+    let Some(repository) = site.get_repository(project.repository_id) else {
+        return Ok(ResponseBuilder::not_found().empty());
+    };
+    if !can_read_repository(
+        &auth,
+        repository.visibility(),
+        repository.id(),
+        site.as_ref(),
+    )
+    .await?
+    {
+        return Ok(MissingPermission::ReadRepository(repository.id()).into_response());
+    }
+    let Some(version) =
+        DBProjectVersion::find_by_version_and_project(&version, project_id, site.as_ref()).await?
+    else {
+        return Ok(ResponseBuilder::not_found().empty());
+    };
+    let dependencies =
+        DBProjectVersionDependency::find_by_version(version.id, site.as_ref()).await?;
+    Ok(ResponseBuilder::ok().json(&dependencies))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{project_id}/dependents",
+    summary = "Get the projects that depend on a project",
+    params(
+        ("project_id" = Uuid, description = "The project ID"),
+        DependentsQuery
+    ),
+    responses(
+        (status = 200, description = "Versions that depend on the project", body = Vec<ProjectDependent>),
+    ),
+)]
+#[instrument]
+pub async fn get_project_dependents(
+    Path(project_id): Path<Uuid>,
+    Query(query): Query<DependentsQuery>,
+    State(site): State<NitroRepo>,
+    auth: Option<Authentication>,
+) -> Result<Response, InternalError> {
+    let dependents = ProjectDependent::find_by_dependency_project(
+        project_id,
+        !query.all_versions,
+        site.as_ref(),
+    )
+    .await?;
+    let dependents = readable_dependents(dependents, &auth, &site).await?;
+    Ok(ResponseBuilder::ok().json(&dependents))
+}
+
+#[utoipa::path(
+    get,
+    path = "/dependents/{group_id}/{artifact_id}",
+    summary = "Get the projects that depend on an artifact",
+    description = "Works for artifacts that are not hosted by this instance",
+    params(
+        ("group_id" = String, description = "The group ID"),
+        ("artifact_id" = String, description = "The artifact ID"),
+        DependentsQuery
+    ),
+    responses(
+        (status = 200, description = "Versions that depend on the artifact", body = Vec<ProjectDependent>),
+    ),
+)]
+#[instrument]
+pub async fn get_artifact_dependents(
+    Path((group_id, artifact_id)): Path<(String, String)>,
+    Query(query): Query<DependentsQuery>,
+    State(site): State<NitroRepo>,
+    auth: Option<Authentication>,
+) -> Result<Response, InternalError> {
+    let dependents = ProjectDependent::find_by_artifact(
+        &group_id,
+        &artifact_id,
+        !query.all_versions,
+        site.as_ref(),
+    )
+    .await?;
+    let dependents = readable_dependents(dependents, &auth, &site).await?;
+    Ok(ResponseBuilder::ok().json(&dependents))
+}
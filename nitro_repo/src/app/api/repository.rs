@@ -5,9 +5,12 @@ use axum::{
 };
 use management::NewRepositoryRequest;
 use nr_core::{
-    database::entities::repository::{
-        DBRepository, DBRepositoryNames, DBRepositoryNamesWithVisibility,
-        DBRepositoryWithStorageName,
+    database::entities::{
+        repository::{
+            DBRepository, DBRepositoryNames, DBRepositoryNamesWithVisibility,
+            DBRepositoryWithStorageName,
+        },
+        stages::{DBStage, DBStageFile},
     },
     repository::{
         Visibility,
@@ -31,13 +34,21 @@ use crate::{
         responses::{MissingPermission, RepositoryNotFound},
     },
     error::InternalError,
-    repository::{Repository, RepositoryTypeDescription},
+    repository::{
+        Repository, RepositoryTypeDescription,
+        maven::{
+            hosted::MavenYankRequest,
+            nitro_deploy::{NRMavenPublishSuccessFile, NRMavenPublishSuccessResponse},
+        },
+    },
     utils::ResponseBuilder,
 };
 mod browse;
 mod config;
 mod management;
+mod npm;
 mod page;
+mod stages;
 mod types;
 #[derive(OpenApi)]
 #[openapi(
@@ -56,6 +67,13 @@ mod types;
         management::update_config,
         management::get_configs_for_repository,
         management::delete_repository,
+        management::yank_maven_version,
+        npm::get_web_login,
+        npm::approve_web_login,
+        stages::list_stages,
+        stages::get_stage,
+        stages::drop_stage,
+        stages::promote_stage,
         browse::browse,
     ),
     components(schemas(
@@ -69,7 +87,15 @@ mod types;
         BrowseResponse,
         ProjectResolution,
         DBRepositoryNames,
-        DBRepositoryNamesWithVisibility
+        DBRepositoryNamesWithVisibility,
+        MavenYankRequest,
+        npm::NPMWebLoginDetails,
+        DBStage,
+        DBStageFile,
+        stages::StageDetails,
+        stages::PromoteStageRequest,
+        NRMavenPublishSuccessResponse,
+        NRMavenPublishSuccessFile
     )),
     nest(
         (path = "/page", api = RepositoryPageRoutes, tags=["repository", "page"]),
@@ -89,6 +115,8 @@ pub fn repository_routes() -> axum::Router<NitroRepo> {
         .route("/types", get(types::repository_types))
         .merge(browse::browse_routes())
         .merge(management::management_routes())
+        .merge(npm::npm_routes())
+        .merge(stages::stage_routes())
         .merge(config::config_routes())
 }
 #[derive(Debug, Serialize, ToSchema)]
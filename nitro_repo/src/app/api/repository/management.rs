@@ -26,7 +26,10 @@ use crate::{
         responses::{InvalidRepositoryConfig, MissingPermission, RepositoryNotFound},
     },
     error::InternalError,
-    repository::Repository,
+    repository::{
+        DynRepository, Repository,
+        maven::{MavenRepository, hosted::MavenYankRequest},
+    },
     utils::{ResponseBuilder, conflict::ConflictResponse},
 };
 pub fn management_routes() -> Router<NitroRepo> {
@@ -36,6 +39,7 @@ pub fn management_routes() -> Router<NitroRepo> {
         .route("/{repository_id}/config/{key}", put(update_config))
         .route("/{repository_id}/config/{key}", get(get_config))
         .route("/{repository_id}", delete(delete_repository))
+        .route("/{repository_id}/maven/yank", put(yank_maven_version))
 }
 #[derive(Deserialize, ToSchema, Debug)]
 pub struct NewRepositoryRequest {
@@ -323,3 +327,56 @@ pub async fn delete_repository(
         .body(Body::empty())
         .unwrap())
 }
+/// Yanks or restores a version of a Maven hosted repository.
+///
+/// Yanked versions keep their files but are removed from `maven-metadata.xml`
+#[utoipa::path(
+    put,
+    path = "/{repository_id}/maven/yank",
+    request_body = MavenYankRequest,
+    params(
+        ("repository_id" = Uuid, Path, description = "The Repository ID"),
+    ),
+    responses(
+        (status = 204, description = "Version Yanked or Restored"),
+        (status = 400, description = "Repository is not a Maven hosted repository"),
+        (status = 403, description = "Yanking is not allowed for this repository"),
+        (status = 404, description = "Repository or version not found"),
+    )
+)]
+#[instrument]
+pub async fn yank_maven_version(
+    State(site): State<NitroRepo>,
+    auth: Authentication,
+    Path(repository): Path<Uuid>,
+    Json(request): Json<MavenYankRequest>,
+) -> Result<Response, InternalError> {
+    if !auth
+        .has_action(RepositoryActions::Edit, repository, &site.database)
+        .await?
+    {
+        return Ok(MissingPermission::EditRepository(repository).into_response());
+    }
+    let Some(loaded_repository) = site.get_repository(repository) else {
+        return Ok(RepositoryNotFound::Uuid(repository).into_response());
+    };
+    let DynRepository::Maven(MavenRepository::Hosted(hosted)) = loaded_repository else {
+        return Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body("Repository is not a Maven hosted repository".into())
+            .unwrap());
+    };
+    if !hosted.push_rules.read().yanking_allowed {
+        return Ok(Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body("Yanking is not allowed for this repository".into())
+            .unwrap());
+    }
+    if !hosted.set_version_yanked(&request).await? {
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("Version not found".into())
+            .unwrap());
+    }
+    Ok(ResponseBuilder::no_content().empty())
+}
@@ -0,0 +1,123 @@
+use axum::{
+    Router,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use http::StatusCode;
+use serde::Serialize;
+use tracing::{info, instrument};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    app::{
+        NitroRepo, authentication::OnlySessionAllowedAuthentication, responses::RepositoryNotFound,
+    },
+    error::InternalError,
+    repository::{
+        DynRepository, Repository,
+        npm::{NPMRegistry, hosted::NPMHostedRegistry},
+    },
+    utils::ResponseBuilder,
+};
+pub fn npm_routes() -> Router<NitroRepo> {
+    Router::new().route(
+        "/{repository_id}/npm/login/{login_id}",
+        get(get_web_login).post(approve_web_login),
+    )
+}
+/// A login started with `npm login --auth-type=web`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NPMWebLoginDetails {
+    pub repository_id: Uuid,
+    pub repository_name: String,
+    /// The hostname of the computer npm is running on
+    pub hostname: Option<String>,
+}
+fn npm_hosted_repository(
+    site: &NitroRepo,
+    repository: Uuid,
+) -> Result<NPMHostedRegistry, Response> {
+    let Some(loaded_repository) = site.get_repository(repository) else {
+        return Err(RepositoryNotFound::Uuid(repository).into_response());
+    };
+    let DynRepository::NPM(NPMRegistry::Hosted(hosted)) = loaded_repository else {
+        return Err(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body("Repository is not a NPM hosted repository".into())
+            .unwrap());
+    };
+    Ok(hosted)
+}
+fn web_login_not_found() -> Response {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body("Login not found or expired".into())
+        .unwrap()
+}
+/// Gets a pending npm web login so the user can check it before approving it
+#[utoipa::path(
+    get,
+    path = "/{repository_id}/npm/login/{login_id}",
+    params(
+        ("repository_id" = Uuid, Path, description = "The Repository ID"),
+        ("login_id" = Uuid, Path, description = "The ID from the login url"),
+    ),
+    responses(
+        (status = 200, description = "Pending Login", body = NPMWebLoginDetails),
+        (status = 400, description = "Repository is not a NPM hosted repository"),
+        (status = 404, description = "Repository or login not found"),
+    )
+)]
+#[instrument]
+pub async fn get_web_login(
+    State(site): State<NitroRepo>,
+    // Pending logins are only shown to users that are logged in to the site
+    _auth: OnlySessionAllowedAuthentication,
+    Path((repository, login_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, InternalError> {
+    let hosted = match npm_hosted_repository(&site, repository) {
+        Ok(hosted) => hosted,
+        Err(response) => return Ok(response),
+    };
+    let Some(login) = hosted.web_logins.get_by_login_id(login_id) else {
+        return Ok(web_login_not_found());
+    };
+    let details = NPMWebLoginDetails {
+        repository_id: hosted.id(),
+        repository_name: hosted.name(),
+        hostname: login.hostname,
+    };
+    Ok(ResponseBuilder::ok().json(&details))
+}
+/// Approves a pending npm web login. The CLI receives a token for the repository the next time it polls
+#[utoipa::path(
+    post,
+    path = "/{repository_id}/npm/login/{login_id}",
+    params(
+        ("repository_id" = Uuid, Path, description = "The Repository ID"),
+        ("login_id" = Uuid, Path, description = "The ID from the login url"),
+    ),
+    responses(
+        (status = 204, description = "Login Approved"),
+        (status = 400, description = "Repository is not a NPM hosted repository"),
+        (status = 404, description = "Repository or login not found"),
+    )
+)]
+#[instrument]
+pub async fn approve_web_login(
+    State(site): State<NitroRepo>,
+    auth: OnlySessionAllowedAuthentication,
+    Path((repository, login_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, InternalError> {
+    let hosted = match npm_hosted_repository(&site, repository) {
+        Ok(hosted) => hosted,
+        Err(response) => return Ok(response),
+    };
+    if !hosted.web_logins.approve(login_id, auth.id) {
+        return Ok(web_login_not_found());
+    }
+    info!(user = auth.id, ?repository, "Approved npm web login");
+    Ok(ResponseBuilder::no_content().empty())
+}
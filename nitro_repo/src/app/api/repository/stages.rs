@@ -0,0 +1,225 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use http::StatusCode;
+use nr_core::{
+    database::entities::stages::{DBStage, DBStageFile},
+    user::permissions::{HasPermissions, RepositoryActions},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    app::{
+        NitroRepo,
+        authentication::Authentication,
+        responses::{MissingPermission, RepositoryNotFound},
+    },
+    error::InternalError,
+    repository::{
+        DynRepository, StagingManager,
+        maven::{
+            MavenRepository,
+            nitro_deploy::{NRMavenPublishSuccessResponse, NewNRMavenDeploy},
+        },
+    },
+    utils::ResponseBuilder,
+};
+pub fn stage_routes() -> Router<NitroRepo> {
+    Router::new()
+        .route("/{repository_id}/stages", get(list_stages))
+        .route(
+            "/{repository_id}/stages/{stage_id}",
+            get(get_stage).delete(drop_stage),
+        )
+        .route(
+            "/{repository_id}/stages/{stage_id}/promote",
+            post(promote_stage),
+        )
+}
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StageDetails {
+    pub stage: DBStage,
+    pub files: Vec<DBStageFile>,
+}
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct PromoteStageRequest {
+    /// The repository to publish the stage into. Defaults to the repository of the stage
+    pub target_repository: Option<Uuid>,
+}
+fn stage_not_found() -> Response {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body("Stage not found".into())
+        .unwrap()
+}
+/// Lists the stages of a repository that have not been published or dropped
+#[utoipa::path(
+    get,
+    path = "/{repository_id}/stages",
+    params(
+        ("repository_id" = Uuid, Path, description = "The Repository ID"),
+    ),
+    responses(
+        (status = 200, description = "Stages of the repository", body = [DBStage]),
+        (status = 403, description = "Missing permission"),
+    )
+)]
+#[instrument]
+pub async fn list_stages(
+    State(site): State<NitroRepo>,
+    auth: Authentication,
+    Path(repository): Path<Uuid>,
+) -> Result<Response, InternalError> {
+    if !auth
+        .has_action(RepositoryActions::Edit, repository, &site.database)
+        .await?
+    {
+        return Ok(MissingPermission::EditRepository(repository).into_response());
+    }
+    let stages = DBStage::get_all_stages_for_repository(repository, &site.database).await?;
+    Ok(ResponseBuilder::ok().json(&stages))
+}
+/// A stage and the files that have been uploaded to it
+#[utoipa::path(
+    get,
+    path = "/{repository_id}/stages/{stage_id}",
+    params(
+        ("repository_id" = Uuid, Path, description = "The Repository ID"),
+        ("stage_id" = Uuid, Path, description = "The Stage ID"),
+    ),
+    responses(
+        (status = 200, description = "Stage Details", body = StageDetails),
+        (status = 403, description = "Missing permission"),
+        (status = 404, description = "Stage not found"),
+    )
+)]
+#[instrument]
+pub async fn get_stage(
+    State(site): State<NitroRepo>,
+    auth: Authentication,
+    Path((repository, stage_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, InternalError> {
+    if !auth
+        .has_action(RepositoryActions::Edit, repository, &site.database)
+        .await?
+    {
+        return Ok(MissingPermission::EditRepository(repository).into_response());
+    }
+    let Some(stage) = DBStage::get_stage_by_id(stage_id, repository, &site.database).await? else {
+        return Ok(stage_not_found());
+    };
+    let files = stage.get_files(&site.database).await?;
+    Ok(ResponseBuilder::ok().json(&StageDetails { stage, files }))
+}
+/// Deletes a stage and all of its files without publishing it
+#[utoipa::path(
+    delete,
+    path = "/{repository_id}/stages/{stage_id}",
+    params(
+        ("repository_id" = Uuid, Path, description = "The Repository ID"),
+        ("stage_id" = Uuid, Path, description = "The Stage ID"),
+    ),
+    responses(
+        (status = 204, description = "Stage Dropped"),
+        (status = 403, description = "Missing permission"),
+        (status = 404, description = "Stage not found"),
+    )
+)]
+#[instrument]
+pub async fn drop_stage(
+    State(site): State<NitroRepo>,
+    auth: Authentication,
+    Path((repository, stage_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, InternalError> {
+    if !auth
+        .has_action(RepositoryActions::Edit, repository, &site.database)
+        .await?
+    {
+        return Ok(MissingPermission::EditRepository(repository).into_response());
+    }
+    let Some(stage) = DBStage::get_stage_by_id(stage_id, repository, &site.database).await? else {
+        return Ok(stage_not_found());
+    };
+    StagingManager::new(repository, site.clone())
+        .delete_stage(&stage)
+        .await?;
+    info!(?stage_id, "Dropped Stage");
+    Ok(ResponseBuilder::no_content().empty())
+}
+/// Publishes a stage into its repository or into another Maven hosted repository.
+///
+/// Requires Edit on the target repository. Promoting into another repository also requires Read on the repository of the stage.
+/// The version is published as the user that created the stage
+#[utoipa::path(
+    post,
+    path = "/{repository_id}/stages/{stage_id}/promote",
+    request_body = PromoteStageRequest,
+    params(
+        ("repository_id" = Uuid, Path, description = "The Repository ID"),
+        ("stage_id" = Uuid, Path, description = "The Stage ID"),
+    ),
+    responses(
+        (status = 200, description = "Stage Published", body = NRMavenPublishSuccessResponse),
+        (status = 400, description = "The target is not a Maven hosted repository or files are missing"),
+        (status = 403, description = "Missing permission or rejected by the push rules"),
+        (status = 404, description = "Stage or repository not found"),
+        (status = 409, description = "A file already exists and overwriting is not allowed"),
+    )
+)]
+#[instrument]
+pub async fn promote_stage(
+    State(site): State<NitroRepo>,
+    auth: Authentication,
+    Path((repository, stage_id)): Path<(Uuid, Uuid)>,
+    Json(request): Json<PromoteStageRequest>,
+) -> Result<Response, InternalError> {
+    let target = request.target_repository.unwrap_or(repository);
+    if !auth
+        .has_action(RepositoryActions::Edit, target, &site.database)
+        .await?
+    {
+        return Ok(MissingPermission::EditRepository(target).into_response());
+    }
+    if target != repository
+        && !auth
+            .has_action(RepositoryActions::Read, repository, &site.database)
+            .await?
+    {
+        return Ok(MissingPermission::ReadRepository(repository).into_response());
+    }
+    let Some(stage) = DBStage::get_stage_by_id(stage_id, repository, &site.database).await? else {
+        return Ok(stage_not_found());
+    };
+    let Some(loaded_repository) = site.get_repository(target) else {
+        return Ok(RepositoryNotFound::Uuid(target).into_response());
+    };
+    let DynRepository::Maven(MavenRepository::Hosted(hosted)) = loaded_repository else {
+        return Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body("Repository is not a Maven hosted repository".into())
+            .unwrap());
+    };
+    let Ok(deploy) = serde_json::from_value::<NewNRMavenDeploy>(stage.stage_state.0.clone()) else {
+        return Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body("Stage is not a Maven deploy".into())
+            .unwrap());
+    };
+    match hosted.publish_stage(&stage, &deploy, Some(&*auth)).await? {
+        Ok(published) => {
+            info!(?stage_id, ?target, "Promoted Stage");
+            Ok(ResponseBuilder::ok().json(&published))
+        }
+        Err(rejection) => Ok(Response::builder()
+            .status(rejection.status_code())
+            .body(rejection.to_string().into())
+            .unwrap()),
+    }
+}
@@ -1,11 +1,12 @@
 use axum::{
     Json,
     body::Body,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     response::Response,
     routing::{delete, get, post},
 };
 use axum_extra::{TypedHeader, headers::UserAgent};
+use chrono::{DateTime, Duration, FixedOffset, Local};
 use http::StatusCode;
 use nr_core::{
     database::entities::user::{
@@ -16,7 +17,7 @@ use nr_core::{
 };
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
 use crate::{
@@ -41,6 +42,9 @@ pub struct NewAuthTokenRequest {
     pub scopes: Vec<NRScope>,
     #[serde(default)]
     pub repository_scopes: Vec<NewRepositoryScope>,
+    /// When the token stops working. None means the token never expires
+    #[serde(default)]
+    pub expires_at: Option<DateTime<FixedOffset>>,
 }
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct NewRepositoryScope {
@@ -73,6 +77,15 @@ async fn create(
             .body("No Scopes Provided".into())
             .unwrap());
     }
+    if new_token
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Local::now().fixed_offset())
+    {
+        return Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body("Expiration must be in the future".into())
+            .unwrap());
+    }
     let repositories: Vec<(Uuid, Vec<RepositoryActions>)> = new_token
         .repository_scopes
         .into_iter()
@@ -85,25 +98,43 @@ async fn create(
         source,
         scopes: new_token.scopes,
         repositories,
+        expires_at: new_token.expires_at,
     };
     let (id, token) = new_token.insert(site.as_ref()).await?;
     let response = NewAuthTokenResponse { id, token };
 
     Ok(ResponseBuilder::ok().json(&response))
 }
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default)]
+pub struct ListTokensQuery {
+    /// Only return tokens that have not been used in this many days. Tokens that have never been used are included
+    pub unused_for_days: Option<u32>,
+}
 #[utoipa::path(
     get,
     path = "/token/list",
+    params(ListTokensQuery),
     responses(
-        (status = 200, description = "A New Auth Token was created", body=[AuthTokenFullResponse]),
+        (status = 200, description = "The tokens of the current user including when they were last used", body=[AuthTokenFullResponse]),
     ),
 )]
 #[instrument]
 async fn list(
     auth: OnlySessionAllowedAuthentication,
     State(site): State<NitroRepo>,
+    Query(query): Query<ListTokensQuery>,
 ) -> Result<Response, InternalError> {
-    let tokens = AuthTokenFullResponse::get_all_for_user(auth.get_id(), site.as_ref()).await?;
+    let mut tokens = AuthTokenFullResponse::get_all_for_user(auth.get_id(), site.as_ref()).await?;
+    if let Some(days) = query.unused_for_days {
+        let cutoff = Local::now().fixed_offset() - Duration::days(days as i64);
+        tokens.retain(|token| {
+            token
+                .token
+                .last_used_at
+                .is_none_or(|last_used| last_used < cutoff)
+        });
+    }
 
     Ok(ResponseBuilder::ok().json(&tokens))
 }
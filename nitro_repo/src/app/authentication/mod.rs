@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 use std::fmt::{Debug, Display};
+use std::net::IpAddr;
 use std::ops::Deref;
 
 use axum::extract::{FromRef, FromRequestParts, OptionalFromRequestParts};
@@ -18,18 +19,20 @@ use session::{Session, SessionError};
 use sqlx::PgPool;
 use strum::EnumIs;
 use thiserror::Error;
-use tracing::{error, instrument, warn};
+use tracing::{debug, error, instrument, warn};
 use utoipa::ToSchema;
 
 pub mod header;
 
 use crate::utils::api_error_response::APIErrorResponse;
+use crate::utils::ip_addr::ConnectionIpAddr;
 use crate::utils::{IntoErrorResponse, ResponseBuilder};
 
 use super::NitroRepo;
 
 pub mod layer;
 pub mod session;
+pub mod token_cleaner;
 pub mod ws;
 
 #[derive(Error, Debug)]
@@ -225,7 +228,9 @@ where
                 return Err(AuthenticationError::Unauthorized);
             }
             AuthenticationRaw::AuthToken(token) => {
-                let (user, auth_token) = get_user_and_auth_token(&token, &repo.database).await?;
+                let ip_address = request_ip_address(parts, &repo).await;
+                let (user, auth_token) =
+                    get_user_and_auth_token(&token, ip_address, &repo.database).await?;
                 Authentication::AuthToken(auth_token, user)
             }
             AuthenticationRaw::Session(session) => {
@@ -262,7 +267,9 @@ where
                 return Ok(None);
             }
             AuthenticationRaw::AuthToken(token) => {
-                let (user, auth_token) = get_user_and_auth_token(&token, &repo.database).await?;
+                let ip_address = request_ip_address(parts, &repo).await;
+                let (user, auth_token) =
+                    get_user_and_auth_token(&token, ip_address, &repo.database).await?;
                 Authentication::AuthToken(auth_token, user)
             }
             AuthenticationRaw::Session(session) => {
@@ -383,19 +390,56 @@ pub async fn verify_login(
     Ok(user.into())
 }
 
+/// Gets the user and auth token. Expired or inactive tokens are rejected.
+/// So are tokens used from an IP address outside of their CIDR whitelist.
+///
+/// The usage of the token is recorded in the background.
 #[instrument(skip(token, database), fields(project_module = "Authentication"))]
 pub async fn get_user_and_auth_token(
     token: &str,
+    ip_address: Option<IpAddr>,
     database: &PgPool,
 ) -> Result<(UserSafeData, AuthToken), AuthenticationError> {
     let auth_token = AuthToken::get_by_token(token, database)
         .await?
         .ok_or(AuthenticationError::Unauthorized)?;
+    if !auth_token.allows_ip_address(ip_address) {
+        debug!(
+            ?ip_address,
+            token_id = auth_token.id,
+            "IP address not in the token's CIDR whitelist"
+        );
+        return Err(AuthenticationError::Unauthorized);
+    }
     let user = UserSafeData::get_by_id(auth_token.user_id, database)
         .await?
         .ok_or(AuthenticationError::Unauthorized)?;
+    record_auth_token_usage(&auth_token, ip_address, database);
     Ok((user, auth_token))
 }
+/// Updates the last used time and ip address of the token.
+///
+/// This is done in a separate task so the request is not slowed down by the update.
+pub fn record_auth_token_usage(token: &AuthToken, ip_address: Option<IpAddr>, database: &PgPool) {
+    let token_id = token.id;
+    let ip_address = ip_address.map(|ip| ip.to_string());
+    let database = database.clone();
+    tokio::spawn(async move {
+        if let Err(err) =
+            AuthToken::update_last_used(token_id, ip_address.as_deref(), &database).await
+        {
+            warn!(?err, ?token_id, "Failed to record auth token usage");
+        }
+    });
+}
+/// The IP address of the client. None if it can not be determined
+pub async fn request_ip_address(parts: &mut Parts, site: &NitroRepo) -> Option<IpAddr> {
+    <ConnectionIpAddr as OptionalFromRequestParts<NitroRepo>>::from_request_parts(parts, site)
+        .await
+        .ok()
+        .flatten()
+        .map(|ip| ip.0)
+}
 pub mod password {
     use argon2::{
         Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
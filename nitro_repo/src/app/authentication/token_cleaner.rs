@@ -0,0 +1,47 @@
+use nr_core::database::entities::user::auth_token::AuthToken;
+use tokio::task::JoinHandle;
+use tracing::{
+    Instrument, Level, debug, error,
+    field::{Empty, display},
+    info, span,
+};
+
+use crate::app::NitroRepo;
+
+/// Periodically deactivates auth tokens that have expired.
+///
+/// Expired tokens are already rejected when used. This keeps the `active` column accurate for the token list.
+pub fn start_auth_token_cleaner(site: NitroRepo) -> JoinHandle<()> {
+    let how_often = site
+        .general_security_settings
+        .auth_token_cleanup_interval
+        .to_std()
+        .expect("Duration is too large");
+    debug!("Starting Auth Token Cleaner with interval: {:?}", how_often);
+    tokio::spawn(async move {
+        loop {
+            let span = span!(
+                Level::INFO,
+                "Auth Token Cleaner",
+                tokens.deactivated = Empty,
+                token.cleaner.error = Empty
+            );
+            let result = AuthToken::deactivate_expired(&site.database)
+                .instrument(span.clone())
+                .await;
+            let sleep_for = match result {
+                Ok(value) => {
+                    info!(parent: &span, "Deactivated {} expired auth tokens", value);
+                    span.record("tokens.deactivated", value);
+                    how_often
+                }
+                Err(err) => {
+                    error!(parent: &span, "Failed to deactivate expired auth tokens: {:?}", err);
+                    span.record("token.cleaner.error", display(err));
+                    how_often / 2
+                }
+            };
+            tokio::time::sleep(sleep_for).await
+        }
+    })
+}
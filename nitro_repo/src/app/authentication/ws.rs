@@ -28,7 +28,9 @@ impl WebSocketAuthenticationMessage {
         match self {
             WebSocketAuthenticationMessage::AuthToken(token) => {
                 Span::current().record("login.type", "auth_token");
-                let (user, auth_token) = get_user_and_auth_token(token, &site.database).await?;
+                // Websocket messages do not have access to the connection information
+                let (user, auth_token) =
+                    get_user_and_auth_token(token, None, &site.database).await?;
                 debug!(?user, "User Login Via Auth Token");
                 let result = WebSocketAuthentication::AuthToken {
                     token: auth_token,
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 
+use chrono::Duration;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -7,12 +8,22 @@ use utoipa::ToSchema;
 pub struct SecuritySettings {
     pub allow_basic_without_tokens: bool,
     pub password_rules: Option<PasswordRules>,
+    /// How often expired auth tokens are deactivated
+    #[serde(
+        with = "nr_core::utils::duration_serde::as_seconds",
+        default = "default_auth_token_cleanup_interval"
+    )]
+    pub auth_token_cleanup_interval: Duration,
+}
+fn default_auth_token_cleanup_interval() -> Duration {
+    Duration::hours(1)
 }
 impl Default for SecuritySettings {
     fn default() -> Self {
         Self {
             allow_basic_without_tokens: false,
             password_rules: Some(PasswordRules::default()),
+            auth_token_cleanup_interval: default_auth_token_cleanup_interval(),
         }
     }
 }
@@ -72,7 +72,8 @@ pub trait Email: Serialize + Debug {
 pub struct EmailAccess {
     queue: Sender<EmailRequest>,
     message_builder: MessageBuilder,
-    email_handlebars: Handlebars<'static>,
+    /// Shared with the other pages rendered by the server
+    email_handlebars: Arc<Handlebars<'static>>,
 }
 impl EmailAccess {
     /// Adds a new Email to the queue to be sent
@@ -88,7 +89,7 @@ impl EmailAccess {
             warn!("Email Queue Error: {}", error);
         };
     }
-    pub fn get_handlebars(&self) -> &Handlebars<'static> {
+    pub fn get_handlebars(&self) -> &Arc<Handlebars<'static>> {
         &self.email_handlebars
     }
     #[inline]
@@ -153,13 +154,19 @@ pub struct EmailService {
     pub handle: tokio::task::JoinHandle<()>,
 }
 impl EmailService {
-    pub async fn start(email: Option<EmailSetting>) -> io::Result<(EmailAccess, Self)> {
+    /// Starts the email service
+    ///
+    /// The email templates are registered into `handlebars`.
+    pub async fn start(
+        email: Option<EmailSetting>,
+        handlebars: Handlebars<'static>,
+    ) -> io::Result<(EmailAccess, Self)> {
         match email {
-            Some(email) => Self::start_inner(email).await,
-            None => Ok(Self::no_email()),
+            Some(email) => Self::start_inner(email, handlebars).await,
+            None => Ok(Self::no_email(handlebars)),
         }
     }
-    fn no_email() -> (EmailAccess, Self) {
+    fn no_email(handlebars: Handlebars<'static>) -> (EmailAccess, Self) {
         let notify = Arc::new(Notify::new());
         let service_notify = notify.clone();
         let (sender, receiver) = flume::bounded(100);
@@ -169,7 +176,7 @@ impl EmailService {
         let access = EmailAccess {
             queue: sender,
             message_builder: Message::builder(),
-            email_handlebars: Handlebars::new(),
+            email_handlebars: Arc::new(handlebars),
         };
         let service = Self {
             notify_shutdown: notify,
@@ -177,7 +184,10 @@ impl EmailService {
         };
         (access, service)
     }
-    async fn start_inner(email: EmailSetting) -> io::Result<(EmailAccess, Self)> {
+    async fn start_inner(
+        email: EmailSetting,
+        mut email_handlebars: Handlebars<'static>,
+    ) -> io::Result<(EmailAccess, Self)> {
         let notify = Arc::new(Notify::new());
         let service_notify = notify.clone();
         let transport = Self::build_connection(email.clone()).await;
@@ -187,7 +197,6 @@ impl EmailService {
             message_builder = message_builder.reply_to(reply_to.parse().unwrap());
         }
 
-        let mut email_handlebars = Handlebars::new();
         email_handlebars
             .register_embed_templates::<EmailTemplates>()
             .map_err(|e| {
@@ -207,7 +216,7 @@ impl EmailService {
         let access_point = EmailAccess {
             queue: sender,
             message_builder,
-            email_handlebars,
+            email_handlebars: Arc::new(email_handlebars),
         };
         let service = Self {
             notify_shutdown: notify,
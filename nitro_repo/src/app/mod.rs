@@ -9,6 +9,7 @@ use config::{Mode, PasswordRules, SecuritySettings, SiteSetting};
 use derive_more::{AsRef, derive::Deref};
 use email::EmailSetting;
 use email_service::{EmailAccess, EmailService};
+use handlebars::Handlebars;
 use http::{HeaderName, Uri};
 pub mod frontend;
 pub mod resources;
@@ -46,7 +47,7 @@ use uuid::Uuid;
 pub mod open_api;
 use crate::{
     repository::{
-        DynRepository, RepositoryType, StagingConfig,
+        DynRepository, RepositoryPages, RepositoryType, StagingConfig,
         maven::{MavenPushRulesConfigType, MavenRepositoryConfigType, MavenRepositoryType},
         npm::{NPMRegistryConfigType, NpmRegistryType},
         repo_tracing::RepositoryMetricsMeter,
@@ -111,6 +112,8 @@ impl From<(String, String)> for RepositoryStorageName {
 #[derive(Debug, Default)]
 pub struct InternalServices {
     pub session_cleaner: Option<JoinHandle<()>>,
+    pub auth_token_cleaner: Option<JoinHandle<()>>,
+    pub stage_cleaner: Option<JoinHandle<()>>,
     pub email: Option<EmailService>,
 }
 pub struct NitroRepoInner {
@@ -122,6 +125,8 @@ pub struct NitroRepoInner {
     #[cfg(feature = "frontend")]
     pub frontend: frontend::HostedFrontend,
     pub staging_config: StagingConfig,
+    /// Pages rendered by the repository handlers. Such as directory listings
+    pub repository_pages: RepositoryPages,
     services: Mutex<InternalServices>,
     pub suggested_local_storage_path: PathBuf,
 }
@@ -140,6 +145,8 @@ macro_rules! take_service {
 impl NitroRepoInner {
     take_service! {
         take_session_cleaner => session_cleaner -> JoinHandle<()>,
+        take_auth_token_cleaner => auth_token_cleaner -> JoinHandle<()>,
+        take_stage_cleaner => stage_cleaner -> JoinHandle<()>,
         take_email => email -> EmailService
     }
     /// Notifies services that have waiters that the application is shutting down
@@ -221,7 +228,7 @@ impl HasForwardedHeader for NitroRepo {
 }
 impl NitroRepo {
     #[instrument]
-    async fn load_database(database: DatabaseConfig) -> anyhow::Result<PgPool> {
+    pub(crate) async fn load_database(database: DatabaseConfig) -> anyhow::Result<PgPool> {
         info!(?database, "Connecting to database");
         let options = database.try_into()?;
         info!(?options, "Database connection options");
@@ -255,7 +262,10 @@ impl NitroRepo {
         };
         let mut services = InternalServices::default();
 
-        let (email_access, service) = EmailService::start(email_settings).await?;
+        let mut handlebars = Handlebars::new();
+        RepositoryPages::register_templates(&mut handlebars)
+            .context("Failed to load repository page templates")?;
+        let (email_access, service) = EmailService::start(email_settings, handlebars).await?;
         services.email = Some(service);
         let suggested_local_storage_path = if let Some(path) = suggested_local_storage_path {
             path
@@ -269,6 +279,7 @@ impl NitroRepo {
             name_lookup_table: Mutex::new(HashMap::new()),
             general_security_settings: security,
             staging_config,
+            repository_pages: RepositoryPages::new(email_access.get_handlebars().clone()),
             services: Mutex::new(services),
             #[cfg(feature = "frontend")]
             frontend: frontend::HostedFrontend::new(site.frontend_path)?,
@@ -379,6 +390,12 @@ impl NitroRepo {
         if let Some(handle) = session_cleaner {
             handle.abort();
         }
+        if let Some(handle) = self.inner.take_auth_token_cleaner() {
+            handle.abort();
+        }
+        if let Some(handle) = self.inner.take_stage_cleaner() {
+            handle.abort();
+        }
     }
     pub fn get_repository_config_type(
         &self,
@@ -489,6 +506,18 @@ impl NitroRepo {
             info!("Session cleaner started");
         }
     }
+    fn start_auth_token_cleaner(&self) {
+        let handle = authentication::token_cleaner::start_auth_token_cleaner(self.clone());
+        let mut services = self.inner.services.lock();
+        services.auth_token_cleaner = Some(handle);
+        info!("Auth token cleaner started");
+    }
+    fn start_stage_cleaner(&self) {
+        let handle = crate::repository::start_stage_cleaner(self.clone());
+        let mut services = self.inner.services.lock();
+        services.stage_cleaner = Some(handle);
+        info!("Stage cleaner started");
+    }
 }
 
 pub type NitroRepoState = State<NitroRepo>;
@@ -64,6 +64,8 @@ pub(crate) async fn start(config_path: Option<PathBuf>) -> anyhow::Result<()> {
     .context("Unable to Initialize Website Core")?;
 
     site.start_session_cleaner();
+    site.start_auth_token_cleaner();
+    site.start_stage_cleaner();
 
     let cloned_site = site.clone();
     let auth_layer = AuthenticationLayer::from(site.clone());
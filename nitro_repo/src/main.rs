@@ -6,11 +6,13 @@ use std::{
 use app::config::NitroRepoConfig;
 use clap::{Parser, Subcommand};
 use config_editor::ConfigSection;
+mod advisories;
 pub mod app;
 mod config_editor;
 pub mod error;
 mod exporter;
 pub mod logging;
+mod repair;
 pub mod repository;
 pub mod utils;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
@@ -67,6 +69,33 @@ enum SubCommands {
         export: ExportOptions,
         location: PathBuf,
     },
+    /// Import security advisories in the OSV format. They are used to answer `npm audit`
+    ///
+    /// Importing an advisory again replaces it. Withdrawn advisories are removed
+    ImportAdvisories {
+        /// The nitro-repo config file
+        #[clap(short, long)]
+        config: Option<PathBuf>,
+        /// The ecosystem to import
+        #[clap(short, long, default_value = "npm")]
+        ecosystem: String,
+        /// A JSON file, a directory of JSON files or a zip of JSON files.
+        ///
+        /// Such as https://osv-vulnerabilities.storage.googleapis.com/npm/all.zip
+        path: PathBuf,
+    },
+    /// Rebuilds every maven-metadata.xml file of a Maven hosted repository from the database
+    ///
+    /// The server should be stopped while this runs
+    RebuildMavenMetadata {
+        /// The nitro-repo config file
+        #[clap(short, long)]
+        config: Option<PathBuf>,
+        /// The name of the storage the repository is in
+        storage: String,
+        /// The name of the repository
+        repository: String,
+    },
 }
 fn main() -> anyhow::Result<()> {
     // For Some Reason Lettre fails if this is not installed
@@ -92,6 +121,16 @@ fn main() -> anyhow::Result<()> {
             ExportOptions::RepositoryTypes => exporter::export_repository_types(location),
             ExportOptions::OpenAPI => exporter::export_openapi(location),
         },
+        SubCommands::ImportAdvisories {
+            config,
+            ecosystem,
+            path,
+        } => advisories::import(config, path, ecosystem),
+        SubCommands::RebuildMavenMetadata {
+            config,
+            storage,
+            repository,
+        } => repair::rebuild_maven_metadata(config, storage, repository),
 
         SubCommands::Config { config, section } => {
             let tokio = tokio::runtime::Builder::new_current_thread()
@@ -0,0 +1,70 @@
+//! Rebuilds files the server generates from the database.
+//!
+//! The server should be stopped while a repair runs. It opens the same sessions database and does not share the server's locks
+use std::path::PathBuf;
+
+use anyhow::Context;
+
+use crate::{
+    app::{
+        NitroRepo, RepositoryStorageName,
+        config::{NitroRepoConfig, load_config},
+    },
+    repository::{DynRepository, maven::MavenRepository},
+};
+/// Rebuilds every `maven-metadata.xml` of a Maven hosted repository
+pub fn rebuild_maven_metadata(
+    config: Option<PathBuf>,
+    storage: String,
+    repository: String,
+) -> anyhow::Result<()> {
+    let NitroRepoConfig {
+        mode,
+        suggested_local_storage_path,
+        database,
+        sessions,
+        site,
+        security,
+        staging,
+        ..
+    } = load_config(config)?;
+    let tokio = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    tokio.block_on(async move {
+        // Emails are never sent by a repair
+        let nitro_repo = NitroRepo::new(
+            mode,
+            site,
+            security,
+            sessions,
+            staging,
+            None,
+            database,
+            suggested_local_storage_path,
+        )
+        .await
+        .context("Unable to Initialize Website Core")?;
+        let name = RepositoryStorageName::from((storage, repository));
+        let Some(loaded_repository) = nitro_repo.get_repository_from_names(&name).await? else {
+            anyhow::bail!(
+                "Repository {}/{} not found",
+                name.storage_name,
+                name.repository_name
+            );
+        };
+        let DynRepository::Maven(MavenRepository::Hosted(hosted)) = loaded_repository else {
+            anyhow::bail!(
+                "{}/{} is not a Maven hosted repository",
+                name.storage_name,
+                name.repository_name
+            );
+        };
+        let report = hosted.rebuild_all_metadata().await?;
+        println!(
+            "Rebuilt the metadata of {} artifacts, {} SNAPSHOT versions and {} groups",
+            report.artifacts, report.snapshot_versions, report.groups
+        );
+        Ok(())
+    })
+}
@@ -0,0 +1,149 @@
+use bytes::Bytes;
+use nr_core::storage::StoragePath;
+use sha2::Digest;
+use thiserror::Error;
+
+/// Checksum files published next to Maven artifacts. Such as `tms-1.0.0.jar.sha1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MavenChecksumType {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+impl MavenChecksumType {
+    pub const ALL: [MavenChecksumType; 4] = [
+        MavenChecksumType::Md5,
+        MavenChecksumType::Sha1,
+        MavenChecksumType::Sha256,
+        MavenChecksumType::Sha512,
+    ];
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MavenChecksumType::Md5 => "md5",
+            MavenChecksumType::Sha1 => "sha1",
+            MavenChecksumType::Sha256 => "sha256",
+            MavenChecksumType::Sha512 => "sha512",
+        }
+    }
+    /// Returns the checksum type if the path is a checksum file
+    pub fn from_path(path: &StoragePath) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|checksum_type| path.has_extension(&format!(".{}", checksum_type.extension())))
+    }
+    /// The path of the checksum file for the artifact
+    pub fn checksum_path(&self, artifact: &StoragePath) -> StoragePath {
+        StoragePath::from(format!("{}.{}", artifact, self.extension()))
+    }
+    /// The lowercase hex digest. The format used by Maven checksum files
+    pub fn digest(&self, bytes: &[u8]) -> String {
+        match self {
+            MavenChecksumType::Md5 => hex_digest::<md5::Md5>(bytes),
+            MavenChecksumType::Sha1 => hex_digest::<sha1::Sha1>(bytes),
+            MavenChecksumType::Sha256 => hex_digest::<sha2::Sha256>(bytes),
+            MavenChecksumType::Sha512 => hex_digest::<sha2::Sha512>(bytes),
+        }
+    }
+}
+fn hex_digest<D: Digest>(bytes: &[u8]) -> String {
+    let hash = D::digest(bytes);
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+#[derive(Debug, Error)]
+#[error("{checksum_type:?} checksum mismatch. Expected {expected} but the file hashed to {actual}")]
+pub struct ChecksumMismatch {
+    pub checksum_type: MavenChecksumType,
+    pub expected: String,
+    pub actual: String,
+}
+/// A checksum file downloaded from an upstream route
+#[derive(Debug, Clone)]
+pub struct UpstreamChecksum {
+    pub checksum_type: MavenChecksumType,
+    pub content: Bytes,
+}
+impl UpstreamChecksum {
+    /// The hash inside of the checksum file.
+    ///
+    /// Some tools write the file name after the hash. So only the first word is used.
+    pub fn expected(&self) -> Option<String> {
+        let content = std::str::from_utf8(&self.content).ok()?;
+        let hash = content.split_whitespace().next()?;
+        if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(hash.to_lowercase())
+    }
+    /// Checks the artifact against every checksum the upstream published.
+    ///
+    /// Checksum files that do not contain a hash are ignored.
+    pub fn verify_all(
+        artifact: &[u8],
+        checksums: &[UpstreamChecksum],
+    ) -> Result<(), ChecksumMismatch> {
+        for checksum in checksums {
+            let Some(expected) = checksum.expected() else {
+                continue;
+            };
+            let actual = checksum.checksum_type.digest(artifact);
+            if expected != actual {
+                return Err(ChecksumMismatch {
+                    checksum_type: checksum.checksum_type,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn checksum(checksum_type: MavenChecksumType, content: &'static str) -> UpstreamChecksum {
+        UpstreamChecksum {
+            checksum_type,
+            content: Bytes::from_static(content.as_bytes()),
+        }
+    }
+    #[test]
+    fn digests() {
+        assert_eq!(
+            MavenChecksumType::Sha1.digest(b"abc"),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            MavenChecksumType::Md5.digest(b"abc"),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            MavenChecksumType::Sha256.digest(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+    #[test]
+    fn checksum_paths() {
+        let artifact = StoragePath::from("dev/kingtux/tms/1.0.0/tms-1.0.0.jar");
+        let sha1 = MavenChecksumType::Sha1.checksum_path(&artifact);
+        assert_eq!(sha1.to_string(), "dev/kingtux/tms/1.0.0/tms-1.0.0.jar.sha1");
+        assert_eq!(
+            MavenChecksumType::from_path(&sha1),
+            Some(MavenChecksumType::Sha1)
+        );
+        assert_eq!(MavenChecksumType::from_path(&artifact), None);
+    }
+    #[test]
+    fn verify() {
+        let valid = checksum(
+            MavenChecksumType::Sha1,
+            "A9993E364706816ABA3E25717850C26C9CD0D89D  abc.txt\n",
+        );
+        assert!(UpstreamChecksum::verify_all(b"abc", &[valid.clone()]).is_ok());
+        let invalid = checksum(MavenChecksumType::Md5, "00000000000000000000000000000000");
+        assert!(UpstreamChecksum::verify_all(b"abc", &[valid, invalid]).is_err());
+        let html = checksum(MavenChecksumType::Sha256, "<html>Not Found</html>");
+        assert!(UpstreamChecksum::verify_all(b"abc", &[html]).is_ok());
+    }
+}
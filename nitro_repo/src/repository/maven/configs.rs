@@ -1,12 +1,13 @@
 use nr_core::repository::{
     Policy,
     config::{ConfigDescription, RepositoryConfigError, RepositoryConfigType},
+    project::ReleaseType,
 };
 use schemars::{JsonSchema, Schema, schema_for};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use super::proxy::MavenProxyConfig;
+use super::proxy::{MavenProxyConfig, MavenProxyRepositoryRoute};
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", content = "config")]
@@ -15,6 +16,23 @@ pub enum MavenRepositoryConfig {
     Proxy(MavenProxyConfig),
 }
 impl MavenRepositoryConfig {
+    pub fn validate(&self) -> Result<(), RepositoryConfigError> {
+        let MavenRepositoryConfig::Proxy(proxy) = self else {
+            return Ok(());
+        };
+        for route in &proxy.routes {
+            if route
+                .http
+                .default_headers(route.credentials.as_ref())
+                .is_err()
+            {
+                return Err(RepositoryConfigError::InvalidConfig(
+                    "Proxy route contains an invalid header",
+                ));
+            }
+        }
+        Ok(())
+    }
     pub fn is_same_type(&self, other: &MavenRepositoryConfig) -> bool {
         matches!(
             (self, other),
@@ -44,7 +62,7 @@ impl RepositoryConfigType for MavenRepositoryConfigType {
     }
     fn validate_config(&self, config: Value) -> Result<(), RepositoryConfigError> {
         let config: MavenRepositoryConfig = serde_json::from_value(config)?;
-        Ok(())
+        config.validate()
     }
     fn validate_change(&self, old: Value, new: Value) -> Result<(), RepositoryConfigError> {
         let new: MavenRepositoryConfig = serde_json::from_value(new)?;
@@ -55,7 +73,21 @@ impl RepositoryConfigType for MavenRepositoryConfigType {
                 "Cannot change the type of Maven Repository",
             ));
         }
-        Ok(())
+        new.validate()
+    }
+    /// Proxy routes can contain credentials. These are redacted
+    fn sanitize_for_public_view(
+        &self,
+        config: Value,
+    ) -> Result<Option<Value>, RepositoryConfigError> {
+        let mut config: MavenRepositoryConfig = serde_json::from_value(config)?;
+        if let MavenRepositoryConfig::Proxy(proxy) = &mut config {
+            proxy
+                .routes
+                .iter_mut()
+                .for_each(MavenProxyRepositoryRoute::sanitize_for_public_view);
+        }
+        Ok(Some(serde_json::to_value(config)?))
     }
     fn default(&self) -> Result<Value, RepositoryConfigError> {
         let config = MavenRepositoryConfig::Hosted;
@@ -66,7 +98,7 @@ impl RepositoryConfigType for MavenRepositoryConfigType {
             name: "Maven Repository Config",
             description: Some("Handles the type of Maven Repository"),
             documentation_link: None,
-            ..Default::default()
+            has_public_view: true,
         }
     }
 }
@@ -104,6 +136,16 @@ impl Default for MavenPushRules {
         }
     }
 }
+impl MavenPushRules {
+    /// If the push policy accepts the version
+    pub fn allows_release_type(&self, release_type: &ReleaseType) -> bool {
+        match self.push_policy {
+            Policy::Release => !release_type.is_snapshot(),
+            Policy::Snapshot => release_type.is_snapshot(),
+            Policy::Mixed => true,
+        }
+    }
+}
 #[derive(Debug, Clone, Copy, Default)]
 
 pub struct MavenPushRulesConfigType;
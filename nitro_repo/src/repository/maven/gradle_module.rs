@@ -0,0 +1,275 @@
+//! Gradle Module Metadata. The `.module` files Gradle publishes next to the POM.
+//!
+//! [Specification](https://github.com/gradle/gradle/blob/master/platforms/documentation/docs/src/docs/design/gradle-module-metadata-latest-specification.md)
+use std::collections::BTreeMap;
+
+use nr_core::{
+    repository::project::{VariantCapability, VariantFile, VersionVariant},
+    storage::StoragePath,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+pub const GRADLE_MODULE_EXTENSION: &str = ".module";
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GradleModule {
+    pub format_version: String,
+    pub component: GradleComponent,
+    #[serde(default)]
+    pub variants: Vec<GradleVariant>,
+}
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct GradleComponent {
+    pub group: String,
+    pub module: String,
+    pub version: String,
+    /// Set if this module is published as a variant of another module
+    #[serde(default)]
+    pub url: Option<String>,
+}
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct GradleVariant {
+    pub name: String,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub capabilities: Vec<GradleCapability>,
+    #[serde(default)]
+    pub files: Vec<GradleFile>,
+    #[serde(default, rename = "available-at")]
+    pub available_at: Option<GradleAvailableAt>,
+}
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct GradleCapability {
+    pub group: String,
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct GradleFile {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct GradleAvailableAt {
+    pub url: String,
+    pub group: String,
+    pub module: String,
+    pub version: String,
+}
+impl GradleModule {
+    pub fn parse(module: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(module)
+    }
+    pub fn version_variants(&self) -> Vec<VersionVariant> {
+        self.variants
+            .iter()
+            .map(|variant| VersionVariant {
+                name: variant.name.clone(),
+                attributes: variant
+                    .attributes
+                    .iter()
+                    .map(|(key, value)| {
+                        let value = match value {
+                            Value::String(value) => value.clone(),
+                            other => other.to_string(),
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect(),
+                capabilities: variant
+                    .capabilities
+                    .iter()
+                    .map(|capability| VariantCapability {
+                        group: capability.group.clone(),
+                        name: capability.name.clone(),
+                        version: capability.version.clone(),
+                    })
+                    .collect(),
+                files: variant
+                    .files
+                    .iter()
+                    .map(|file| VariantFile {
+                        name: file.name.clone(),
+                        url: file.url.clone(),
+                        size: file.size,
+                    })
+                    .collect(),
+                available_at: variant
+                    .available_at
+                    .as_ref()
+                    .map(|available_at| available_at.url.clone()),
+            })
+            .collect()
+    }
+    /// Every file referenced by the variants. `version_directory` is the directory the module is in
+    pub fn file_paths(&self, version_directory: &StoragePath) -> Vec<StoragePath> {
+        let mut paths: Vec<StoragePath> = Vec::new();
+        for file in self
+            .variants
+            .iter()
+            .flat_map(|variant| variant.files.iter())
+        {
+            let Some(path) = resolve_relative(version_directory, &file.url) else {
+                continue;
+            };
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+    /// The modules other variants are available at. Such as the platform modules of a Kotlin Multiplatform library
+    pub fn available_at_paths(&self, version_directory: &StoragePath) -> Vec<StoragePath> {
+        let mut paths: Vec<StoragePath> = Vec::new();
+        for available_at in self
+            .variants
+            .iter()
+            .filter_map(|variant| variant.available_at.as_ref())
+        {
+            let Some(path) = resolve_relative(version_directory, &available_at.url) else {
+                continue;
+            };
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+}
+/// Resolves a URL relative to the directory.
+///
+/// Absolute URLs and paths that leave the repository are ignored
+fn resolve_relative(directory: &StoragePath, url: &str) -> Option<StoragePath> {
+    if url.contains("://") || url.starts_with('/') {
+        return None;
+    }
+    let directory = directory.to_string();
+    let mut components: Vec<&str> = directory
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .collect();
+    for component in url.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            component => components.push(component),
+        }
+    }
+    if components.is_empty() {
+        return None;
+    }
+    Some(StoragePath::from(components.join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const MODULE: &str = r#"{
+  "formatVersion": "1.1",
+  "component": {
+    "group": "org.jetbrains.kotlinx",
+    "module": "kotlinx-coroutines-core",
+    "version": "1.7.0",
+    "attributes": {
+      "org.gradle.status": "release"
+    }
+  },
+  "createdBy": {
+    "gradle": {
+      "version": "8.0"
+    }
+  },
+  "variants": [
+    {
+      "name": "jvmApiElements-published",
+      "attributes": {
+        "org.gradle.category": "library",
+        "org.gradle.jvm.version": 8,
+        "org.jetbrains.kotlin.platform.type": "jvm"
+      },
+      "available-at": {
+        "url": "../../kotlinx-coroutines-core-jvm/1.7.0/kotlinx-coroutines-core-jvm-1.7.0.module",
+        "group": "org.jetbrains.kotlinx",
+        "module": "kotlinx-coroutines-core-jvm",
+        "version": "1.7.0"
+      }
+    },
+    {
+      "name": "metadataApiElements",
+      "attributes": {
+        "org.jetbrains.kotlin.platform.type": "common"
+      },
+      "files": [
+        {
+          "name": "kotlinx-coroutines-core-metadata-1.7.0.jar",
+          "url": "kotlinx-coroutines-core-1.7.0.jar",
+          "size": 100
+        }
+      ],
+      "capabilities": [
+        {
+          "group": "org.jetbrains.kotlinx",
+          "name": "kotlinx-coroutines-core",
+          "version": "1.7.0"
+        }
+      ]
+    }
+  ]
+}"#;
+    #[test]
+    fn parse() {
+        let module = GradleModule::parse(MODULE.as_bytes()).unwrap();
+        let variants = module.version_variants();
+        assert_eq!(variants.len(), 2);
+        assert_eq!(
+            variants[0]
+                .attributes
+                .get("org.gradle.jvm.version")
+                .map(String::as_str),
+            Some("8")
+        );
+        assert_eq!(variants[1].capabilities.len(), 1);
+        assert_eq!(variants[1].files[0].size, Some(100));
+    }
+    #[test]
+    fn paths() {
+        let module = GradleModule::parse(MODULE.as_bytes()).unwrap();
+        let version_directory =
+            StoragePath::from("org/jetbrains/kotlinx/kotlinx-coroutines-core/1.7.0");
+        assert_eq!(
+            module
+                .file_paths(&version_directory)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "org/jetbrains/kotlinx/kotlinx-coroutines-core/1.7.0/kotlinx-coroutines-core-1.7.0.jar"
+            ]
+        );
+        assert_eq!(
+            module
+                .available_at_paths(&version_directory)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "org/jetbrains/kotlinx/kotlinx-coroutines-core-jvm/1.7.0/kotlinx-coroutines-core-jvm-1.7.0.module"
+            ]
+        );
+        assert_eq!(
+            resolve_relative(&version_directory, "https://example.com/file.jar"),
+            None
+        );
+        assert_eq!(
+            resolve_relative(&StoragePath::from("dev"), "../../file.jar"),
+            None
+        );
+    }
+}
@@ -4,6 +4,7 @@ use std::sync::{
 };
 
 use derive_more::derive::Deref;
+use http::StatusCode;
 use maven_rs::pom::Pom;
 use nr_core::{
     database::entities::{
@@ -22,24 +23,38 @@ use nr_core::{
     storage::StoragePath,
     user::permissions::{HasPermissions, RepositoryActions},
 };
-use nr_storage::{DynStorage, Storage, StorageFile};
+use nr_storage::{DynStorage, Storage};
 use parking_lot::RwLock;
-use tracing::{debug, error, event, info, instrument};
+use tracing::{debug, error, event, info, instrument, warn};
 use uuid::Uuid;
 
 use crate::{
     app::NitroRepo,
     repository::{
-        Repository, RepositoryFactoryError,
+        FileRequestHeaders, OpenedFile, Repository, RepositoryAuthentication,
+        RepositoryFactoryError, StagingManager,
         maven::{MavenRepositoryConfigType, configs::MavenPushRulesConfigType},
+        open_file_for_request,
         utils::RepositoryExt,
     },
 };
 
 use super::{
     MavenError, REPOSITORY_TYPE_ID, RepoResponse, RepositoryRequest, configs::MavenPushRules,
+    gradle_module::GRADLE_MODULE_EXTENSION, metadata::is_maven_metadata_path,
     utils::MavenRepositoryExt,
 };
+mod delete;
+mod metadata;
+mod nitro_deploy;
+mod path_locks;
+mod push_rules;
+mod yank;
+pub use metadata::*;
+pub use nitro_deploy::MavenStageRejection;
+pub use path_locks::*;
+pub use push_rules::*;
+pub use yank::*;
 #[derive(derive_more::Debug)]
 pub struct MavenHostedInner {
     pub id: Uuid,
@@ -52,6 +67,14 @@ pub struct MavenHostedInner {
     pub storage: DynStorage,
     #[debug(skip)]
     pub site: NitroRepo,
+    /// Held while generating `maven-metadata.xml` files so concurrent uploads do not overwrite each other
+    #[debug(skip)]
+    pub metadata_lock: tokio::sync::Mutex<()>,
+    /// Locked by version directory while uploads are checked against the push rules and written
+    #[debug(skip)]
+    pub deploy_locks: PathLocks,
+    /// Stages files uploaded with Nitro Deploy
+    pub staging: StagingManager,
 }
 impl MavenHostedInner {}
 #[derive(Debug, Clone, Deref)]
@@ -109,27 +132,83 @@ impl MavenHosted {
 
         let body = body.body_as_bytes().await?;
         trace.metrics.project_write_bytes(body.len() as u64);
-        // TODO: Validate Against Push Rules
+        let save_path = format!(
+            "/repositories/{}/{}/{}",
+            self.storage.storage_config().storage_config.storage_name,
+            self.name,
+            path
+        );
+        if is_maven_metadata_path(&path) {
+            debug!(
+                ?path,
+                "Ignoring uploaded Maven Metadata. The repository generates it"
+            );
+            return Ok(RepoResponse::put_response(false, save_path));
+        }
+        let deploy_guard = self.deploy_locks.lock(&parent_path).await;
+        if let Some(rejection) = self
+            .check_push_rules(&path, authentication.get_user())
+            .await?
+        {
+            return Ok(rejection.into());
+        }
         let pom = if path.has_extension("pom") {
             let pom: Pom = self.parse_pom(body.to_vec())?;
-            Some(pom)
+            Some((pom, body.clone()))
         } else {
             None
         };
+        let module = path
+            .has_extension(GRADLE_MODULE_EXTENSION)
+            .then(|| body.clone());
         let (size, created) = self.storage.save_file(self.id, body.into(), &path).await?;
+        drop(deploy_guard);
         // Trigger Push Event if it is the .pom file
-        let save_path = format!(
-            "/repositories/{}/{}/{}",
-            self.storage.storage_config().storage_config.storage_name,
-            self.name,
-            path
-        );
-        if let Some(pom) = pom {
+        if let Some((pom, pom_file)) = pom {
             debug!(?pom, "Parsed POM File");
-            self.post_pom_upload(path.clone(), Some(user_id), pom).await;
+            self.post_pom_upload(path.clone(), Some(user_id), pom, &pom_file, None)
+                .await;
         };
+        if let Some(module) = module {
+            let result = self.post_module_upload(&path, &module).await;
+            if let Err(error) = result {
+                warn!(?error, ?path, "Failed to handle Gradle Module upload");
+            }
+        }
+        if let Err(error) = self.regenerate_metadata_for_path(&path).await {
+            error!(?error, ?path, "Failed to regenerate Maven Metadata");
+        }
         Ok(RepoResponse::put_response(created, save_path))
     }
+    /// Checks if the request is allowed to push to the repository
+    async fn check_write(
+        &self,
+        authentication: &RepositoryAuthentication,
+    ) -> Result<Option<RepoResponse>, MavenError> {
+        {
+            let push_rules = self.push_rules.read();
+            if push_rules.must_use_auth_token_for_push && !authentication.has_auth_token() {
+                info!("Repository requires an auth token for push");
+                return Ok(Some(RepoResponse::require_auth_token()));
+            }
+        }
+
+        let Some(user) = authentication
+            .get_user_if_has_action(RepositoryActions::Write, self.id, self.site.as_ref())
+            .await?
+        else {
+            info!("No acceptable user authentication provided");
+            return Ok(Some(RepoResponse::unauthorized()));
+        };
+        if !user
+            .has_action(RepositoryActions::Write, self.id, self.site.as_ref())
+            .await?
+        {
+            info!(?self.id, ?user, "User does not have write permissions");
+            return Ok(Some(RepoResponse::forbidden()));
+        }
+        Ok(None)
+    }
     pub async fn load(
         repository: DBRepository,
         storage: DynStorage,
@@ -149,6 +228,7 @@ impl MavenHosted {
         .await?;
         let active = AtomicBool::new(repository.active);
         debug!("Loaded Frontend Config: {:?}", project_db);
+        let staging = StagingManager::new(repository.id, site.clone());
         let inner = MavenHostedInner {
             id: repository.id,
             name: repository.name.into(),
@@ -158,6 +238,9 @@ impl MavenHosted {
             project: RwLock::new(project_db.value.0),
             storage,
             site,
+            metadata_lock: tokio::sync::Mutex::new(()),
+            deploy_locks: PathLocks::default(),
+            staging,
         };
         Ok(Self(Arc::new(inner)))
     }
@@ -253,9 +336,15 @@ impl Repository for MavenHosted {
             return Ok(err);
         }
         let visibility = self.visibility();
-        let file = self.0.storage.open_file(self.id, &path).await?;
-        if let Some(StorageFile::File { meta, .. }) = &file {
-            trace.metrics.project_access_bytes(meta.file_type.file_size);
+        let file = open_file_for_request(
+            &self.0.storage,
+            self.id,
+            &path,
+            &FileRequestHeaders::from(&parts.headers),
+        )
+        .await?;
+        if let Some(content_length) = file.as_ref().and_then(OpenedFile::content_length) {
+            trace.metrics.project_access_bytes(content_length);
             let parent = path.parent();
             let meta = self
                 .0
@@ -299,39 +388,46 @@ impl Repository for MavenHosted {
     }
     async fn handle_put(&self, request: RepositoryRequest) -> Result<RepoResponse, MavenError> {
         info!("Handling PUT Request for Repository: {}", self.id);
-        {
-            let push_rules = self.push_rules.read();
-            if push_rules.must_use_auth_token_for_push && !request.authentication.has_auth_token() {
-                info!("Repository requires an auth token for push");
-                return Ok(RepoResponse::require_auth_token());
-            }
+        if let Some(err) = self.check_write(&request.authentication).await? {
+            return Ok(err);
         }
 
-        let Some(user) = request
-            .authentication
-            .get_user_if_has_action(RepositoryActions::Write, self.id, self.site.as_ref())
+        let Some(nitro_deploy_version) = request.get_nitro_repo_deploy_header()? else {
+            return self.standard_maven_deploy(request).await;
+        };
+        info!(?nitro_deploy_version, "Handling Nitro Deploy Version");
+        self.handle_nitro_deploy_put(nitro_deploy_version, request)
+            .await
+    }
+    async fn handle_delete(
+        &self,
+        RepositoryRequest {
+            path,
+            authentication,
+            ..
+        }: RepositoryRequest,
+    ) -> Result<RepoResponse, MavenError> {
+        let Some(user) = authentication
+            .get_user_if_has_action(RepositoryActions::Edit, self.id, self.site.as_ref())
             .await?
         else {
             info!("No acceptable user authentication provided");
             return Ok(RepoResponse::unauthorized());
         };
-        if !user
-            .has_action(RepositoryActions::Write, self.id, self.site.as_ref())
-            .await?
-        {
-            info!(?self.id, ?user, "User does not have write permissions");
-            return Ok(RepoResponse::forbidden());
+        if is_maven_metadata_path(&path) {
+            return Ok(RepoResponse::basic_text_response(
+                StatusCode::BAD_REQUEST,
+                "Maven Metadata is generated by the repository and can not be deleted",
+            ));
         }
-
-        let Some(nitro_deploy_version) = request.get_nitro_repo_deploy_header()? else {
-            return self.standard_maven_deploy(request).await;
-        };
-        info!(?nitro_deploy_version, "Handling Nitro Deploy Version");
-
-        Ok(RepoResponse::unsupported_method_response(
-            request.parts.method,
-            self.get_type(),
-        ))
+        info!(?path, user = user.id, "Handling DELETE Request");
+        if !self.delete_path(&path).await? {
+            return Ok(RepoResponse::basic_text_response(
+                StatusCode::NOT_FOUND,
+                "File not found",
+            ));
+        }
+        Ok(ResponseBuilder::no_content().empty().into())
     }
     async fn handle_post(&self, request: RepositoryRequest) -> Result<RepoResponse, MavenError> {
         let Some(nitro_deploy_version) = request.get_nitro_repo_deploy_header()? else {
@@ -341,7 +437,11 @@ impl Repository for MavenHosted {
             ));
         };
         info!(?nitro_deploy_version, "Handling Nitro Deploy Version");
-        todo!()
+        if let Some(err) = self.check_write(&request.authentication).await? {
+            return Ok(err);
+        }
+        self.handle_nitro_deploy_post(nitro_deploy_version, request)
+            .await
     }
     #[instrument(fields(repository_type = "maven/hosted"))]
     async fn resolve_project_and_version_for_path(
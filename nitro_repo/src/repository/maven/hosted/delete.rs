@@ -0,0 +1,104 @@
+use nr_core::{
+    database::entities::project::{DBProject, ProjectDBType, versions::DBProjectVersion},
+    storage::StoragePath,
+};
+use nr_storage::{FileType, Storage};
+use tracing::{error, info, instrument};
+
+use super::{MavenHosted, metadata::split_project_key};
+use crate::repository::maven::MavenError;
+
+impl MavenHosted {
+    /// Deletes a file or directory.
+    ///
+    /// Deleting a version directory, or the last file in it, removes the version from the database.
+    /// Deleting a project directory removes the project and all of its versions.
+    ///
+    /// Returns false if nothing exists at the path
+    #[instrument(skip(self), fields(nr.repository.id = %self.id))]
+    pub async fn delete_path(&self, path: &StoragePath) -> Result<bool, MavenError> {
+        let Some(meta) = self.storage.get_file_information(self.id, path).await? else {
+            return Ok(false);
+        };
+        let FileType::Directory(_) = meta.file_type else {
+            self.storage.delete_file(self.id, path).await?;
+            info!(?path, "Deleted File");
+            let project = self.delete_version_if_empty(path.clone().parent()).await?;
+            if let Err(error) = self.regenerate_metadata_for_path(path).await {
+                error!(?error, ?path, "Failed to regenerate Maven Metadata");
+            }
+            self.regenerate_project_metadata(project.as_ref(), path)
+                .await;
+            return Ok(true);
+        };
+        let directory = path.to_string();
+        let database = &self.site.database;
+        let project = if let Some(version) =
+            DBProjectVersion::find_by_version_directory(&directory, self.id, database).await?
+        {
+            info!(?version.version, ?version.project_id, "Deleting Version");
+            DBProjectVersion::delete_by_id(version.id, database).await?;
+            DBProject::find_by_id(version.project_id, database).await?
+        } else if let Some(project) =
+            DBProject::find_by_project_directory(&directory, self.id, database).await?
+        {
+            info!(?project.key, "Deleting Project");
+            DBProject::delete_by_id(project.id, database).await?;
+            None
+        } else {
+            None
+        };
+        self.storage.delete_file(self.id, path).await?;
+        info!(?path, "Deleted Directory");
+        self.regenerate_project_metadata(project.as_ref(), path)
+            .await;
+        Ok(true)
+    }
+    /// Rewrites the artifact metadata of the project a version was removed from
+    async fn regenerate_project_metadata(&self, project: Option<&DBProject>, path: &StoragePath) {
+        let Some((group_id, artifact_id)) = project.and_then(split_project_key) else {
+            return;
+        };
+        if let Err(error) = self
+            .regenerate_artifact_metadata(group_id, artifact_id)
+            .await
+        {
+            error!(?error, ?path, "Failed to regenerate Maven Metadata");
+        }
+    }
+    /// Removes the version of the directory from the database once no files are left in it.
+    ///
+    /// Returns the project of the removed version
+    async fn delete_version_if_empty(
+        &self,
+        version_directory: StoragePath,
+    ) -> Result<Option<DBProject>, MavenError> {
+        let is_empty = match self
+            .storage
+            .get_file_information(self.id, &version_directory)
+            .await?
+        {
+            Some(meta) => match meta.file_type {
+                FileType::Directory(directory) => directory.file_count == 0,
+                FileType::File(_) => false,
+            },
+            None => true,
+        };
+        if !is_empty {
+            return Ok(None);
+        }
+        let database = &self.site.database;
+        let Some(version) = DBProjectVersion::find_by_version_directory(
+            &version_directory.to_string(),
+            self.id,
+            database,
+        )
+        .await?
+        else {
+            return Ok(None);
+        };
+        info!(?version.version, ?version.project_id, "Deleting Version without files");
+        DBProjectVersion::delete_by_id(version.id, database).await?;
+        Ok(DBProject::find_by_id(version.project_id, database).await?)
+    }
+}
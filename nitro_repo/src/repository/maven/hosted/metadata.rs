@@ -0,0 +1,253 @@
+use ahash::{HashSet, HashSetExt};
+use chrono::Utc;
+use nr_core::{
+    database::entities::project::{DBProject, ProjectDBType, versions::DBProjectVersion},
+    storage::StoragePath,
+};
+use nr_storage::{Storage, StorageFile};
+use tracing::{debug, info, instrument, warn};
+
+use super::MavenHosted;
+use crate::repository::maven::{
+    MavenError,
+    metadata::{
+        ArtifactMetadata, GroupMetadata, MavenCoordinates, PluginMetadata, SnapshotMetadata,
+        is_snapshot_version, metadata_files,
+    },
+    utils::MavenRepositoryExt,
+    version::compare_versions,
+};
+/// The result of rebuilding all `maven-metadata.xml` files of a repository
+#[derive(Debug, Clone, Default)]
+pub struct MavenMetadataRebuildReport {
+    /// Number of artifact level metadata files written
+    pub artifacts: usize,
+    /// Number of SNAPSHOT version level metadata files written
+    pub snapshot_versions: usize,
+    /// Number of group level metadata files written. Only groups with plugins have one
+    pub groups: usize,
+}
+/// Splits a project key of `{groupId}:{artifactId}`
+pub(super) fn split_project_key(project: &DBProject) -> Option<(&str, &str)> {
+    let split = project.key.split_once(':');
+    if split.is_none() {
+        warn!(?project.key, "Project key is not a Maven project key");
+    }
+    split
+}
+impl MavenHosted {
+    /// Regenerates the metadata affected by a file being uploaded.
+    ///
+    /// The version list only changes when a pom is uploaded. SNAPSHOT builds change with every artifact
+    #[instrument(skip(self), fields(nr.repository.id = %self.id))]
+    pub async fn regenerate_metadata_for_path(&self, path: &StoragePath) -> Result<(), MavenError> {
+        let Some(coordinates) = MavenCoordinates::from_artifact_path(path) else {
+            return Ok(());
+        };
+        let _guard = self.metadata_lock.lock().await;
+        if is_snapshot_version(&coordinates.version) {
+            self.write_snapshot_metadata(&coordinates).await?;
+        }
+        if path.has_extension(".pom") {
+            self.write_artifact_metadata_for(&coordinates.group_id, &coordinates.artifact_id)
+                .await?;
+            self.write_group_metadata(&coordinates.group_id).await?;
+        }
+        Ok(())
+    }
+    /// Regenerates the version list of an artifact. Used after versions are removed
+    #[instrument(skip(self), fields(nr.repository.id = %self.id))]
+    pub async fn regenerate_artifact_metadata(
+        &self,
+        group_id: &str,
+        artifact_id: &str,
+    ) -> Result<(), MavenError> {
+        let _guard = self.metadata_lock.lock().await;
+        self.write_artifact_metadata_for(group_id, artifact_id)
+            .await?;
+        self.write_group_metadata(group_id).await?;
+        Ok(())
+    }
+    /// Rebuilds every `maven-metadata.xml` in the repository from the database.
+    #[instrument(skip(self), fields(nr.repository.id = %self.id))]
+    pub async fn rebuild_all_metadata(&self) -> Result<MavenMetadataRebuildReport, MavenError> {
+        let _guard = self.metadata_lock.lock().await;
+        let mut report = MavenMetadataRebuildReport::default();
+        let projects = DBProject::find_by_repository(self.id, &self.site.database).await?;
+        let mut groups = HashSet::new();
+        for project in &projects {
+            let Some((group_id, artifact_id)) = split_project_key(project) else {
+                continue;
+            };
+            let versions = self.write_artifact_metadata(project).await?;
+            report.artifacts += 1;
+            groups.insert(group_id.to_owned());
+            for version in versions {
+                if !is_snapshot_version(&version.version) {
+                    continue;
+                }
+                let coordinates = MavenCoordinates {
+                    group_id: group_id.to_owned(),
+                    artifact_id: artifact_id.to_owned(),
+                    version: version.version,
+                };
+                self.write_snapshot_metadata(&coordinates).await?;
+                report.snapshot_versions += 1;
+            }
+        }
+        for group_id in groups {
+            if self.write_group_metadata(&group_id).await? {
+                report.groups += 1;
+            }
+        }
+        info!(?report, "Rebuilt Maven Metadata");
+        Ok(report)
+    }
+    async fn write_artifact_metadata_for(
+        &self,
+        group_id: &str,
+        artifact_id: &str,
+    ) -> Result<(), MavenError> {
+        let project_key = format!("{}:{}", group_id, artifact_id);
+        let Some(project) =
+            DBProject::find_by_project_key(&project_key, self.id, &self.site.database).await?
+        else {
+            debug!(?project_key, "No project found. Skipping artifact metadata");
+            return Ok(());
+        };
+        self.write_artifact_metadata(&project).await?;
+        Ok(())
+    }
+    /// Writes the artifact level metadata. Returns the versions of the project
+    async fn write_artifact_metadata(
+        &self,
+        project: &DBProject,
+    ) -> Result<Vec<DBProjectVersion>, MavenError> {
+        let Some((group_id, artifact_id)) = split_project_key(project) else {
+            return Ok(Vec::new());
+        };
+        let mut versions =
+            DBProjectVersion::get_all_versions(project.id, &self.site.database).await?;
+        versions.sort_by(|a, b| compare_versions(&a.version, &b.version));
+        let directory = StoragePath::from(project.path.as_str());
+        // Yanked versions are kept in storage but are no longer listed
+        let listed: Vec<&DBProjectVersion> = versions
+            .iter()
+            .filter(|version| version.yanked_at.is_none())
+            .collect();
+        if listed.is_empty() {
+            self.delete_metadata(&directory).await?;
+            return Ok(versions);
+        }
+        let metadata = ArtifactMetadata {
+            group_id: group_id.to_owned(),
+            artifact_id: artifact_id.to_owned(),
+            versions: listed
+                .iter()
+                .map(|version| version.version.clone())
+                .collect(),
+            last_updated: listed
+                .iter()
+                .map(|version| version.updated_at.with_timezone(&Utc))
+                .max()
+                .unwrap_or_else(Utc::now),
+        };
+        self.write_metadata(&directory, metadata.to_xml()).await?;
+        Ok(versions)
+    }
+    async fn write_snapshot_metadata(
+        &self,
+        coordinates: &MavenCoordinates,
+    ) -> Result<(), MavenError> {
+        let directory = coordinates.version_directory();
+        let Some(StorageFile::Directory { files, .. }) =
+            self.storage.open_file(self.id, &directory).await?
+        else {
+            debug!(?directory, "Version directory does not exist");
+            return self.delete_metadata(&directory).await;
+        };
+        let metadata = SnapshotMetadata::from_files(
+            coordinates,
+            files.iter().map(|file| (file.name.as_str(), file.modified)),
+        );
+        self.write_metadata(&directory, metadata.to_xml()).await
+    }
+    /// Writes the group level metadata if the group contains any plugins.
+    ///
+    /// Returns true if the metadata was written
+    async fn write_group_metadata(&self, group_id: &str) -> Result<bool, MavenError> {
+        let projects = DBProject::find_by_scope(group_id, self.id, &self.site.database).await?;
+        let mut plugins: Vec<PluginMetadata> = Vec::new();
+        for project in &projects {
+            let Some((_, artifact_id)) = split_project_key(project) else {
+                continue;
+            };
+            if !self.is_maven_plugin(project).await? {
+                continue;
+            }
+            plugins.push(PluginMetadata {
+                name: project.name.clone(),
+                prefix: PluginMetadata::default_prefix(artifact_id)
+                    .unwrap_or_else(|| artifact_id.to_owned()),
+                artifact_id: artifact_id.to_owned(),
+            });
+        }
+        let directory = StoragePath::from(group_id.replace('.', "/"));
+        if plugins.is_empty() {
+            // A plugin that was removed or yanked would otherwise stay listed
+            self.delete_metadata(&directory).await?;
+            return Ok(false);
+        }
+        plugins.sort_by(|a, b| a.artifact_id.cmp(&b.artifact_id));
+        self.write_metadata(&directory, GroupMetadata { plugins }.to_xml())
+            .await?;
+        Ok(true)
+    }
+    /// Checks the packaging of the POM of the highest version that is not yanked
+    async fn is_maven_plugin(&self, project: &DBProject) -> Result<bool, MavenError> {
+        let versions = DBProjectVersion::get_all_versions(project.id, &self.site.database).await?;
+        let Some(version) = versions
+            .iter()
+            .filter(|version| version.yanked_at.is_none())
+            .max_by(|a, b| compare_versions(&a.version, &b.version))
+        else {
+            return Ok(false);
+        };
+        let directory = StoragePath::from(version.path.as_str());
+        let Some(StorageFile::Directory { files, .. }) =
+            self.storage.open_file(self.id, &directory).await?
+        else {
+            return Ok(false);
+        };
+        // SNAPSHOT versions have a POM per build. The newest build sorts last
+        let Some(pom) = files
+            .iter()
+            .map(|file| file.name.as_str())
+            .filter(|name| name.ends_with(".pom"))
+            .max()
+        else {
+            debug!(?directory, "Version has no POM");
+            return Ok(false);
+        };
+        let path = directory.clone().push(pom);
+        match self.read_stored_pom(&path).await? {
+            Some(pom) => Ok(pom.is_maven_plugin()),
+            None => Ok(false),
+        }
+    }
+    async fn write_metadata(&self, directory: &StoragePath, xml: String) -> Result<(), MavenError> {
+        for (path, content) in metadata_files(directory, xml) {
+            debug!(?path, "Writing Maven Metadata");
+            self.storage
+                .save_file(self.id, content.into(), &path)
+                .await?;
+        }
+        Ok(())
+    }
+    async fn delete_metadata(&self, directory: &StoragePath) -> Result<(), MavenError> {
+        for (path, _) in metadata_files(directory, String::new()) {
+            self.storage.delete_file(self.id, &path).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,543 @@
+use ahash::HashSet;
+use bytes::Bytes;
+use http::StatusCode;
+use nr_core::{
+    database::entities::{
+        project::{
+            DBProject, NewProject, ProjectDBType,
+            members::NewProjectMember,
+            versions::{
+                DBProjectVersion, NewVersion, UpdateProjectVersion,
+                dependencies::DBProjectVersionDependency,
+            },
+        },
+        stages::DBStage,
+        user::UserSafeData,
+    },
+    storage::StoragePath,
+};
+use nr_storage::Storage;
+use thiserror::Error;
+use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+use super::{MavenHosted, MavenPushRejection};
+use crate::{
+    repository::{
+        NitroRepoDeployHeaderValue, RepoResponse, RepositoryRequest,
+        maven::{
+            MavenError, REPOSITORY_TYPE_ID,
+            checksum::MavenChecksumType,
+            get_release_type,
+            gradle_module::{GRADLE_MODULE_EXTENSION, GradleModule},
+            nitro_deploy::{
+                NITRO_MAVEN_DEPLOY_VERSION, NRMavenPublishSuccessFile,
+                NRMavenPublishSuccessResponse, NewNRMavenDeploy, NewNrMavenDeploySuccessResponse,
+            },
+            utils::MavenRepositoryExt,
+        },
+    },
+    utils::ResponseBuilder,
+};
+/// A file saved while publishing a stage
+struct WrittenDeployFile {
+    path: StoragePath,
+    /// The content the file had before the publish. None if the file did not exist
+    previous: Option<Bytes>,
+}
+/// The deploy id and the file name taken from `{deploy_id}/{file_name}`
+fn parse_deploy_path(path: &StoragePath) -> Option<(Uuid, Option<String>)> {
+    let path = path.to_string();
+    let path = path.trim_matches('/');
+    let (deploy_id, file_name) = match path.split_once('/') {
+        Some((deploy_id, file_name)) => (deploy_id, Some(file_name.to_owned())),
+        None => (path, None),
+    };
+    let deploy_id = Uuid::parse_str(deploy_id).ok()?;
+    Some((deploy_id, file_name))
+}
+/// Why a stage could not be published
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MavenStageRejection {
+    #[error("Files have not been uploaded: {}", .0.join(", "))]
+    MissingFiles(Vec<String>),
+    #[error(transparent)]
+    PushRule(#[from] MavenPushRejection),
+}
+impl MavenStageRejection {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            MavenStageRejection::MissingFiles(_) => StatusCode::BAD_REQUEST,
+            MavenStageRejection::PushRule(rejection) => rejection.status_code(),
+        }
+    }
+}
+impl From<MavenStageRejection> for RepoResponse {
+    fn from(rejection: MavenStageRejection) -> Self {
+        RepoResponse::basic_text_response(rejection.status_code(), rejection.to_string())
+    }
+}
+fn bad_request(message: impl Into<String>) -> RepoResponse {
+    RepoResponse::basic_text_response(StatusCode::BAD_REQUEST, message)
+}
+fn deploy_not_found() -> RepoResponse {
+    RepoResponse::basic_text_response(StatusCode::NOT_FOUND, "Deploy not found")
+}
+impl MavenHosted {
+    fn check_nitro_deploy_header(header: &NitroRepoDeployHeaderValue) -> Option<RepoResponse> {
+        if header.repository_type != REPOSITORY_TYPE_ID
+            || header.version != NITRO_MAVEN_DEPLOY_VERSION
+        {
+            warn!(?header, "Unsupported Nitro Deploy");
+            return Some(bad_request(format!(
+                "Unsupported Nitro Deploy. This repository supports `{} {}`",
+                REPOSITORY_TYPE_ID, NITRO_MAVEN_DEPLOY_VERSION
+            )));
+        }
+        None
+    }
+    /// Loads the stage of a deploy. The stage can only be used by the user that created it
+    async fn get_deploy(
+        &self,
+        deploy_id: Uuid,
+        user_id: i32,
+    ) -> Result<Result<(DBStage, NewNRMavenDeploy), RepoResponse>, MavenError> {
+        let Some(stage) = self.staging.get_stage(deploy_id).await? else {
+            return Ok(Err(deploy_not_found()));
+        };
+        if stage.created_by != user_id {
+            info!(?deploy_id, user_id, "Deploy belongs to another user");
+            return Ok(Err(RepoResponse::forbidden()));
+        }
+        let deploy: NewNRMavenDeploy = serde_json::from_value(stage.stage_state.0.clone())?;
+        Ok(Ok((stage, deploy)))
+    }
+    /// POST with an empty path creates a deploy. POST to `{deploy_id}` publishes it
+    #[instrument(skip(self, request), fields(nr.repository.id = %self.id))]
+    pub async fn handle_nitro_deploy_post(
+        &self,
+        header: NitroRepoDeployHeaderValue,
+        request: RepositoryRequest,
+    ) -> Result<RepoResponse, MavenError> {
+        if let Some(response) = Self::check_nitro_deploy_header(&header) {
+            return Ok(response);
+        }
+        let Some(user_id) = request.authentication.get_user().map(|user| user.id) else {
+            return Ok(RepoResponse::unauthorized());
+        };
+        if request.path.to_string().trim_matches('/').is_empty() {
+            let deploy: NewNRMavenDeploy = request.body.body_as_json().await?;
+            return self.create_deploy(deploy, user_id).await;
+        }
+        let Some((deploy_id, None)) = parse_deploy_path(&request.path) else {
+            return Ok(bad_request("Expected a path of `{deploy_id}`"));
+        };
+        self.publish_deploy(deploy_id, user_id, &request).await
+    }
+    /// PUT to `{deploy_id}/{file_name}` stages a file
+    #[instrument(skip(self, request), fields(nr.repository.id = %self.id))]
+    pub async fn handle_nitro_deploy_put(
+        &self,
+        header: NitroRepoDeployHeaderValue,
+        request: RepositoryRequest,
+    ) -> Result<RepoResponse, MavenError> {
+        if let Some(response) = Self::check_nitro_deploy_header(&header) {
+            return Ok(response);
+        }
+        let Some(user_id) = request.authentication.get_user().map(|user| user.id) else {
+            return Ok(RepoResponse::unauthorized());
+        };
+        let Some((deploy_id, Some(file_name))) = parse_deploy_path(&request.path) else {
+            return Ok(bad_request("Expected a path of `{deploy_id}/{file_name}`"));
+        };
+        let (_, deploy) = match self.get_deploy(deploy_id, user_id).await? {
+            Ok(ok) => ok,
+            Err(response) => return Ok(response),
+        };
+        let Some(declared) = deploy.get_file(&file_name) else {
+            return Ok(bad_request(format!(
+                "File {} is not part of the deploy",
+                file_name
+            )));
+        };
+        let body = request.body.body_as_bytes().await?;
+        if let Err(mismatch) = declared.verify(&body) {
+            warn!(?deploy_id, ?file_name, %mismatch, "Rejected staged file");
+            return Ok(bad_request(format!("{}: {}", file_name, mismatch)));
+        }
+        request.trace.metrics.project_write_bytes(body.len() as u64);
+        self.staging.add_file(deploy_id, file_name, body).await?;
+        Ok(RepoResponse::basic_text_response(
+            StatusCode::NO_CONTENT,
+            "",
+        ))
+    }
+    async fn create_deploy(
+        &self,
+        deploy: NewNRMavenDeploy,
+        user_id: i32,
+    ) -> Result<RepoResponse, MavenError> {
+        if let Err(invalid) = deploy.validate() {
+            return Ok(bad_request(invalid.to_string()));
+        }
+        {
+            let push_rules = self.push_rules.read();
+            let release_type = get_release_type(&deploy.version);
+            if !push_rules.allows_release_type(&release_type) {
+                return Ok(MavenPushRejection::PolicyViolation {
+                    version: deploy.version.clone(),
+                    release_type,
+                    policy: push_rules.push_policy.clone(),
+                }
+                .into());
+            }
+        }
+        let stage = self
+            .staging
+            .create_stage(serde_json::to_value(&deploy)?, user_id)
+            .await?;
+        info!(
+            ?stage.id,
+            project = %deploy.project_key(),
+            version = %deploy.version,
+            "Created Nitro Deploy"
+        );
+        Ok(ResponseBuilder::ok()
+            .json(&NewNrMavenDeploySuccessResponse {
+                deploy_id: stage.id,
+            })
+            .into())
+    }
+    /// POST to `{deploy_id}` publishes the deploy
+    async fn publish_deploy(
+        &self,
+        deploy_id: Uuid,
+        user_id: i32,
+        request: &RepositoryRequest,
+    ) -> Result<RepoResponse, MavenError> {
+        let (stage, deploy) = match self.get_deploy(deploy_id, user_id).await? {
+            Ok(ok) => ok,
+            Err(response) => return Ok(response),
+        };
+        match self
+            .publish_stage(&stage, &deploy, request.authentication.get_user())
+            .await?
+        {
+            Ok(response) => Ok(ResponseBuilder::ok().json(&response).into()),
+            Err(rejection) => Ok(rejection.into()),
+        }
+    }
+    /// Publishes every staged file of a deploy into this repository and deletes the stage.
+    ///
+    /// The stage does not have to belong to this repository. This is how stages are promoted.
+    ///
+    /// All push rules are checked against `user` before anything is written.
+    /// If a file fails to save, the files that were already saved are put back to what they were before the publish.
+    /// The version is published as the user that created the stage
+    #[instrument(skip(self, deploy, user), fields(nr.repository.id = %self.id))]
+    pub async fn publish_stage(
+        &self,
+        stage: &DBStage,
+        deploy: &NewNRMavenDeploy,
+        user: Option<&UserSafeData>,
+    ) -> Result<Result<NRMavenPublishSuccessResponse, MavenStageRejection>, MavenError> {
+        let staged: HashSet<String> = stage
+            .get_files(&self.site.database)
+            .await?
+            .into_iter()
+            .map(|file| file.file_name)
+            .collect();
+        let missing: Vec<String> = deploy
+            .files
+            .iter()
+            .filter(|file| !staged.contains(&file.file_name))
+            .map(|file| file.file_name.clone())
+            .collect();
+        if !missing.is_empty() {
+            return Ok(Err(MavenStageRejection::MissingFiles(missing)));
+        }
+        let version_directory = deploy.version_directory();
+        let deploy_guard = self.deploy_locks.lock(&version_directory).await;
+        let mut files = Vec::with_capacity(deploy.files.len());
+        for file in &deploy.files {
+            let path = version_directory.clone().push(&file.file_name);
+            if let Some(rejection) = self.check_push_rules(&path, user).await? {
+                return Ok(Err(rejection.into()));
+            }
+            let Some(content) = self.staging.read_file(stage.id, &file.file_name).await? else {
+                return Ok(Err(MavenStageRejection::MissingFiles(vec![
+                    file.file_name.clone(),
+                ])));
+            };
+            files.push((file.file_name.clone(), path, content));
+        }
+        let mut written = Vec::new();
+        if let Err(error) = self.write_deploy_files(&files, &mut written).await {
+            error!(
+                ?error,
+                ?stage.id,
+                "Failed to publish stage. Undoing written files"
+            );
+            self.undo_deploy_writes(written).await;
+            return Err(error);
+        }
+        drop(deploy_guard);
+        let pom_file = files
+            .iter()
+            .find(|(name, _, _)| name.ends_with(".pom"))
+            .map(|(_, _, content)| content.as_ref());
+        let module_file = files
+            .iter()
+            .find(|(name, _, _)| name.ends_with(GRADLE_MODULE_EXTENSION))
+            .map(|(_, _, content)| content.as_ref());
+        self.create_deploy_version(deploy, stage.created_by, pom_file, module_file)
+            .await?;
+        if let Err(error) = self
+            .regenerate_artifact_metadata(&deploy.group_id, &deploy.artifact_id)
+            .await
+        {
+            error!(?error, "Failed to regenerate Maven Metadata");
+        }
+        // Generates the SNAPSHOT metadata. Deploys always contain at least one file
+        let first_file = version_directory.push(&deploy.files[0].file_name);
+        if let Err(error) = self.regenerate_metadata_for_path(&first_file).await {
+            error!(?error, "Failed to regenerate Maven Metadata");
+        }
+        self.staging.delete_stage(stage).await?;
+        let storage_name = &self.storage.storage_config().storage_config.storage_name;
+        let response = NRMavenPublishSuccessResponse {
+            files: files
+                .into_iter()
+                .map(|(name, path, _)| NRMavenPublishSuccessFile {
+                    name,
+                    path: format!("/repositories/{}/{}/{}", storage_name, self.name, path),
+                })
+                .collect(),
+        };
+        info!(?stage.id, "Published Stage");
+        Ok(Ok(response))
+    }
+    /// Saves the files and their checksums. Every saved file is added to `written`
+    async fn write_deploy_files(
+        &self,
+        files: &[(String, StoragePath, Bytes)],
+        written: &mut Vec<WrittenDeployFile>,
+    ) -> Result<(), MavenError> {
+        for (_, path, content) in files {
+            self.write_deploy_file(path, content.clone(), written)
+                .await?;
+            for checksum_type in MavenChecksumType::ALL {
+                let checksum_path = checksum_type.checksum_path(path);
+                let checksum = checksum_type.digest(content);
+                self.write_deploy_file(&checksum_path, checksum.into(), written)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+    /// Saves a file. The content it replaces is kept so the write can be undone
+    async fn write_deploy_file(
+        &self,
+        path: &StoragePath,
+        content: Bytes,
+        written: &mut Vec<WrittenDeployFile>,
+    ) -> Result<(), MavenError> {
+        let previous = match self
+            .storage
+            .open_file(self.id, path)
+            .await?
+            .and_then(|file| file.file())
+        {
+            Some((reader, meta)) => Some(Bytes::from(
+                reader
+                    .read_to_vec(meta.file_type.file_size as usize)
+                    .await?,
+            )),
+            None => None,
+        };
+        self.storage
+            .save_file(self.id, content.into(), path)
+            .await?;
+        written.push(WrittenDeployFile {
+            path: path.clone(),
+            previous,
+        });
+        Ok(())
+    }
+    /// Restores the files a failed publish overwrote and removes the ones it created
+    async fn undo_deploy_writes(&self, written: Vec<WrittenDeployFile>) {
+        for file in written.into_iter().rev() {
+            let result = match file.previous {
+                Some(previous) => self
+                    .storage
+                    .save_file(self.id, previous.into(), &file.path)
+                    .await
+                    .map(|_| ()),
+                None => self
+                    .storage
+                    .delete_file(self.id, &file.path)
+                    .await
+                    .map(|_| ()),
+            };
+            if let Err(error) = result {
+                error!(?error, path = ?file.path, "Failed to undo the write of a file");
+            }
+        }
+    }
+    /// Creates the project if needed and creates or updates the version.
+    ///
+    /// The dependencies are read from the POM of the deploy. So is `extra` if the deploy does not contain it
+    async fn create_deploy_version(
+        &self,
+        deploy: &NewNRMavenDeploy,
+        publisher: i32,
+        pom_file: Option<&[u8]>,
+        module_file: Option<&[u8]>,
+    ) -> Result<(), MavenError> {
+        let database = &self.site.database;
+        let version_directory = deploy.version_directory();
+        let resolved = match pom_file {
+            Some(pom_file) => match self.resolve_pom(pom_file).await {
+                Ok(resolved) => Some(resolved),
+                Err(error) => {
+                    warn!(
+                        ?error,
+                        "Failed to read the project information from the POM"
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+        let mut extra = match (&deploy.extra, &resolved) {
+            (Some(extra), _) => extra.clone(),
+            (None, Some(resolved)) => resolved.version_data(),
+            (None, None) => Default::default(),
+        };
+        extra.tags = deploy.tags.clone();
+        if let Some(module_file) = module_file {
+            match GradleModule::parse(module_file) {
+                Ok(module) => extra.variants = module.version_variants(),
+                Err(error) => warn!(?error, "Failed to read the Gradle Module"),
+            }
+        }
+        let project_key = deploy.project_key();
+        let project = if let Some(project) =
+            DBProject::find_by_project_key(&project_key, self.id, database).await?
+        {
+            project
+        } else {
+            let project = NewProject {
+                scope: Some(deploy.group_id.clone()),
+                project_key,
+                name: deploy.name.clone(),
+                description: extra.description.clone(),
+                repository: self.id,
+                storage_path: version_directory.clone().parent().to_string(),
+            }
+            .insert(database)
+            .await?;
+            NewProjectMember::new_owner(publisher, project.id)
+                .insert_no_return(database)
+                .await?;
+            info!(?project, "Created Project");
+            DBProjectVersionDependency::link_to_project(
+                project.id,
+                &deploy.group_id,
+                &deploy.artifact_id,
+                database,
+            )
+            .await?;
+            project
+        };
+        let release_type = get_release_type(&deploy.version);
+        let version = if let Some(version) =
+            DBProjectVersion::find_by_version_and_project(&deploy.version, project.id, database)
+                .await?
+        {
+            UpdateProjectVersion {
+                release_type: Some(release_type),
+                publisher: Some(Some(publisher)),
+                extra: Some(extra),
+                ..Default::default()
+            }
+            .update(version.id, database)
+            .await?;
+            version.id
+        } else {
+            let version = NewVersion {
+                project_id: project.id,
+                version: deploy.version.clone(),
+                release_type,
+                version_path: version_directory.to_string(),
+                publisher: Some(publisher),
+                version_page: None,
+                extra,
+            }
+            .insert(database)
+            .await?;
+            debug!(?version, "Created Version");
+            version.id
+        };
+        let project_directory = StoragePath::from(project.path.as_str());
+        let mut project_meta = self
+            .storage
+            .get_repository_meta(self.id, &project_directory)
+            .await?
+            .unwrap_or_default();
+        project_meta.set_project_id(project.id);
+        self.storage
+            .put_repository_meta(self.id, &project_directory, project_meta)
+            .await?;
+        let mut version_meta = self
+            .storage
+            .get_repository_meta(self.id, &version_directory)
+            .await?
+            .unwrap_or_default();
+        version_meta.set_project_id(project.id);
+        version_meta.set_version_id(version);
+        self.storage
+            .put_repository_meta(self.id, &version_directory, version_meta)
+            .await?;
+        if let Some(resolved) = resolved {
+            self.save_dependencies(version, &resolved).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn deploy_paths() {
+        let deploy_id = Uuid::new_v4();
+        assert_eq!(
+            parse_deploy_path(&StoragePath::from(deploy_id.to_string())),
+            Some((deploy_id, None))
+        );
+        assert_eq!(
+            parse_deploy_path(&StoragePath::from(format!("{}/tms-1.0.0.jar", deploy_id))),
+            Some((deploy_id, Some("tms-1.0.0.jar".to_owned())))
+        );
+        assert_eq!(
+            parse_deploy_path(&StoragePath::from("dev/kingtux/tms")),
+            None
+        );
+    }
+    #[test]
+    fn stage_rejections() {
+        let rejection = MavenStageRejection::MissingFiles(vec![
+            "tms-1.0.0.pom".to_owned(),
+            "tms-1.0.0.jar".to_owned(),
+        ]);
+        assert_eq!(rejection.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            rejection.to_string(),
+            "Files have not been uploaded: tms-1.0.0.pom, tms-1.0.0.jar"
+        );
+        let rejection: MavenStageRejection =
+            MavenPushRejection::OverwriteNotAllowed("tms-1.0.0.jar".to_owned()).into();
+        assert_eq!(rejection.status_code(), StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use ahash::HashMap;
+use nr_core::storage::StoragePath;
+use parking_lot::Mutex;
+use tokio::sync::OwnedMutexGuard;
+/// A lock per path. Held while an upload is checked against the push rules and written.
+///
+/// Otherwise two uploads of the same file could both pass the overwrite check
+#[derive(Debug, Default)]
+pub struct PathLocks(Mutex<HashMap<StoragePath, Arc<tokio::sync::Mutex<()>>>>);
+impl PathLocks {
+    /// Waits until no one else holds the lock of the path
+    pub async fn lock(&self, path: &StoragePath) -> PathLockGuard<'_> {
+        let lock = self.0.lock().entry(path.clone()).or_default().clone();
+        let guard = lock.lock_owned().await;
+        PathLockGuard {
+            locks: self,
+            path: path.clone(),
+            guard: Some(guard),
+        }
+    }
+}
+/// Releases the lock of the path when dropped
+#[derive(Debug)]
+pub struct PathLockGuard<'a> {
+    locks: &'a PathLocks,
+    path: StoragePath,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+impl Drop for PathLockGuard<'_> {
+    fn drop(&mut self) {
+        let mut locks = self.locks.0.lock();
+        drop(self.guard.take());
+        // Only the map holds the lock once no one else is waiting for it
+        let unused = locks
+            .get(&self.path)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1);
+        if unused {
+            locks.remove(&self.path);
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    #[tokio::test]
+    async fn waits_for_the_same_path() {
+        let locks = PathLocks::default();
+        let path = StoragePath::from("dev/kingtux/tms/1.0.0");
+        let guard = locks.lock(&path).await;
+        let waiting = tokio::time::timeout(Duration::from_millis(50), locks.lock(&path)).await;
+        assert!(waiting.is_err());
+        let other = locks
+            .lock(&StoragePath::from("dev/kingtux/tms/2.0.0"))
+            .await;
+        drop(other);
+        drop(guard);
+        assert!(locks.0.lock().is_empty());
+        let _guard = locks.lock(&path).await;
+    }
+}
@@ -0,0 +1,145 @@
+use http::StatusCode;
+use nr_core::{
+    database::entities::{
+        project::{DBProject, ProjectDBType, members::DBProjectMember},
+        user::UserSafeData,
+    },
+    repository::{Policy, project::ReleaseType},
+    storage::StoragePath,
+    user::permissions::HasPermissions,
+};
+use nr_storage::Storage;
+use thiserror::Error;
+use tracing::{debug, info, instrument};
+
+use super::MavenHosted;
+use crate::repository::{
+    RepoResponse,
+    maven::{MavenError, get_release_type, metadata::MavenCoordinates},
+};
+/// Why a push was rejected by the [MavenPushRules](crate::repository::maven::configs::MavenPushRules).
+///
+/// The message is sent as the response body so Maven can print it
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MavenPushRejection {
+    #[error(
+        "Version {version} is a {release_type} version. This repository only accepts {policy:?} versions"
+    )]
+    PolicyViolation {
+        version: String,
+        release_type: ReleaseType,
+        policy: Policy,
+    },
+    #[error("{0} already exists and this repository does not allow overwriting files")]
+    OverwriteNotAllowed(String),
+    #[error("You are not a member of project {0} with write access")]
+    NotProjectMember(String),
+}
+impl MavenPushRejection {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            MavenPushRejection::PolicyViolation { .. } => StatusCode::BAD_REQUEST,
+            MavenPushRejection::OverwriteNotAllowed(_) => StatusCode::CONFLICT,
+            MavenPushRejection::NotProjectMember(_) => StatusCode::FORBIDDEN,
+        }
+    }
+}
+impl From<MavenPushRejection> for RepoResponse {
+    fn from(rejection: MavenPushRejection) -> Self {
+        RepoResponse::basic_text_response(rejection.status_code(), rejection.to_string())
+    }
+}
+impl MavenHosted {
+    /// Validates an upload against the push rules of the repository.
+    ///
+    /// `maven-metadata.xml` files are generated by the repository and should not be passed in.
+    /// `user` is the user pushing the file. Membership is not checked without a user
+    #[instrument(skip(self, user), fields(nr.repository.id = %self.id))]
+    pub async fn check_push_rules(
+        &self,
+        path: &StoragePath,
+        user: Option<&UserSafeData>,
+    ) -> Result<Option<MavenPushRejection>, MavenError> {
+        let push_rules = self.push_rules.read().clone();
+        let coordinates = MavenCoordinates::from_artifact_path(path);
+        if let Some(coordinates) = &coordinates {
+            let release_type = get_release_type(&coordinates.version);
+            if !push_rules.allows_release_type(&release_type) {
+                info!(
+                    ?coordinates,
+                    ?release_type,
+                    "Version rejected by push policy"
+                );
+                return Ok(Some(MavenPushRejection::PolicyViolation {
+                    version: coordinates.version.clone(),
+                    release_type,
+                    policy: push_rules.push_policy,
+                }));
+            }
+        }
+        if !push_rules.allow_overwrite
+            && self
+                .storage
+                .get_file_information(self.id, path)
+                .await?
+                .is_some()
+        {
+            info!(?path, "Overwrite rejected");
+            return Ok(Some(MavenPushRejection::OverwriteNotAllowed(
+                path.to_string(),
+            )));
+        }
+        if !push_rules.must_be_project_member {
+            return Ok(None);
+        }
+        let Some(coordinates) = coordinates else {
+            return Ok(None);
+        };
+        let Some(user) = user else {
+            return Ok(None);
+        };
+        if user.is_admin_or_system_manager() {
+            return Ok(None);
+        }
+        let project_key = format!("{}:{}", coordinates.group_id, coordinates.artifact_id);
+        let Some(project) =
+            DBProject::find_by_project_key(&project_key, self.id, &self.site.database).await?
+        else {
+            debug!(
+                ?project_key,
+                "Project does not exist yet. The pusher will become the owner"
+            );
+            return Ok(None);
+        };
+        let member =
+            DBProjectMember::find_by_project_and_user(project.id, user.id, &self.site.database)
+                .await?;
+        if member.is_some_and(|member| member.can_write) {
+            return Ok(None);
+        }
+        info!(?project_key, user = user.id, "User is not a project member");
+        Ok(Some(MavenPushRejection::NotProjectMember(project_key)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn rejection_messages() {
+        let rejection = MavenPushRejection::PolicyViolation {
+            version: "1.0.0-SNAPSHOT".to_owned(),
+            release_type: ReleaseType::Snapshot,
+            policy: Policy::Release,
+        };
+        assert_eq!(rejection.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            rejection.to_string(),
+            "Version 1.0.0-SNAPSHOT is a Snapshot version. This repository only accepts Release versions"
+        );
+        let rejection = MavenPushRejection::OverwriteNotAllowed(
+            "dev/kingtux/tms/1.0.0/tms-1.0.0.jar".to_owned(),
+        );
+        assert_eq!(rejection.status_code(), StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,58 @@
+use nr_core::database::entities::project::{DBProject, ProjectDBType, versions::DBProjectVersion};
+use serde::Deserialize;
+use tracing::{error, info, instrument};
+use utoipa::ToSchema;
+
+use super::MavenHosted;
+use crate::repository::maven::MavenError;
+
+/// Yanks or restores a version of a Maven project
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct MavenYankRequest {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: String,
+    /// False restores a yanked version
+    #[serde(default = "default_yanked")]
+    pub yanked: bool,
+    /// Why the version was yanked
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+fn default_yanked() -> bool {
+    true
+}
+impl MavenHosted {
+    /// Yanks or restores a version. The files are kept and the version is removed from `maven-metadata.xml`
+    ///
+    /// Returns false if the version does not exist
+    #[instrument(skip(self), fields(nr.repository.id = %self.id))]
+    pub async fn set_version_yanked(&self, request: &MavenYankRequest) -> Result<bool, MavenError> {
+        let database = &self.site.database;
+        let project_key = format!("{}:{}", request.group_id, request.artifact_id);
+        let Some(project) = DBProject::find_by_project_key(&project_key, self.id, database).await?
+        else {
+            return Ok(false);
+        };
+        let Some(version) =
+            DBProjectVersion::find_by_version_and_project(&request.version, project.id, database)
+                .await?
+        else {
+            return Ok(false);
+        };
+        if request.yanked {
+            info!(?project_key, ?version.version, "Yanking Version");
+            DBProjectVersion::yank(version.id, request.reason.as_deref(), database).await?;
+        } else {
+            info!(?project_key, ?version.version, "Restoring Yanked Version");
+            DBProjectVersion::unyank(version.id, database).await?;
+        }
+        if let Err(error) = self
+            .regenerate_artifact_metadata(&request.group_id, &request.artifact_id)
+            .await
+        {
+            error!(?error, ?project_key, "Failed to regenerate Maven Metadata");
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,551 @@
+//! Generation of `maven-metadata.xml` files.
+//!
+//! Hosted repositories own these files. They are generated from the database and the files in storage
+//! instead of being uploaded by clients.
+use std::fmt::Write;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use nr_core::storage::StoragePath;
+
+use super::checksum::MavenChecksumType;
+
+pub static MAVEN_METADATA_FILE: &str = "maven-metadata.xml";
+/// The format of `lastUpdated` and the SNAPSHOT timestamp without the dot
+const LAST_UPDATED_FORMAT: &str = "%Y%m%d%H%M%S";
+const SNAPSHOT_SUFFIX: &str = "-SNAPSHOT";
+
+/// If the path is a `maven-metadata.xml` file or one of its checksums
+pub fn is_maven_metadata_path(path: &StoragePath) -> bool {
+    let path = path.to_string();
+    path.rsplit('/')
+        .next()
+        .is_some_and(|file_name| file_name.starts_with(MAVEN_METADATA_FILE))
+}
+pub fn is_snapshot_version(version: &str) -> bool {
+    version.ends_with(SNAPSHOT_SUFFIX)
+}
+/// Files next to artifacts that are not artifacts themselves
+fn is_artifact_sidecar(file_name: &str) -> bool {
+    file_name.ends_with(".asc")
+        || MavenChecksumType::ALL
+            .iter()
+            .any(|checksum_type| file_name.ends_with(&format!(".{}", checksum_type.extension())))
+}
+fn format_last_updated(date: DateTime<Utc>) -> String {
+    date.format(LAST_UPDATED_FORMAT).to_string()
+}
+/// The group id, artifact id and version taken from the path of a file inside of a version directory.
+///
+/// `dev/kingtux/tms/1.0.0/tms-1.0.0.jar` becomes `dev.kingtux`, `tms` and `1.0.0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MavenCoordinates {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: String,
+}
+impl MavenCoordinates {
+    pub fn from_artifact_path(path: &StoragePath) -> Option<Self> {
+        let path = path.to_string();
+        let mut components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        // The file name
+        components.pop()?;
+        let version = components.pop()?.to_owned();
+        let artifact_id = components.pop()?.to_owned();
+        if components.is_empty() {
+            return None;
+        }
+        Some(Self {
+            group_id: components.join("."),
+            artifact_id,
+            version,
+        })
+    }
+    pub fn group_directory(&self) -> StoragePath {
+        StoragePath::from(self.group_id.replace('.', "/"))
+    }
+    pub fn artifact_directory(&self) -> StoragePath {
+        self.group_directory().push(&self.artifact_id)
+    }
+    pub fn version_directory(&self) -> StoragePath {
+        self.artifact_directory().push(&self.version)
+    }
+}
+/// The versions of an artifact. Stored at `{groupId}/{artifactId}/maven-metadata.xml`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactMetadata {
+    pub group_id: String,
+    pub artifact_id: String,
+    /// Sorted in Maven version order. Lowest to highest
+    pub versions: Vec<String>,
+    pub last_updated: DateTime<Utc>,
+}
+impl ArtifactMetadata {
+    /// The highest version including snapshots
+    pub fn latest(&self) -> Option<&str> {
+        self.versions.last().map(String::as_str)
+    }
+    /// The highest version that is not a snapshot
+    pub fn release(&self) -> Option<&str> {
+        self.versions
+            .iter()
+            .rev()
+            .find(|version| !is_snapshot_version(version))
+            .map(String::as_str)
+    }
+    pub fn to_xml(&self) -> String {
+        let mut xml = XmlWriter::new("metadata");
+        xml.element("groupId", &self.group_id);
+        xml.element("artifactId", &self.artifact_id);
+        xml.open("versioning");
+        if let Some(latest) = self.latest() {
+            xml.element("latest", latest);
+        }
+        if let Some(release) = self.release() {
+            xml.element("release", release);
+        }
+        xml.open("versions");
+        for version in &self.versions {
+            xml.element("version", version);
+        }
+        xml.close("versions");
+        xml.element("lastUpdated", &format_last_updated(self.last_updated));
+        xml.close("versioning");
+        xml.finish()
+    }
+}
+/// A timestamped file of a SNAPSHOT version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotFile {
+    /// `20240101.120000`
+    pub timestamp: String,
+    pub build_number: u32,
+    pub classifier: Option<String>,
+    pub extension: String,
+}
+impl SnapshotFile {
+    /// Parses a file name such as `tms-1.0.0-20240101.120000-1-sources.jar`
+    pub fn parse(artifact_id: &str, version: &str, file_name: &str) -> Option<Self> {
+        let base_version = version.strip_suffix(SNAPSHOT_SUFFIX)?;
+        let rest = file_name
+            .strip_prefix(artifact_id)?
+            .strip_prefix('-')?
+            .strip_prefix(base_version)?
+            .strip_prefix('-')?;
+        // yyyyMMdd.HHmmss
+        let timestamp = rest.get(..15)?;
+        let (date, time) = timestamp.split_once('.')?;
+        if date.len() != 8
+            || time.len() != 6
+            || !date.chars().chain(time.chars()).all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+        let rest = rest[15..].strip_prefix('-')?;
+        let build_end = rest.find(|c: char| !c.is_ascii_digit())?;
+        let build_number = rest[..build_end].parse().ok()?;
+        let rest = &rest[build_end..];
+        let (classifier, extension) = if let Some(rest) = rest.strip_prefix('-') {
+            let (classifier, extension) = rest.split_once('.')?;
+            (Some(classifier.to_owned()), extension)
+        } else {
+            (None, rest.strip_prefix('.')?)
+        };
+        if extension.is_empty() {
+            return None;
+        }
+        Some(Self {
+            timestamp: timestamp.to_owned(),
+            build_number,
+            classifier,
+            extension: extension.to_owned(),
+        })
+    }
+    /// The version used in the file name. `1.0.0-20240101.120000-1`
+    pub fn value(&self, base_version: &str) -> String {
+        format!("{}-{}-{}", base_version, self.timestamp, self.build_number)
+    }
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotVersion {
+    pub classifier: Option<String>,
+    pub extension: String,
+    pub value: String,
+    pub updated: DateTime<Utc>,
+}
+/// The builds of a SNAPSHOT version. Stored at `{groupId}/{artifactId}/{version}/maven-metadata.xml`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotMetadata {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: String,
+    /// The timestamp and build number of the newest build. None if no timestamped files were deployed
+    pub snapshot: Option<(String, u32)>,
+    pub snapshot_versions: Vec<SnapshotVersion>,
+    pub last_updated: DateTime<Utc>,
+}
+impl SnapshotMetadata {
+    /// Builds the metadata from the files in the version directory.
+    ///
+    /// Only the newest build of each classifier and extension is listed
+    pub fn from_files<'a>(
+        coordinates: &MavenCoordinates,
+        files: impl IntoIterator<Item = (&'a str, DateTime<FixedOffset>)>,
+    ) -> Self {
+        let base_version = coordinates
+            .version
+            .strip_suffix(SNAPSHOT_SUFFIX)
+            .unwrap_or(&coordinates.version);
+        let mut snapshot: Option<(String, u32)> = None;
+        let mut snapshot_versions: Vec<(SnapshotFile, SnapshotVersion)> = Vec::new();
+        let mut last_updated: Option<DateTime<Utc>> = None;
+        for (file_name, modified) in files {
+            if is_artifact_sidecar(file_name) {
+                continue;
+            }
+            let Some(file) =
+                SnapshotFile::parse(&coordinates.artifact_id, &coordinates.version, file_name)
+            else {
+                continue;
+            };
+            let modified = modified.with_timezone(&Utc);
+            last_updated = Some(last_updated.map_or(modified, |last| last.max(modified)));
+            let build = (file.timestamp.clone(), file.build_number);
+            if snapshot.as_ref().is_none_or(|newest| *newest < build) {
+                snapshot = Some(build);
+            }
+            let existing = snapshot_versions.iter_mut().find(|(existing, _)| {
+                existing.classifier == file.classifier && existing.extension == file.extension
+            });
+            let version = SnapshotVersion {
+                classifier: file.classifier.clone(),
+                extension: file.extension.clone(),
+                value: file.value(base_version),
+                updated: modified,
+            };
+            match existing {
+                Some((existing, existing_version)) => {
+                    if (&existing.timestamp, existing.build_number)
+                        < (&file.timestamp, file.build_number)
+                    {
+                        *existing = file;
+                        *existing_version = version;
+                    }
+                }
+                None => snapshot_versions.push((file, version)),
+            }
+        }
+        let mut snapshot_versions: Vec<SnapshotVersion> = snapshot_versions
+            .into_iter()
+            .map(|(_, version)| version)
+            .collect();
+        snapshot_versions.sort_by(|a, b| {
+            a.extension
+                .cmp(&b.extension)
+                .then_with(|| a.classifier.cmp(&b.classifier))
+        });
+        Self {
+            group_id: coordinates.group_id.clone(),
+            artifact_id: coordinates.artifact_id.clone(),
+            version: coordinates.version.clone(),
+            snapshot,
+            snapshot_versions,
+            last_updated: last_updated.unwrap_or_else(Utc::now),
+        }
+    }
+    pub fn to_xml(&self) -> String {
+        let mut xml = XmlWriter::new("metadata modelVersion=\"1.1.0\"");
+        xml.element("groupId", &self.group_id);
+        xml.element("artifactId", &self.artifact_id);
+        xml.element("version", &self.version);
+        xml.open("versioning");
+        if let Some((timestamp, build_number)) = &self.snapshot {
+            xml.open("snapshot");
+            xml.element("timestamp", timestamp);
+            xml.element("buildNumber", &build_number.to_string());
+            xml.close("snapshot");
+        }
+        xml.element("lastUpdated", &format_last_updated(self.last_updated));
+        if !self.snapshot_versions.is_empty() {
+            xml.open("snapshotVersions");
+            for version in &self.snapshot_versions {
+                xml.open("snapshotVersion");
+                if let Some(classifier) = &version.classifier {
+                    xml.element("classifier", classifier);
+                }
+                xml.element("extension", &version.extension);
+                xml.element("value", &version.value);
+                xml.element("updated", &format_last_updated(version.updated));
+                xml.close("snapshotVersion");
+            }
+            xml.close("snapshotVersions");
+        }
+        xml.close("versioning");
+        xml.finish()
+    }
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginMetadata {
+    pub name: String,
+    pub prefix: String,
+    pub artifact_id: String,
+}
+impl PluginMetadata {
+    /// The default goal prefix Maven uses for the plugin.
+    ///
+    /// `maven-{prefix}-plugin` and `{prefix}-maven-plugin`. None if the artifactId follows neither convention
+    pub fn default_prefix(artifact_id: &str) -> Option<String> {
+        let prefix = if let Some(prefix) = artifact_id
+            .strip_prefix("maven-")
+            .and_then(|rest| rest.strip_suffix("-plugin"))
+        {
+            prefix
+        } else {
+            artifact_id.strip_suffix("-maven-plugin")?
+        };
+        if prefix.is_empty() {
+            return None;
+        }
+        Some(prefix.to_owned())
+    }
+}
+/// The plugins of a group. Stored at `{groupId}/maven-metadata.xml`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GroupMetadata {
+    pub plugins: Vec<PluginMetadata>,
+}
+impl GroupMetadata {
+    pub fn to_xml(&self) -> String {
+        let mut xml = XmlWriter::new("metadata");
+        xml.open("plugins");
+        for plugin in &self.plugins {
+            xml.open("plugin");
+            xml.element("name", &plugin.name);
+            xml.element("prefix", &plugin.prefix);
+            xml.element("artifactId", &plugin.artifact_id);
+            xml.close("plugin");
+        }
+        xml.close("plugins");
+        xml.finish()
+    }
+}
+/// The generated file and its checksum files
+pub fn metadata_files(directory: &StoragePath, xml: String) -> Vec<(StoragePath, String)> {
+    let path = directory.clone().push(MAVEN_METADATA_FILE);
+    let mut files: Vec<(StoragePath, String)> = MavenChecksumType::ALL
+        .into_iter()
+        .map(|checksum_type| {
+            (
+                checksum_type.checksum_path(&path),
+                checksum_type.digest(xml.as_bytes()),
+            )
+        })
+        .collect();
+    files.insert(0, (path, xml));
+    files
+}
+/// A minimal XML writer. The metadata files are small and only contain text elements
+struct XmlWriter {
+    xml: String,
+    root: &'static str,
+    depth: usize,
+}
+impl XmlWriter {
+    fn new(root: &'static str) -> Self {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(xml, "<{}>", root);
+        Self {
+            xml,
+            root,
+            depth: 1,
+        }
+    }
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.xml.push_str("  ");
+        }
+    }
+    fn open(&mut self, name: &str) {
+        self.indent();
+        let _ = writeln!(self.xml, "<{}>", name);
+        self.depth += 1;
+    }
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        self.indent();
+        let _ = writeln!(self.xml, "</{}>", name);
+    }
+    fn element(&mut self, name: &str, value: &str) {
+        self.indent();
+        let _ = writeln!(self.xml, "<{name}>{}</{name}>", escape_xml(value));
+    }
+    fn finish(mut self) -> String {
+        // The root can contain attributes
+        let root_name = self.root.split_whitespace().next().unwrap_or(self.root);
+        let _ = writeln!(self.xml, "</{}>", root_name);
+        self.xml
+    }
+}
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    fn coordinates() -> MavenCoordinates {
+        MavenCoordinates {
+            group_id: "dev.kingtux".to_owned(),
+            artifact_id: "tms".to_owned(),
+            version: "1.0.0-SNAPSHOT".to_owned(),
+        }
+    }
+    #[test]
+    fn coordinates_from_path() {
+        let coordinates = MavenCoordinates::from_artifact_path(&StoragePath::from(
+            "dev/kingtux/tms/1.0.0-SNAPSHOT/tms-1.0.0-20240101.120000-1.jar",
+        ))
+        .unwrap();
+        assert_eq!(coordinates, self::coordinates());
+        assert_eq!(
+            coordinates.version_directory().to_string(),
+            "dev/kingtux/tms/1.0.0-SNAPSHOT"
+        );
+        assert!(
+            MavenCoordinates::from_artifact_path(&StoragePath::from("tms/1.0/a.jar")).is_none()
+        );
+    }
+    #[test]
+    fn metadata_paths() {
+        assert!(is_maven_metadata_path(&StoragePath::from(
+            "dev/kingtux/tms/maven-metadata.xml"
+        )));
+        assert!(is_maven_metadata_path(&StoragePath::from(
+            "dev/kingtux/tms/maven-metadata.xml.sha1"
+        )));
+        assert!(!is_maven_metadata_path(&StoragePath::from(
+            "dev/kingtux/tms/1.0.0/tms-1.0.0.jar"
+        )));
+    }
+    #[test]
+    fn parse_snapshot_files() {
+        let file = SnapshotFile::parse(
+            "tms",
+            "1.0.0-SNAPSHOT",
+            "tms-1.0.0-20240101.120000-3-sources.jar",
+        )
+        .unwrap();
+        assert_eq!(file.timestamp, "20240101.120000");
+        assert_eq!(file.build_number, 3);
+        assert_eq!(file.classifier.as_deref(), Some("sources"));
+        assert_eq!(file.extension, "jar");
+        assert_eq!(file.value("1.0.0"), "1.0.0-20240101.120000-3");
+
+        let file = SnapshotFile::parse("tms", "1.0.0-SNAPSHOT", "tms-1.0.0-20240101.120000-3.pom")
+            .unwrap();
+        assert_eq!(file.classifier, None);
+        assert_eq!(file.extension, "pom");
+
+        assert!(SnapshotFile::parse("tms", "1.0.0-SNAPSHOT", "tms-1.0.0-SNAPSHOT.jar").is_none());
+        assert!(SnapshotFile::parse("tms", "1.0.0", "tms-1.0.0.jar").is_none());
+    }
+    #[test]
+    fn snapshot_metadata() {
+        let modified = Utc
+            .with_ymd_and_hms(2024, 1, 2, 12, 0, 0)
+            .unwrap()
+            .fixed_offset();
+        let files = [
+            "tms-1.0.0-20240101.120000-1.jar",
+            "tms-1.0.0-20240101.120000-1.jar.sha1",
+            "tms-1.0.0-20240101.120000-1.pom",
+            "tms-1.0.0-20240102.120000-2.jar",
+            "tms-1.0.0-20240102.120000-2-sources.jar",
+            "maven-metadata.xml",
+        ];
+        let metadata = SnapshotMetadata::from_files(
+            &coordinates(),
+            files.iter().map(|file| (*file, modified)),
+        );
+        assert_eq!(metadata.snapshot, Some(("20240102.120000".to_owned(), 2)));
+        let values: Vec<_> = metadata
+            .snapshot_versions
+            .iter()
+            .map(|version| {
+                (
+                    version.classifier.as_deref(),
+                    version.extension.as_str(),
+                    version.value.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (None, "jar", "1.0.0-20240102.120000-2"),
+                (Some("sources"), "jar", "1.0.0-20240102.120000-2"),
+                (None, "pom", "1.0.0-20240101.120000-1"),
+            ]
+        );
+        let xml = metadata.to_xml();
+        assert!(xml.contains("<buildNumber>2</buildNumber>"));
+        assert!(xml.contains("<lastUpdated>20240102120000</lastUpdated>"));
+    }
+    #[test]
+    fn artifact_metadata() {
+        let metadata = ArtifactMetadata {
+            group_id: "dev.kingtux".to_owned(),
+            artifact_id: "tms".to_owned(),
+            versions: vec![
+                "1.0.0".to_owned(),
+                "1.1.0".to_owned(),
+                "1.2.0-SNAPSHOT".to_owned(),
+            ],
+            last_updated: Utc.with_ymd_and_hms(2024, 1, 2, 12, 0, 0).unwrap(),
+        };
+        assert_eq!(metadata.latest(), Some("1.2.0-SNAPSHOT"));
+        assert_eq!(metadata.release(), Some("1.1.0"));
+        let xml = metadata.to_xml();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<metadata>\n"));
+        assert!(xml.contains("    <release>1.1.0</release>\n"));
+        assert!(xml.ends_with("</metadata>\n"));
+    }
+    #[test]
+    fn plugin_prefixes() {
+        assert_eq!(
+            PluginMetadata::default_prefix("maven-compiler-plugin").as_deref(),
+            Some("compiler")
+        );
+        assert_eq!(
+            PluginMetadata::default_prefix("spotless-maven-plugin").as_deref(),
+            Some("spotless")
+        );
+        assert_eq!(PluginMetadata::default_prefix("tms"), None);
+    }
+    #[test]
+    fn metadata_checksums() {
+        let files = metadata_files(
+            &StoragePath::from("dev/kingtux/tms"),
+            "<metadata/>".to_owned(),
+        );
+        assert_eq!(files.len(), 5);
+        assert_eq!(files[0].0.to_string(), "dev/kingtux/tms/maven-metadata.xml");
+        assert_eq!(
+            files[2].0.to_string(),
+            "dev/kingtux/tms/maven-metadata.xml.sha1"
+        );
+        assert_eq!(files[2].1, MavenChecksumType::Sha1.digest(b"<metadata/>"));
+    }
+}
@@ -22,10 +22,15 @@ use nr_storage::DynStorage;
 use proxy::MavenProxy;
 mod configs;
 use super::{DynRepository, Repository, RepositoryFactoryError, RepositoryType};
+pub mod checksum;
+pub mod gradle_module;
 pub mod hosted;
+pub mod metadata;
 pub mod nitro_deploy;
+pub mod pom;
 pub mod proxy;
 pub mod utils;
+pub mod version;
 pub static REPOSITORY_TYPE_ID: &str = "maven";
 #[derive(Debug, Default)]
 pub struct MavenRepositoryType;
@@ -108,6 +113,17 @@ pub enum MavenRepository {
     Hosted(MavenHosted),
     Proxy(MavenProxy),
 }
+/// The ids of every loaded Maven hosted repository
+pub fn hosted_repository_ids(site: &NitroRepo) -> Vec<uuid::Uuid> {
+    site.repositories
+        .read()
+        .iter()
+        .filter(|(_, repository)| {
+            matches!(repository, DynRepository::Maven(MavenRepository::Hosted(_)))
+        })
+        .map(|(id, _)| *id)
+        .collect()
+}
 impl MavenRepository {
     pub async fn load(
         repo: DBRepository,
@@ -147,6 +163,8 @@ pub enum MavenError {
 
     #[error("Missing From Pom: {0}")]
     MissingFromPom(&'static str),
+    #[error("No upstream repository could be reached. Try again later")]
+    UpstreamUnavailable,
     #[error("{0}")]
     Other(Box<dyn IntoErrorResponse>),
 }
@@ -197,6 +215,12 @@ impl IntoResponse for MavenError {
                     err
                 )))
                 .unwrap(),
+            MavenError::UpstreamUnavailable => axum::http::Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(axum::body::Body::from(
+                    MavenError::UpstreamUnavailable.to_string(),
+                ))
+                .unwrap(),
             MavenError::MavenRS(e) => axum::http::Response::builder()
                 .status(500)
                 .body(axum::body::Body::from(format!("Maven Error: {}", e)))
@@ -1,7 +1,19 @@
-use nr_core::repository::project::VersionData;
+//! Nitro Deploy for Maven.
+//!
+//! 1. The client POSTs a [NewNRMavenDeploy] to the repository and receives a `deploy_id`
+//! 2. Each file is PUT to `{deploy_id}/{file_name}` and staged after its checksums are verified
+//! 3. A POST to `{deploy_id}` publishes every file at once and creates the version
+//!
+//! Every request must contain the header `x-nitro-repo-deploy: maven 1`
+use nr_core::{repository::project::VersionData, storage::StoragePath};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use utoipa::ToSchema;
 use uuid::Uuid;
+
+use super::checksum::{ChecksumMismatch, MavenChecksumType};
+/// The version of the Nitro Deploy protocol supported by Maven repositories
+pub const NITRO_MAVEN_DEPLOY_VERSION: u8 = 1;
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 pub enum MavenFileType {
     Pom,
@@ -21,6 +33,91 @@ pub struct NewNRMavenDeploy {
     #[serde(default)]
     pub extra: Option<VersionData>,
 }
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InvalidNRMavenDeploy {
+    #[error("Deploy is missing the {0}")]
+    MissingField(&'static str),
+    #[error("Deploy does not contain any files")]
+    NoFiles,
+    #[error(
+        "Invalid file name {0}. Files must be named `{{artifactId}}-{{version}}` followed by a classifier or extension"
+    )]
+    InvalidFileName(String),
+    #[error("File {0} is declared more than once")]
+    DuplicateFile(String),
+    #[error(
+        "Invalid {field} {value}. It can not contain empty, `.` or `..` path segments or slashes"
+    )]
+    InvalidPathSegment { field: &'static str, value: String },
+}
+/// If the segment can not be used as a directory name. Such as `..` which would leave the repository
+fn is_invalid_path_segment(segment: &str) -> bool {
+    segment.is_empty() || segment == "." || segment == ".." || segment.contains(['/', '\\'])
+}
+impl NewNRMavenDeploy {
+    pub fn validate(&self) -> Result<(), InvalidNRMavenDeploy> {
+        for (value, field) in [
+            (&self.group_id, "groupId"),
+            (&self.artifact_id, "artifactId"),
+            (&self.version, "version"),
+        ] {
+            if value.trim().is_empty() {
+                return Err(InvalidNRMavenDeploy::MissingField(field));
+            }
+        }
+        // Each segment of the groupId becomes a directory
+        let invalid_field = if self.group_id.split('.').any(is_invalid_path_segment) {
+            Some(("groupId", &self.group_id))
+        } else if is_invalid_path_segment(&self.artifact_id) {
+            Some(("artifactId", &self.artifact_id))
+        } else if is_invalid_path_segment(&self.version) {
+            Some(("version", &self.version))
+        } else {
+            None
+        };
+        if let Some((field, value)) = invalid_field {
+            return Err(InvalidNRMavenDeploy::InvalidPathSegment {
+                field,
+                value: value.clone(),
+            });
+        }
+        if self.files.is_empty() {
+            return Err(InvalidNRMavenDeploy::NoFiles);
+        }
+        let prefix = format!("{}-{}", self.artifact_id, self.version);
+        for (index, file) in self.files.iter().enumerate() {
+            let file_name = &file.file_name;
+            if !file_name.starts_with(&prefix)
+                || file_name.len() == prefix.len()
+                || file_name.contains(['/', '\\'])
+            {
+                return Err(InvalidNRMavenDeploy::InvalidFileName(file_name.clone()));
+            }
+            if self.files[..index]
+                .iter()
+                .any(|other| &other.file_name == file_name)
+            {
+                return Err(InvalidNRMavenDeploy::DuplicateFile(file_name.clone()));
+            }
+        }
+        Ok(())
+    }
+    /// `{groupId}/{artifactId}/{version}`
+    pub fn version_directory(&self) -> StoragePath {
+        StoragePath::from(format!(
+            "{}/{}/{}",
+            self.group_id.replace('.', "/"),
+            self.artifact_id,
+            self.version
+        ))
+    }
+    pub fn project_key(&self) -> String {
+        format!("{}:{}", self.group_id, self.artifact_id)
+    }
+    pub fn get_file(&self, file_name: &str) -> Option<&NewNRMavenDeployFile> {
+        self.files.iter().find(|file| file.file_name == file_name)
+    }
+}
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct NewNRMavenDeployFile {
     pub file_name: String,
@@ -29,6 +126,26 @@ pub struct NewNRMavenDeployFile {
     pub md5: String,
     pub maven_file_type: Option<MavenFileType>,
 }
+impl NewNRMavenDeployFile {
+    /// Checks the uploaded file against the declared checksums
+    pub fn verify(&self, bytes: &[u8]) -> Result<(), ChecksumMismatch> {
+        for (checksum_type, expected) in [
+            (MavenChecksumType::Sha1, &self.sha1),
+            (MavenChecksumType::Sha512, &self.sha512),
+            (MavenChecksumType::Md5, &self.md5),
+        ] {
+            let actual = checksum_type.digest(bytes);
+            if !actual.eq_ignore_ascii_case(expected.trim()) {
+                return Err(ChecksumMismatch {
+                    checksum_type,
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+}
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct NewNrMavenDeploySuccessResponse {
     pub deploy_id: Uuid,
@@ -43,3 +160,90 @@ pub struct NRMavenPublishSuccessFile {
     pub name: String,
     pub path: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn file(file_name: &str, content: &[u8]) -> NewNRMavenDeployFile {
+        NewNRMavenDeployFile {
+            file_name: file_name.to_owned(),
+            sha512: MavenChecksumType::Sha512.digest(content),
+            sha1: MavenChecksumType::Sha1.digest(content),
+            md5: MavenChecksumType::Md5.digest(content),
+            maven_file_type: None,
+        }
+    }
+    fn deploy(files: Vec<NewNRMavenDeployFile>) -> NewNRMavenDeploy {
+        NewNRMavenDeploy {
+            group_id: "dev.kingtux".to_owned(),
+            artifact_id: "tms".to_owned(),
+            name: "tms".to_owned(),
+            version: "1.0.0".to_owned(),
+            tags: vec![],
+            files,
+            extra: None,
+        }
+    }
+    #[test]
+    fn validate() {
+        let valid = deploy(vec![
+            file("tms-1.0.0.pom", b"pom"),
+            file("tms-1.0.0-sources.jar", b"sources"),
+        ]);
+        assert_eq!(valid.validate(), Ok(()));
+        assert_eq!(
+            valid.version_directory().to_string(),
+            "dev/kingtux/tms/1.0.0"
+        );
+        assert_eq!(
+            deploy(vec![]).validate(),
+            Err(InvalidNRMavenDeploy::NoFiles)
+        );
+        let invalid = |group_id: &str, artifact_id: &str, version: &str| {
+            let mut deploy = deploy(vec![file("tms-1.0.0.pom", b"pom")]);
+            deploy.group_id = group_id.to_owned();
+            deploy.artifact_id = artifact_id.to_owned();
+            deploy.version = version.to_owned();
+            match deploy.validate() {
+                Err(InvalidNRMavenDeploy::InvalidPathSegment { field, .. }) => Some(field),
+                _ => None,
+            }
+        };
+        assert_eq!(invalid("dev..kingtux", "tms", "1.0.0"), Some("groupId"));
+        assert_eq!(invalid(".dev", "tms", "1.0.0"), Some("groupId"));
+        assert_eq!(invalid("dev/kingtux", "tms", "1.0.0"), Some("groupId"));
+        assert_eq!(invalid("dev\\kingtux", "tms", "1.0.0"), Some("groupId"));
+        assert_eq!(invalid("dev.kingtux", "..", "1.0.0"), Some("artifactId"));
+        assert_eq!(invalid("dev.kingtux", ".", "1.0.0"), Some("artifactId"));
+        assert_eq!(
+            invalid("dev.kingtux", "tms\\..", "1.0.0"),
+            Some("artifactId")
+        );
+        assert_eq!(invalid("dev.kingtux", "tms", ".."), Some("version"));
+        assert_eq!(invalid("dev.kingtux", "tms", "1.0/0"), Some("version"));
+        assert_eq!(invalid("dev.kingtux", "tms", "1.0.0"), None);
+        assert_eq!(
+            deploy(vec![file("../tms-1.0.0.pom", b"pom")]).validate(),
+            Err(InvalidNRMavenDeploy::InvalidFileName(
+                "../tms-1.0.0.pom".to_owned()
+            ))
+        );
+        assert_eq!(
+            deploy(vec![
+                file("tms-1.0.0.pom", b"pom"),
+                file("tms-1.0.0.pom", b"pom")
+            ])
+            .validate(),
+            Err(InvalidNRMavenDeploy::DuplicateFile(
+                "tms-1.0.0.pom".to_owned()
+            ))
+        );
+    }
+    #[test]
+    fn verify() {
+        let declared = file("tms-1.0.0.jar", b"jar");
+        assert!(declared.verify(b"jar").is_ok());
+        let mismatch = declared.verify(b"other").unwrap_err();
+        assert_eq!(mismatch.checksum_type, MavenChecksumType::Sha1);
+    }
+}
@@ -0,0 +1,595 @@
+//! The descriptive parts of a POM that are stored in [VersionData].
+//!
+//! Values missing from a POM are inherited from its `<parent>` and `${...}` properties are interpolated
+//! against the inherited model. The same way Maven builds the effective POM.
+use ahash::HashMap;
+use nr_core::{
+    repository::project::{Author, Licence, LicenceValue, ProjectSource, VersionData},
+    storage::StoragePath,
+};
+use serde::Deserialize;
+
+use super::MavenError;
+use crate::utils::bad_request::BadRequestErrors;
+/// Parents are not followed past this depth. Protects against cyclic parents
+pub const MAX_PARENT_DEPTH: usize = 10;
+/// How many times a value is interpolated. Allows properties that reference other properties
+const MAX_INTERPOLATION_PASSES: usize = 5;
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PomParent {
+    pub group_id: Option<String>,
+    pub artifact_id: Option<String>,
+    pub version: Option<String>,
+}
+impl PomParent {
+    /// `{groupId}/{artifactId}/{version}/{artifactId}-{version}.pom`
+    pub fn pom_path(&self) -> Option<StoragePath> {
+        let group_id = self.group_id.as_deref()?;
+        let artifact_id = self.artifact_id.as_deref()?;
+        let version = self.version.as_deref()?;
+        Some(StoragePath::from(format!(
+            "{}/{}/{}/{}-{}.pom",
+            group_id.replace('.', "/"),
+            artifact_id,
+            version,
+            artifact_id,
+            version
+        )))
+    }
+}
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PomLicense {
+    pub name: Option<String>,
+    pub url: Option<String>,
+}
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PomLicenses {
+    pub license: Vec<PomLicense>,
+}
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PomDeveloper {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub url: Option<String>,
+}
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PomDevelopers {
+    pub developer: Vec<PomDeveloper>,
+}
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PomScm {
+    pub url: Option<String>,
+    pub connection: Option<String>,
+    pub developer_connection: Option<String>,
+    pub tag: Option<String>,
+}
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PomIssueManagement {
+    pub system: Option<String>,
+    pub url: Option<String>,
+}
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PomDependency {
+    pub group_id: Option<String>,
+    pub artifact_id: Option<String>,
+    pub version: Option<String>,
+    pub scope: Option<String>,
+    pub optional: Option<String>,
+}
+impl PomDependency {
+    fn is_same_artifact(&self, other: &PomDependency) -> bool {
+        self.group_id == other.group_id && self.artifact_id == other.artifact_id
+    }
+}
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PomDependencies {
+    pub dependency: Vec<PomDependency>,
+}
+impl PomDependencies {
+    /// Adds the dependencies of the parent that are not redeclared
+    fn inherit(&mut self, parent: PomDependencies) {
+        for dependency in parent.dependency {
+            if !self
+                .dependency
+                .iter()
+                .any(|declared| declared.is_same_artifact(&dependency))
+            {
+                self.dependency.push(dependency);
+            }
+        }
+    }
+}
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PomDependencyManagement {
+    pub dependencies: Option<PomDependencies>,
+}
+/// A dependency after properties and `<dependencyManagement>` have been applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MavenDependency {
+    pub group_id: String,
+    pub artifact_id: String,
+    /// A version or a version range. None if it is managed by something that could not be resolved. Such as an imported BOM
+    pub version: Option<String>,
+    pub scope: String,
+    pub optional: bool,
+}
+/// The parts of a POM that describe the project.
+///
+/// Parsed separately from [maven_rs::pom::Pom] so unknown or broken sections of the POM do not matter
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PomMetadata {
+    pub parent: Option<PomParent>,
+    pub group_id: Option<String>,
+    pub artifact_id: Option<String>,
+    pub version: Option<String>,
+    /// Not inherited from the parent. Defaults to `jar`
+    pub packaging: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub licenses: Option<PomLicenses>,
+    pub developers: Option<PomDevelopers>,
+    pub scm: Option<PomScm>,
+    pub issue_management: Option<PomIssueManagement>,
+    pub properties: HashMap<String, String>,
+    pub dependencies: Option<PomDependencies>,
+    pub dependency_management: Option<PomDependencyManagement>,
+}
+impl PomMetadata {
+    pub fn parse(pom: &[u8]) -> Result<Self, MavenError> {
+        let pom = String::from_utf8(pom.to_vec()).map_err(BadRequestErrors::from)?;
+        Ok(maven_rs::quick_xml::de::from_str(&pom)?)
+    }
+    /// If the POM builds a Maven plugin
+    pub fn is_maven_plugin(&self) -> bool {
+        self.packaging.as_deref() == Some("maven-plugin")
+    }
+    /// Fills in everything this POM does not define from its parent.
+    ///
+    /// Call with the closest parent first
+    pub fn inherit(&mut self, parent: PomMetadata) {
+        let parent_coordinates = self.parent.clone().unwrap_or_default();
+        if self.group_id.is_none() {
+            self.group_id = parent_coordinates.group_id.or(parent.group_id);
+        }
+        if self.version.is_none() {
+            self.version = parent_coordinates.version.or(parent.version);
+        }
+        self.description = self.description.take().or(parent.description);
+        self.url = self.url.take().or(parent.url);
+        self.licenses = self.licenses.take().or(parent.licenses);
+        self.developers = self.developers.take().or(parent.developers);
+        self.scm = self.scm.take().or(parent.scm);
+        self.issue_management = self.issue_management.take().or(parent.issue_management);
+        for (key, value) in parent.properties {
+            self.properties.entry(key).or_insert(value);
+        }
+        if let Some(parent_dependencies) = parent.dependencies {
+            self.dependencies
+                .get_or_insert_with(Default::default)
+                .inherit(parent_dependencies);
+        }
+        let Some(parent_managed) = parent.dependency_management.and_then(|m| m.dependencies) else {
+            return;
+        };
+        self.dependency_management
+            .get_or_insert_with(Default::default)
+            .dependencies
+            .get_or_insert_with(Default::default)
+            .inherit(parent_managed);
+    }
+    /// The values `${...}` expressions can reference
+    fn interpolation_values(&self) -> HashMap<String, String> {
+        let mut values = self.properties.clone();
+        let parent = self.parent.clone().unwrap_or_default();
+        for (key, value) in [
+            ("groupId", &self.group_id),
+            ("artifactId", &self.artifact_id),
+            ("version", &self.version),
+            ("name", &self.name),
+            ("description", &self.description),
+            ("url", &self.url),
+            ("parent.groupId", &parent.group_id),
+            ("parent.artifactId", &parent.artifact_id),
+            ("parent.version", &parent.version),
+        ] {
+            let Some(value) = value else {
+                continue;
+            };
+            values.insert(format!("project.{}", key), value.clone());
+            values.insert(format!("pom.{}", key), value.clone());
+        }
+        values
+    }
+    /// Replaces `${...}` expressions in every value that is copied into [VersionData]
+    pub fn interpolate(&mut self) {
+        let values = self.interpolation_values();
+        let interpolate = |value: &mut Option<String>| {
+            if let Some(value) = value.as_mut() {
+                *value = interpolate_value(value, &values);
+            }
+        };
+        interpolate(&mut self.description);
+        interpolate(&mut self.url);
+        for license in self.licenses.iter_mut().flat_map(|l| l.license.iter_mut()) {
+            interpolate(&mut license.name);
+            interpolate(&mut license.url);
+        }
+        for developer in self
+            .developers
+            .iter_mut()
+            .flat_map(|d| d.developer.iter_mut())
+        {
+            interpolate(&mut developer.name);
+            interpolate(&mut developer.email);
+            interpolate(&mut developer.url);
+        }
+        if let Some(scm) = self.scm.as_mut() {
+            interpolate(&mut scm.url);
+            interpolate(&mut scm.connection);
+            interpolate(&mut scm.developer_connection);
+            interpolate(&mut scm.tag);
+        }
+        if let Some(issue_management) = self.issue_management.as_mut() {
+            interpolate(&mut issue_management.url);
+        }
+        let managed = self
+            .dependency_management
+            .iter_mut()
+            .flat_map(|m| m.dependencies.iter_mut());
+        for dependency in self
+            .dependencies
+            .iter_mut()
+            .chain(managed)
+            .flat_map(|d| d.dependency.iter_mut())
+        {
+            interpolate(&mut dependency.group_id);
+            interpolate(&mut dependency.artifact_id);
+            interpolate(&mut dependency.version);
+            interpolate(&mut dependency.scope);
+            interpolate(&mut dependency.optional);
+        }
+    }
+    /// Applies the parents and interpolates the result.
+    ///
+    /// `parents` must be ordered from the closest parent to the furthest
+    pub fn resolve(mut self, parents: impl IntoIterator<Item = PomMetadata>) -> Self {
+        for parent in parents {
+            self.inherit(parent);
+        }
+        self.interpolate();
+        self
+    }
+    fn licence(&self) -> Option<Licence> {
+        let licenses: Vec<LicenceValue> = self
+            .licenses
+            .iter()
+            .flat_map(|licenses| licenses.license.iter())
+            .filter_map(|license| {
+                let name = license.name.clone().or_else(|| license.url.clone())?;
+                Some(LicenceValue {
+                    name,
+                    url: license.url.clone(),
+                })
+            })
+            .collect();
+        if licenses.is_empty() {
+            None
+        } else {
+            Some(Licence::Array(licenses))
+        }
+    }
+    fn authors(&self) -> Vec<Author> {
+        self.developers
+            .iter()
+            .flat_map(|developers| developers.developer.iter())
+            .filter(|developer| {
+                developer.name.is_some() || developer.email.is_some() || developer.url.is_some()
+            })
+            .map(|developer| Author {
+                name: developer.name.clone(),
+                email: developer.email.clone(),
+                website: developer.url.clone(),
+            })
+            .collect()
+    }
+    /// Only git is supported by [ProjectSource]. SCM with another connection type is ignored
+    fn source(&self) -> Option<ProjectSource> {
+        let scm = self.scm.as_ref()?;
+        let connection = scm
+            .connection
+            .as_deref()
+            .or(scm.developer_connection.as_deref());
+        if connection.is_some_and(|connection| !connection.starts_with("scm:git:")) {
+            return None;
+        }
+        let url = scm.url.clone().or_else(|| {
+            connection
+                .and_then(|connection| connection.strip_prefix("scm:git:"))
+                .map(str::to_owned)
+        })?;
+        let commit = scm.tag.clone().filter(|tag| tag != "HEAD");
+        Some(ProjectSource::Git {
+            url,
+            branch: None,
+            commit,
+        })
+    }
+    /// The dependencies of the project. Versions and scopes missing from a dependency are taken from `<dependencyManagement>`
+    pub fn dependencies(&self) -> Vec<MavenDependency> {
+        let managed: Vec<&PomDependency> = self
+            .dependency_management
+            .iter()
+            .flat_map(|m| m.dependencies.iter())
+            .flat_map(|d| d.dependency.iter())
+            .collect();
+        self.dependencies
+            .iter()
+            .flat_map(|d| d.dependency.iter())
+            .filter_map(|dependency| {
+                let managed = managed
+                    .iter()
+                    .find(|managed| managed.is_same_artifact(dependency));
+                let version = dependency
+                    .version
+                    .clone()
+                    .or_else(|| managed.and_then(|managed| managed.version.clone()));
+                let scope = dependency
+                    .scope
+                    .clone()
+                    .or_else(|| managed.and_then(|managed| managed.scope.clone()))
+                    .unwrap_or_else(|| "compile".to_owned());
+                Some(MavenDependency {
+                    group_id: dependency.group_id.clone()?,
+                    artifact_id: dependency.artifact_id.clone()?,
+                    version,
+                    scope,
+                    optional: dependency
+                        .optional
+                        .as_deref()
+                        .is_some_and(|optional| optional.trim() == "true"),
+                })
+            })
+            .collect()
+    }
+    pub fn version_data(&self) -> VersionData {
+        VersionData {
+            website: self.url.clone(),
+            authors: self.authors(),
+            description: self.description.clone(),
+            source: self.source(),
+            licence: self.licence(),
+            issues_url: self
+                .issue_management
+                .as_ref()
+                .and_then(|issue_management| issue_management.url.clone()),
+            ..Default::default()
+        }
+    }
+}
+/// Replaces `${key}` with its value. Unknown keys are left as is
+fn interpolate_value(value: &str, values: &HashMap<String, String>) -> String {
+    let mut value = value.to_owned();
+    for _ in 0..MAX_INTERPOLATION_PASSES {
+        if !value.contains("${") {
+            break;
+        }
+        let mut result = String::with_capacity(value.len());
+        let mut rest = value.as_str();
+        while let Some(start) = rest.find("${") {
+            result.push_str(&rest[..start]);
+            let expression = &rest[start + 2..];
+            let Some(end) = expression.find('}') else {
+                result.push_str(&rest[start..]);
+                rest = "";
+                break;
+            };
+            let key = &expression[..end];
+            match values.get(key) {
+                Some(replacement) => result.push_str(replacement),
+                None => result.push_str(&rest[start..start + end + 3]),
+            }
+            rest = &expression[end + 1..];
+        }
+        result.push_str(rest);
+        if result == value {
+            break;
+        }
+        value = result;
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const PARENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<project>
+    <groupId>dev.kingtux</groupId>
+    <artifactId>parent</artifactId>
+    <version>1.0.0</version>
+    <url>https://github.com/wyatt-herkamp/${project.artifactId}</url>
+    <licenses>
+        <license>
+            <name>MIT</name>
+            <url>https://opensource.org/licenses/MIT</url>
+        </license>
+    </licenses>
+    <developers>
+        <developer>
+            <name>Wyatt Herkamp</name>
+            <email>wherkamp@gmail.com</email>
+        </developer>
+    </developers>
+    <scm>
+        <connection>scm:git:https://github.com/wyatt-herkamp/tms.git</connection>
+        <tag>v${project.version}</tag>
+    </scm>
+    <properties>
+        <issues.url>https://github.com/wyatt-herkamp/tms/issues</issues.url>
+        <serde.version>1.0.0</serde.version>
+    </properties>
+    <dependencyManagement>
+        <dependencies>
+            <dependency>
+                <groupId>dev.kingtux</groupId>
+                <artifactId>serde</artifactId>
+                <version>${serde.version}</version>
+                <scope>provided</scope>
+            </dependency>
+        </dependencies>
+    </dependencyManagement>
+    <dependencies>
+        <dependency>
+            <groupId>org.junit</groupId>
+            <artifactId>junit</artifactId>
+            <version>5.0.0</version>
+            <scope>test</scope>
+        </dependency>
+    </dependencies>
+</project>"#;
+    const CHILD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<project>
+    <parent>
+        <groupId>dev.kingtux</groupId>
+        <artifactId>parent</artifactId>
+        <version>1.0.0</version>
+    </parent>
+    <artifactId>tms</artifactId>
+    <version>2.0.0</version>
+    <description>Version ${project.version} of ${project.artifactId}</description>
+    <issueManagement>
+        <system>GitHub</system>
+        <url>${issues.url}</url>
+    </issueManagement>
+    <dependencies>
+        <dependency>
+            <groupId>dev.kingtux</groupId>
+            <artifactId>serde</artifactId>
+        </dependency>
+        <dependency>
+            <groupId>${project.groupId}</groupId>
+            <artifactId>tms-core</artifactId>
+            <version>[${project.version},3.0)</version>
+            <optional>true</optional>
+        </dependency>
+    </dependencies>
+</project>"#;
+    #[test]
+    fn parent_path() {
+        let child = PomMetadata::parse(CHILD.as_bytes()).unwrap();
+        assert_eq!(
+            child.parent.unwrap().pom_path().unwrap().to_string(),
+            "dev/kingtux/parent/1.0.0/parent-1.0.0.pom"
+        );
+    }
+    #[test]
+    fn resolve() {
+        let parent = PomMetadata::parse(PARENT.as_bytes()).unwrap();
+        let child = PomMetadata::parse(CHILD.as_bytes())
+            .unwrap()
+            .resolve([parent]);
+        assert_eq!(child.group_id.as_deref(), Some("dev.kingtux"));
+        let data = child.version_data();
+        assert_eq!(data.description.as_deref(), Some("Version 2.0.0 of tms"));
+        assert_eq!(
+            data.website.as_deref(),
+            Some("https://github.com/wyatt-herkamp/tms")
+        );
+        assert_eq!(
+            data.issues_url.as_deref(),
+            Some("https://github.com/wyatt-herkamp/tms/issues")
+        );
+        assert_eq!(
+            data.licence,
+            Some(Licence::Array(vec![LicenceValue {
+                name: "MIT".to_owned(),
+                url: Some("https://opensource.org/licenses/MIT".to_owned()),
+            }]))
+        );
+        assert_eq!(data.authors.len(), 1);
+        assert_eq!(
+            data.source,
+            Some(ProjectSource::Git {
+                url: "https://github.com/wyatt-herkamp/tms.git".to_owned(),
+                branch: None,
+                commit: Some("v2.0.0".to_owned()),
+            })
+        );
+    }
+    #[test]
+    fn dependencies() {
+        let parent = PomMetadata::parse(PARENT.as_bytes()).unwrap();
+        let child = PomMetadata::parse(CHILD.as_bytes())
+            .unwrap()
+            .resolve([parent]);
+        assert_eq!(
+            child.dependencies(),
+            vec![
+                MavenDependency {
+                    group_id: "dev.kingtux".to_owned(),
+                    artifact_id: "serde".to_owned(),
+                    version: Some("1.0.0".to_owned()),
+                    scope: "provided".to_owned(),
+                    optional: false,
+                },
+                MavenDependency {
+                    group_id: "dev.kingtux".to_owned(),
+                    artifact_id: "tms-core".to_owned(),
+                    version: Some("[2.0.0,3.0)".to_owned()),
+                    scope: "compile".to_owned(),
+                    optional: true,
+                },
+                MavenDependency {
+                    group_id: "org.junit".to_owned(),
+                    artifact_id: "junit".to_owned(),
+                    version: Some("5.0.0".to_owned()),
+                    scope: "test".to_owned(),
+                    optional: false,
+                },
+            ]
+        );
+    }
+    #[test]
+    fn maven_plugin_packaging() {
+        let child = PomMetadata::parse(CHILD.as_bytes()).unwrap();
+        assert!(!child.is_maven_plugin());
+        let plugin = PomMetadata::parse(
+            br#"<project>
+    <artifactId>tms-maven-plugin</artifactId>
+    <packaging>maven-plugin</packaging>
+</project>"#,
+        )
+        .unwrap();
+        assert!(plugin.is_maven_plugin());
+    }
+    #[test]
+    fn unknown_properties_are_kept() {
+        let values = HashMap::default();
+        assert_eq!(interpolate_value("${missing}-${", &values), "${missing}-${");
+    }
+    #[test]
+    fn non_git_scm_is_ignored() {
+        let pom = PomMetadata {
+            scm: Some(PomScm {
+                connection: Some("scm:svn:https://svn.example.com/tms".to_owned()),
+                url: Some("https://svn.example.com/tms".to_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(pom.version_data().source, None);
+    }
+}
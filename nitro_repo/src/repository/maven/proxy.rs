@@ -1,11 +1,15 @@
 use std::{
     ops::Deref,
     sync::{Arc, atomic::AtomicBool},
+    time::Duration,
 };
 
-use axum::response::Response;
+use axum::{body::Body, response::Response};
 use bytes::Bytes;
-use http::StatusCode;
+use http::{
+    StatusCode,
+    header::{CONTENT_LENGTH, CONTENT_TYPE, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+};
 use maven_rs::pom::Pom;
 use nr_core::{
     database::entities::repository::{DBRepository, DBRepositoryConfig},
@@ -20,22 +24,38 @@ use nr_core::{
     },
     storage::StoragePath,
 };
-use nr_storage::{DynStorage, FileContent, Storage, StorageFile};
+use nr_storage::{DynStorage, FileContent, FileType, Storage, StorageFile};
 use parking_lot::RwLock;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, instrument, warn};
 use uuid::Uuid;
 
-use crate::{app::NitroRepo, repository::Repository};
+use crate::{
+    app::NitroRepo,
+    repository::{
+        FileRequestHeaders, Repository, open_file_for_request,
+        proxy::{ProxyCacheMeta, ProxyClientError, ProxyCredentials, ProxyHttpSettings},
+    },
+};
 
 use super::{
     MavenError, MavenRepositoryConfig, MavenRepositoryConfigType, REPOSITORY_TYPE_ID, RepoResponse,
-    RepositoryRequest, repo_type::RepositoryFactoryError, utils::MavenRepositoryExt,
+    RepositoryRequest,
+    checksum::{MavenChecksumType, UpstreamChecksum},
+    gradle_module::{GRADLE_MODULE_EXTENSION, GradleModule},
+    repo_type::RepositoryFactoryError,
+    utils::MavenRepositoryExt,
 };
+mod cache;
+mod route_selection;
+pub use cache::*;
+pub use route_selection::*;
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MavenProxyConfig {
     pub routes: Vec<MavenProxyRepositoryRoute>,
+    #[serde(default)]
+    pub cache: MavenProxyCacheConfig,
 }
 impl MavenProxyConfig {
     pub fn sort(&mut self) {
@@ -53,7 +73,236 @@ pub struct MavenProxyRepositoryRoute {
     pub name: Option<String>,
     /// If Null then it will be the lowest priority
     pub priority: Option<i32>,
-    // TODO: Credentials
+    /// Credentials used to authenticate with the upstream repository
+    #[serde(default)]
+    pub credentials: Option<ProxyCredentials>,
+    #[serde(default)]
+    pub http: ProxyHttpSettings,
+}
+impl MavenProxyRepositoryRoute {
+    /// Removes any secrets so the route can be shown to the public
+    pub fn sanitize_for_public_view(&mut self) {
+        self.credentials = self.credentials.as_ref().map(ProxyCredentials::redacted);
+        self.http = self.http.redacted();
+    }
+}
+/// A route and the HTTP client used to make requests to it.
+///
+/// Clients are created when the config is loaded so connections are reused between requests.
+#[derive(Debug, Clone)]
+pub struct MavenProxyRoute {
+    pub config: MavenProxyRepositoryRoute,
+    pub client: reqwest::Client,
+}
+impl MavenProxyRoute {
+    /// Builds the clients for all routes. Sorted by priority
+    pub fn from_config(mut config: MavenProxyConfig) -> Result<Vec<Self>, ProxyClientError> {
+        config.sort();
+        config
+            .routes
+            .into_iter()
+            .map(|route| {
+                let client = route.http.build_client(route.credentials.as_ref())?;
+                Ok(MavenProxyRoute {
+                    config: route,
+                    client,
+                })
+            })
+            .collect()
+    }
+    /// Identifies the route in the route selection cache
+    pub fn key(&self) -> String {
+        self.config.url.to_string()
+    }
+    /// The name of the route or the url if it does not have one
+    pub fn display_name(&self) -> String {
+        self.config
+            .name
+            .clone()
+            .unwrap_or_else(|| self.config.url.to_string())
+    }
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.config.url, path.trim_start_matches('/'))
+    }
+    /// Requests the file from a single route.
+    ///
+    /// Artifacts are verified against the checksum files the route publishes next to them.
+    async fn request(
+        &self,
+        path: &StoragePath,
+        validators: Option<&ProxyCacheMeta>,
+    ) -> Result<RouteResponse, reqwest::Error> {
+        let url = self.url(&path.to_string());
+        debug!(?url, "Proxying request");
+        let mut request = self.client.get(&url);
+        if let Some(validators) = validators {
+            if let Some(etag) = &validators.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(RouteResponse::NotModified);
+        }
+        if response.status().is_server_error() {
+            warn!(?response, ?url, "Upstream returned a server error");
+            return Ok(RouteResponse::Unavailable(response.status()));
+        }
+        if !response.status().is_success() {
+            warn!(?response, ?url, "Failed to proxy request");
+            return Ok(RouteResponse::Missing);
+        }
+        let cache_meta = ProxyCacheMeta::from_response_headers(response.headers());
+        let bytes = response.bytes().await?;
+        let checksums = if MavenChecksumType::from_path(path).is_some() {
+            Vec::new()
+        } else {
+            self.request_checksums(path).await
+        };
+        if let Err(err) = UpstreamChecksum::verify_all(&bytes, &checksums) {
+            error!(
+                ?err,
+                ?url,
+                "File from upstream does not match its checksums"
+            );
+            return Ok(RouteResponse::Missing);
+        }
+        Ok(RouteResponse::Found(UpstreamFile {
+            bytes,
+            cache_meta,
+            checksums,
+        }))
+    }
+    /// Requests all checksum files for the artifact. Missing checksum files are skipped
+    async fn request_checksums(&self, artifact: &StoragePath) -> Vec<UpstreamChecksum> {
+        let requests = MavenChecksumType::ALL
+            .into_iter()
+            .map(|checksum_type| async move {
+                let url = self.url(&checksum_type.checksum_path(artifact).to_string());
+                let response = match self.client.get(&url).send().await {
+                    Ok(ok) if ok.status().is_success() => ok,
+                    Ok(_) => return None,
+                    Err(err) => {
+                        warn!(?err, ?url, "Failed to request checksum");
+                        return None;
+                    }
+                };
+                match response.bytes().await {
+                    Ok(content) => Some(UpstreamChecksum {
+                        checksum_type,
+                        content,
+                    }),
+                    Err(err) => {
+                        warn!(?err, ?url, "Failed to read checksum");
+                        None
+                    }
+                }
+            });
+        futures::future::join_all(requests)
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+/// A file downloaded from an upstream route
+#[derive(Debug)]
+struct UpstreamFile {
+    bytes: Bytes,
+    cache_meta: ProxyCacheMeta,
+    /// The checksum files published next to the file. The file has already been verified against them
+    checksums: Vec<UpstreamChecksum>,
+}
+/// The result of requesting a file from a single route
+#[derive(Debug)]
+enum RouteResponse {
+    Found(UpstreamFile),
+    NotModified,
+    /// The route does not have the file or the file did not match its checksums
+    Missing,
+    /// The route responded with a server error
+    Unavailable(StatusCode),
+}
+/// The result of requesting a file from the upstream routes
+#[derive(Debug)]
+enum UpstreamResponse {
+    Found {
+        file: UpstreamFile,
+        route: MavenProxyRoute,
+    },
+    /// The upstream confirmed the cached file is still current
+    NotModified,
+    /// Every route responded that it does not have the file
+    NotFound,
+    /// A route failed or was skipped for being unhealthy. Nothing is cached because the file may still exist upstream
+    Unavailable,
+}
+/// Requests the file from each route.
+///
+/// The route that last served the project is tried first. Then the rest in order of priority.
+/// Routes that recently failed are skipped.
+///
+/// The file is only [UpstreamResponse::NotFound] if every route responded that it does not have it. Only then is it added to the not found cache.
+/// A route that failed or was skipped could still have the file.
+///
+/// If validators are provided the request is conditional and the upstream may respond with Not Modified.
+async fn lookup_upstream(
+    routes: Vec<MavenProxyRoute>,
+    route_selection: &RouteSelectionCache,
+    not_found: &NotFoundCache,
+    not_found_ttl: Duration,
+    path: &StoragePath,
+    validators: Option<&ProxyCacheMeta>,
+) -> UpstreamResponse {
+    let prefix = RouteSelectionCache::project_prefix(path);
+    let number_of_routes = routes.len();
+    let routes = route_selection.order_routes(prefix.as_deref(), routes, MavenProxyRoute::key);
+    let mut every_route_missing = routes.len() == number_of_routes;
+    if !every_route_missing {
+        debug!(
+            skipped = number_of_routes - routes.len(),
+            "Unhealthy routes skipped"
+        );
+    }
+    for route in routes {
+        let route_key = route.key();
+        match route.request(path, validators).await {
+            Ok(RouteResponse::Found(file)) => {
+                route_selection.record_success(&route_key);
+                if let Some(prefix) = &prefix {
+                    route_selection.record_selection(prefix, &route_key);
+                }
+                return UpstreamResponse::Found { file, route };
+            }
+            Ok(RouteResponse::NotModified) => {
+                route_selection.record_success(&route_key);
+                return UpstreamResponse::NotModified;
+            }
+            Ok(RouteResponse::Missing) => {
+                route_selection.record_success(&route_key);
+            }
+            Ok(RouteResponse::Unavailable(status)) => {
+                debug!(?status, url = %route.config.url, "Route is unavailable");
+                route_selection.record_failure(&route_key);
+                every_route_missing = false;
+            }
+            Err(err) => {
+                error!(?err, url = %route.config.url, "Failed to send request");
+                if err.is_timeout() || err.is_connect() {
+                    route_selection.record_failure(&route_key);
+                }
+                every_route_missing = false;
+            }
+        }
+    }
+    if !every_route_missing {
+        return UpstreamResponse::Unavailable;
+    }
+    not_found.insert(path, not_found_ttl);
+    UpstreamResponse::NotFound
 }
 fn project_download_files(pom: &Pom) -> Result<Vec<String>, MavenError> {
     let version = pom
@@ -63,8 +312,11 @@ fn project_download_files(pom: &Pom) -> Result<Vec<String>, MavenError> {
         format!("{}-{}.jar", pom.artifact_id, version),
         format!("{}-{}-sources.jar", pom.artifact_id, version),
         format!("{}-{}-javadoc.jar", pom.artifact_id, version),
+        format!("{}-{}{}", pom.artifact_id, version, GRADLE_MODULE_EXTENSION),
     ])
 }
+/// How many `available-at` redirects are followed when prefetching a Gradle Module
+const MAX_AVAILABLE_AT_DEPTH: usize = 2;
 #[derive(Debug)]
 pub struct MavenProxyInner {
     pub storage: DynStorage,
@@ -74,7 +326,11 @@ pub struct MavenProxyInner {
     pub visibility: RwLock<Visibility>,
     pub active: AtomicBool,
     pub project: RwLock<ProjectConfig>,
-    pub config: RwLock<MavenProxyConfig>,
+    pub routes: RwLock<Vec<MavenProxyRoute>>,
+    pub cache: RwLock<MavenProxyCacheConfig>,
+    /// Paths recently not found on any route
+    pub not_found: NotFoundCache,
+    pub route_selection: RouteSelectionCache,
 }
 #[derive(Debug, Clone)]
 pub struct MavenProxy(Arc<MavenProxyInner>);
@@ -97,12 +353,22 @@ impl MavenProxy {
                 site.as_ref(),
             )
             .await?;
+        let cache = proxy_config.cache.clone();
+        let routes = MavenProxyRoute::from_config(proxy_config).map_err(|err| {
+            RepositoryFactoryError::InvalidConfig(
+                MavenRepositoryConfigType::get_type_static(),
+                err.to_string(),
+            )
+        })?;
         let inner = MavenProxyInner {
             id: repository.id,
             name: repository.name.into(),
             active: AtomicBool::new(repository.active),
             visibility: RwLock::new(repository.visibility),
-            config: RwLock::new(proxy_config),
+            routes: RwLock::new(routes),
+            cache: RwLock::new(cache),
+            not_found: NotFoundCache::default(),
+            route_selection: RouteSelectionCache::default(),
             project: RwLock::new(project_config_db.value.0),
             storage,
             site,
@@ -119,40 +385,224 @@ impl MavenProxy {
             .await?;
         Ok(())
     }
+    /// Downloads a file from the route into the cache. Returns the content if the route had the file
+    async fn prefetch_file(
+        &self,
+        route: &MavenProxyRoute,
+        path: &StoragePath,
+    ) -> Result<Option<Bytes>, MavenError> {
+        debug!(?path, "Downloading file");
+        match route.request(path, None).await {
+            Ok(RouteResponse::Found(upstream_file)) => {
+                let bytes = upstream_file.bytes.clone();
+                self.save_upstream_file(path, upstream_file).await?;
+                Ok(Some(bytes))
+            }
+            Ok(_) => {
+                warn!(?path, "Failed to download file");
+                Ok(None)
+            }
+            Err(err) => {
+                warn!(?path, ?err, "Failed to download file");
+                Ok(None)
+            }
+        }
+    }
     #[instrument(skip(self), fields(nr.repository.id = %self.id, nr.repository.name = %self.name))]
     pub async fn proxy_project_download(
         &self,
         path: StoragePath,
-        proxy_config: MavenProxyRepositoryRoute,
-        pom: Bytes,
+        route: MavenProxyRoute,
+        pom_file: Bytes,
     ) -> Result<(), MavenError> {
-        let pom = self.parse_pom(pom.to_vec())?;
-        let version_dir = path.parent();
-        let http_client = reqwest::Client::builder()
-            .user_agent("Nitro Repo")
-            .build()
-            .expect("Failed to build HTTP Client");
+        let pom = self.parse_pom(pom_file.to_vec())?;
+        let version_dir = path.clone().parent();
 
         for file in project_download_files(&pom)? {
-            debug!(?file, "Downloading file");
             let mut path = version_dir.clone();
             path.push_mut(&file);
-            let url = format!("{}/{}", proxy_config.url, path);
-            match http_client.get(&url).send().await {
-                Ok(ok) => {
-                    if ok.status().is_success() {
-                        let bytes = ok.bytes().await?;
-                        self.save_bytes(bytes, &path).await?;
-                    } else {
-                        warn!(?url, ?file, ?ok, "Failed to download file");
-                    }
+            let Some(bytes) = self.prefetch_file(&route, &path).await? else {
+                continue;
+            };
+            if path.has_extension(GRADLE_MODULE_EXTENSION) {
+                self.proxy_module_files(&route, path, bytes).await;
+            }
+        }
+        // Proxied projects have no publisher
+        self.post_pom_upload(path, None, pom, &pom_file, Some(route.display_name()))
+            .await;
+        Ok(())
+    }
+    /// Downloads the files of a Gradle Module and the modules its variants are available at.
+    ///
+    /// Kotlin Multiplatform libraries point to a module per platform. Those are downloaded with their POM so Gradle resolves them from the cache
+    #[instrument(skip(self, module_file), fields(nr.repository.id = %self.id, nr.repository.name = %self.name))]
+    async fn proxy_module_files(
+        &self,
+        route: &MavenProxyRoute,
+        module_path: StoragePath,
+        module_file: Bytes,
+    ) {
+        let mut visited = vec![module_path.clone()];
+        let mut modules = vec![(module_path, module_file, 0)];
+        while let Some((module_path, module_file, depth)) = modules.pop() {
+            let module = match GradleModule::parse(&module_file) {
+                Ok(module) => module,
+                Err(error) => {
+                    warn!(?error, ?module_path, "Failed to parse Gradle Module");
+                    continue;
                 }
-                Err(err) => {
-                    warn!(?url, ?file, ?err, "Failed to download file");
+            };
+            let version_directory = module_path.clone().parent();
+            for file_path in module.file_paths(&version_directory) {
+                if let Err(error) = self.prefetch_file(route, &file_path).await {
+                    warn!(?error, ?file_path, "Failed to save file");
+                }
+            }
+            if depth > 0 {
+                self.proxy_module_pom(route, &module_path).await;
+            }
+            if depth >= MAX_AVAILABLE_AT_DEPTH {
+                continue;
+            }
+            for available_at in module.available_at_paths(&version_directory) {
+                if visited.contains(&available_at) {
+                    continue;
+                }
+                visited.push(available_at.clone());
+                match self.prefetch_file(route, &available_at).await {
+                    Ok(Some(bytes)) => modules.push((available_at, bytes, depth + 1)),
+                    Ok(None) => {}
+                    Err(error) => {
+                        warn!(?error, ?available_at, "Failed to save Gradle Module");
+                    }
                 }
             }
         }
-        // TODO: Trigger project indexing
+    }
+    /// Downloads the POM next to a Gradle Module and creates its project
+    async fn proxy_module_pom(&self, route: &MavenProxyRoute, module_path: &StoragePath) {
+        let Some(pom_path) = module_path
+            .to_string()
+            .strip_suffix(GRADLE_MODULE_EXTENSION)
+            .map(|path| StoragePath::from(format!("{path}.pom")))
+        else {
+            return;
+        };
+        let pom_file = match self.prefetch_file(route, &pom_path).await {
+            Ok(Some(pom_file)) => pom_file,
+            Ok(None) => return,
+            Err(error) => {
+                warn!(?error, ?pom_path, "Failed to save POM");
+                return;
+            }
+        };
+        let pom = match self.parse_pom(pom_file.to_vec()) {
+            Ok(pom) => pom,
+            Err(error) => {
+                warn!(?error, ?pom_path, "Failed to parse POM");
+                return;
+            }
+        };
+        self.post_pom_upload(pom_path, None, pom, &pom_file, Some(route.display_name()))
+            .await;
+    }
+    /// Downloads the files and POM of a proxied Gradle Module and stores its variants on the version.
+    ///
+    /// Gradle does not request the POM if a module is published
+    #[instrument(skip(self, module_file), fields(nr.repository.id = %self.id, nr.repository.name = %self.name))]
+    async fn proxy_module_download(
+        &self,
+        path: StoragePath,
+        route: MavenProxyRoute,
+        module_file: Bytes,
+    ) -> Result<(), MavenError> {
+        self.proxy_module_files(&route, path.clone(), module_file.clone())
+            .await;
+        self.proxy_module_pom(&route, &path).await;
+        self.post_module_upload(&path, &module_file).await
+    }
+    /// Downloads the rest of the project in the background when a pom or Gradle Module is proxied
+    fn spawn_project_download(&self, path: &StoragePath, route: MavenProxyRoute, file: Bytes) {
+        // TODO: Handle projects. When requesting a path such as /dev/kingtux/tms/1.0.0/tms-1.0.0.pom. Go ahead and download all files in that directory.
+        let self_clone = self.clone();
+        let path = path.clone();
+        if path.has_extension(".pom") {
+            tokio::spawn(async move {
+                if let Err(error) = self_clone.proxy_project_download(path, route, file).await {
+                    error!(?error, "Failed to download project files");
+                };
+            });
+        } else if path.has_extension(GRADLE_MODULE_EXTENSION) {
+            tokio::spawn(async move {
+                if let Err(error) = self_clone.proxy_module_download(path, route, file).await {
+                    error!(?error, "Failed to download Gradle Module files");
+                };
+            });
+        }
+    }
+    /// Requests the file from the upstream routes. A file every route confirmed as missing is remembered in the not found cache
+    #[instrument(skip(self, validators), fields(nr.repository.id = %self.id, nr.repository.name = %self.name))]
+    async fn request_upstream(
+        &self,
+        path: &StoragePath,
+        validators: Option<&ProxyCacheMeta>,
+    ) -> UpstreamResponse {
+        let routes = self.routes.read().clone();
+        let not_found_ttl = self.cache.read().not_found_ttl();
+        lookup_upstream(
+            routes,
+            &self.route_selection,
+            &self.not_found,
+            not_found_ttl,
+            path,
+            validators,
+        )
+        .await
+    }
+    /// Saves a file from the upstream and its checksum files.
+    ///
+    /// The validators are stored so the file can be revalidated later
+    async fn save_upstream_file(
+        &self,
+        path: &StoragePath,
+        file: UpstreamFile,
+    ) -> Result<(), MavenError> {
+        let UpstreamFile {
+            bytes,
+            cache_meta,
+            checksums,
+        } = file;
+        self.save_bytes(bytes, path).await?;
+        self.put_cache_meta(path, &cache_meta).await?;
+        let checksum_cache_meta = ProxyCacheMeta {
+            checked_at: cache_meta.checked_at,
+            ..Default::default()
+        };
+        for checksum in checksums {
+            let checksum_path = checksum.checksum_type.checksum_path(path);
+            self.save_bytes(checksum.content, &checksum_path).await?;
+            self.put_cache_meta(&checksum_path, &checksum_cache_meta)
+                .await?;
+            self.not_found.remove(&checksum_path);
+        }
+        self.not_found.remove(path);
+        Ok(())
+    }
+    async fn put_cache_meta(
+        &self,
+        path: &StoragePath,
+        cache_meta: &ProxyCacheMeta,
+    ) -> Result<(), MavenError> {
+        let mut meta = self
+            .storage
+            .get_repository_meta(self.id, path)
+            .await?
+            .unwrap_or_default();
+        cache_meta.apply_to(&mut meta);
+        self.storage
+            .put_repository_meta(self.id, path, meta)
+            .await?;
         Ok(())
     }
     #[instrument(skip(self), fields(nr.repository.id = %self.id, nr.repository.name = %self.name))]
@@ -160,59 +610,108 @@ impl MavenProxy {
         &self,
         path: StoragePath,
     ) -> Result<Option<StorageFile>, MavenError> {
-        // TODO: Setup internal cache to check the following
-        //  If a recent previous request was made with a similar path use that proxy config.
-        //  Similar path being both starting with /dev/kingtux/tms/... They should be in the same proxy
-        // TODO: Handle projects. When requesting a path such as /dev/kingtux/tms/1.0.0/tms-1.0.0.pom. Go ahead and download all files in that directory.
-        let proxy_config = self.config.read().clone();
-        let http_client = reqwest::Client::builder()
-            .user_agent("Nitro Repo")
-            .build()
-            .expect("Failed to build HTTP Client");
-        for route in proxy_config.routes {
-            let mut path_as_string = path.to_string();
-            if path_as_string.starts_with("/") {
-                path_as_string = path_as_string[1..].into();
+        match self.request_upstream(&path, None).await {
+            UpstreamResponse::Found { file, route } => {
+                self.spawn_project_download(&path, route, file.bytes.clone());
+                self.save_upstream_file(&path, file).await?;
+                Ok(self.storage.open_file(self.id, &path).await?)
             }
-            let url_string = format!("{}/{}", route.url, path_as_string);
-            debug!(?url_string, "Proxying request");
-            let url = match url::Url::parse(&url_string) {
-                Ok(ok) => ok,
-                Err(err) => {
-                    error!(?err, ?url_string, "Failed to parse URL");
-                    continue;
-                }
-            };
-            let response = match http_client.get(url).send().await {
+            UpstreamResponse::NotModified => Ok(self.storage.open_file(self.id, &path).await?),
+            UpstreamResponse::NotFound => Ok(None),
+            UpstreamResponse::Unavailable => Err(MavenError::UpstreamUnavailable),
+        }
+    }
+    /// Sends a HEAD request to each route.
+    ///
+    /// Build tools probe for files with HEAD requests before downloading them.
+    #[instrument(skip(self), fields(nr.repository.id = %self.id, nr.repository.name = %self.name))]
+    pub async fn head_from_proxy(&self, path: &StoragePath) -> RepoResponse {
+        let routes = self.routes.read().clone();
+        let mut every_route_missing = true;
+        for route in routes {
+            let url = route.url(&path.to_string());
+            let response = match route.client.head(&url).send().await {
                 Ok(ok) => ok,
                 Err(err) => {
-                    error!(?err, ?url_string, "Failed to send request");
+                    error!(?err, ?url, "Failed to send HEAD request");
+                    every_route_missing = false;
                     continue;
                 }
             };
-            if response.status().is_success() {
-                let response_bytes = response.bytes().await?;
-                if path_as_string.ends_with(".pom") {
-                    let self_clone = self.clone();
-                    let path = path.clone();
-                    let pom = response_bytes.clone();
-                    tokio::spawn(async move {
-                        if let Err(error) =
-                            self_clone.proxy_project_download(path, route, pom).await
-                        {
-                            error!(?error, "Failed to download project files");
-                        };
-                    });
+            if response.status().is_server_error() {
+                warn!(?response, ?url, "Upstream returned a server error");
+                every_route_missing = false;
+                continue;
+            }
+            if !response.status().is_success() {
+                debug!(?response, ?url, "File not found on route");
+                continue;
+            }
+            let mut builder = Response::builder().status(StatusCode::OK);
+            for header in [CONTENT_LENGTH, CONTENT_TYPE, LAST_MODIFIED] {
+                if let Some(value) = response.headers().get(&header) {
+                    builder = builder.header(header, value.clone());
                 }
-                self.storage
-                    .save_file(self.id, FileContent::Bytes(response_bytes), &path)
-                    .await?;
-                return Ok(self.storage.open_file(self.id, &path).await?);
-            } else {
-                warn!(?response, ?url_string, "Failed to proxy request");
+            }
+            return builder.body(Body::empty()).into();
+        }
+        if !every_route_missing {
+            return RepoResponse::basic_text_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                MavenError::UpstreamUnavailable.to_string(),
+            );
+        }
+        let ttl = self.cache.read().not_found_ttl();
+        self.not_found.insert(path, ttl);
+        RepoResponse::basic_text_response(StatusCode::NOT_FOUND, "File not found")
+    }
+    /// Revalidates the cached file against the upstream if it is older than the max age of its class.
+    ///
+    /// If the upstream can not be reached the cached file is kept and served.
+    #[instrument(skip(self), fields(nr.repository.id = %self.id, nr.repository.name = %self.name))]
+    pub async fn refresh_if_expired(&self, path: &StoragePath) -> Result<(), MavenError> {
+        let max_age = self.cache.read().max_age(MavenFileClass::from(path));
+        if max_age.is_none() {
+            return Ok(());
+        }
+        let Some(file) = self.storage.get_file_information(self.id, path).await? else {
+            return Ok(());
+        };
+        if let FileType::Directory(_) = file.file_type {
+            return Ok(());
+        }
+        let mut meta = self
+            .storage
+            .get_repository_meta(self.id, path)
+            .await?
+            .unwrap_or_default();
+        let mut cache_meta = ProxyCacheMeta::from(&meta);
+        if !cache_meta.is_expired(max_age, file.modified) {
+            return Ok(());
+        }
+        debug!(?path, "Cached file expired. Revalidating");
+        match self.request_upstream(path, Some(&cache_meta)).await {
+            UpstreamResponse::Found { file, route } => {
+                self.spawn_project_download(path, route, file.bytes.clone());
+                return self.save_upstream_file(path, file).await;
+            }
+            UpstreamResponse::NotModified => {
+                debug!(?path, "Cached file is still current");
+            }
+            UpstreamResponse::NotFound | UpstreamResponse::Unavailable => {
+                // Marked as checked so an unavailable upstream is not requested on every download
+                warn!(
+                    ?path,
+                    "Failed to revalidate file with upstream. Serving cached file"
+                );
             }
         }
-        Ok(None)
+        cache_meta.mark_checked();
+        cache_meta.apply_to(&mut meta);
+        self.storage
+            .put_repository_meta(self.id, path, meta)
+            .await?;
+        Ok(())
     }
 }
 
@@ -276,8 +775,18 @@ impl Repository for MavenProxy {
         {
             match maven_config_db.value.0 {
                 MavenRepositoryConfig::Proxy(proxy_config) => {
-                    let mut maven_config = self.config.write();
-                    *maven_config = proxy_config;
+                    let cache = proxy_config.cache.clone();
+                    let routes = MavenProxyRoute::from_config(proxy_config).map_err(|err| {
+                        RepositoryFactoryError::InvalidConfig(
+                            MavenRepositoryConfigType::get_type_static(),
+                            err.to_string(),
+                        )
+                    })?;
+                    *self.routes.write() = routes;
+                    *self.cache.write() = cache;
+                    // The routes may have changed so previous misses may now be found
+                    self.not_found.clear();
+                    self.route_selection.clear();
                 }
                 _ => {
                     return Err(RepositoryFactoryError::InvalidConfig(
@@ -302,7 +811,25 @@ impl Repository for MavenProxy {
             return Ok(err);
         }
         let visibility = self.visibility();
-        let Some(file) = self.0.storage.open_file(self.id, &path).await? else {
+        if let Err(err) = self.refresh_if_expired(&path).await {
+            warn!(?err, ?path, "Failed to check the age of the cached file");
+        }
+        let Some(file) = open_file_for_request(
+            &self.0.storage,
+            self.id,
+            &path,
+            &FileRequestHeaders::from(&parts.headers),
+        )
+        .await?
+        else {
+            let not_found_ttl = self.cache.read().not_found_ttl();
+            if self.not_found.is_missing(&path, not_found_ttl) {
+                debug!(?path, "File was recently not found upstream");
+                return Ok(RepoResponse::basic_text_response(
+                    StatusCode::NOT_FOUND,
+                    "File not found",
+                ));
+            }
             debug!(?path, "File not found in storage. Proxying request");
             return match self.get_from_proxy(path).await {
                 Ok(ok) => Ok(RepoResponse::from(ok)),
@@ -315,7 +842,6 @@ impl Repository for MavenProxy {
                 }
             };
         };
-        // TODO: Check file age. If it is older than the configured time then re-download the file.
         return self.indexing_check(file, &authentication).await;
     }
     async fn handle_head(
@@ -328,11 +854,21 @@ impl Repository for MavenProxy {
         }: RepositoryRequest,
     ) -> Result<RepoResponse, MavenError> {
         let visibility = self.visibility();
-        // TODO: Proxy HEAD request
         if let Some(err) = self.check_read(&authentication).await? {
             return Ok(err);
         }
         let file = self.storage.get_file_information(self.id, &path).await?;
+        if file.is_none() {
+            let not_found_ttl = self.cache.read().not_found_ttl();
+            if self.not_found.is_missing(&path, not_found_ttl) {
+                return Ok(RepoResponse::basic_text_response(
+                    StatusCode::NOT_FOUND,
+                    "File not found",
+                ));
+            }
+            debug!(?path, "File not found in storage. Proxying HEAD request");
+            return Ok(self.head_from_proxy(&path).await);
+        }
         return self.indexing_check_option(file, &authentication).await;
     }
     fn site(&self) -> NitroRepo {
@@ -340,3 +876,95 @@ impl Repository for MavenProxy {
     }
 }
 impl MavenRepositoryExt for MavenProxy {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use axum::{Router, extract::Path, routing::get};
+
+    use super::*;
+    const JAR: &str = "dev/kingtux/tms/1.0.0/tms-1.0.0.jar";
+    /// Starts an upstream with two routes.
+    ///
+    /// `/flaky` returns 500 until `up` is set. Then it has every jar. `/empty` has nothing
+    async fn mock_upstream(up: Arc<AtomicBool>) -> Vec<MavenProxyRoute> {
+        let router = Router::new()
+            .route(
+                "/flaky/{*path}",
+                get(move |Path(path): Path<String>| {
+                    let up = up.load(Ordering::SeqCst);
+                    async move {
+                        if !up {
+                            return (StatusCode::INTERNAL_SERVER_ERROR, String::new());
+                        }
+                        if path.ends_with(".jar") {
+                            (StatusCode::OK, "jar".to_owned())
+                        } else {
+                            (StatusCode::NOT_FOUND, String::new())
+                        }
+                    }
+                }),
+            )
+            .route("/empty/{*path}", get(|| async { StatusCode::NOT_FOUND }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        let route = |name: &str, priority: i32| MavenProxyRepositoryRoute {
+            url: ProxyURL::try_from(format!("http://{address}/{name}")).unwrap(),
+            name: Some(name.to_owned()),
+            priority: Some(priority),
+            credentials: None,
+            http: ProxyHttpSettings::default(),
+        };
+        MavenProxyRoute::from_config(MavenProxyConfig {
+            routes: vec![route("flaky", 0), route("empty", 1)],
+            cache: MavenProxyCacheConfig::default(),
+        })
+        .unwrap()
+    }
+    #[tokio::test]
+    async fn server_errors_are_not_cached_as_missing() {
+        let up = Arc::new(AtomicBool::new(false));
+        let routes = mock_upstream(up.clone()).await;
+        let route_selection = RouteSelectionCache::default();
+        let not_found = NotFoundCache::default();
+        let ttl = Duration::from_secs(600);
+        let path = StoragePath::from(JAR);
+        let lookup = || {
+            lookup_upstream(
+                routes.clone(),
+                &route_selection,
+                &not_found,
+                ttl,
+                &path,
+                None,
+            )
+        };
+
+        assert!(matches!(lookup().await, UpstreamResponse::Unavailable));
+        assert!(!not_found.is_missing(&path, ttl));
+        // The failed route is skipped while it is unhealthy. The file could still be on it
+        assert!(matches!(lookup().await, UpstreamResponse::Unavailable));
+        assert!(!not_found.is_missing(&path, ttl));
+
+        up.store(true, Ordering::SeqCst);
+        route_selection.record_success(&routes[0].key());
+        assert!(matches!(lookup().await, UpstreamResponse::Found { .. }));
+
+        let missing = StoragePath::from("dev/kingtux/tms/1.0.0/tms-1.0.0.pom");
+        let response = lookup_upstream(
+            routes.clone(),
+            &route_selection,
+            &not_found,
+            ttl,
+            &missing,
+            None,
+        )
+        .await;
+        assert!(matches!(response, UpstreamResponse::NotFound));
+        assert!(not_found.is_missing(&missing, ttl));
+    }
+}
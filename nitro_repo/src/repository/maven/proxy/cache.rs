@@ -0,0 +1,151 @@
+use std::time::{Duration, Instant};
+
+use ahash::HashMap;
+use nr_core::storage::StoragePath;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::trace;
+
+/// The number of missed paths kept before expired entries are removed
+const NOT_FOUND_CACHE_PRUNE_SIZE: usize = 10_000;
+
+/// How long files are served from the cache before they are revalidated against the upstream.
+///
+/// All values are in seconds. Null means the file is never revalidated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct MavenProxyCacheConfig {
+    /// `maven-metadata.xml` and its checksums
+    #[schemars(title = "Metadata Max Age")]
+    pub metadata_max_age: Option<u64>,
+    /// Files inside of a SNAPSHOT version
+    #[schemars(title = "Snapshot Max Age")]
+    pub snapshot_max_age: Option<u64>,
+    /// Release artifacts. These should never change upstream
+    #[schemars(title = "Release Max Age")]
+    pub release_max_age: Option<u64>,
+    /// How long a file that was not found on any upstream is remembered as missing
+    #[schemars(title = "Not Found TTL")]
+    pub not_found_ttl: u64,
+}
+impl Default for MavenProxyCacheConfig {
+    fn default() -> Self {
+        Self {
+            metadata_max_age: Some(30 * 60),
+            snapshot_max_age: Some(60 * 60),
+            release_max_age: None,
+            not_found_ttl: 10 * 60,
+        }
+    }
+}
+impl MavenProxyCacheConfig {
+    pub fn max_age(&self, class: MavenFileClass) -> Option<Duration> {
+        let seconds = match class {
+            MavenFileClass::Metadata => self.metadata_max_age,
+            MavenFileClass::Snapshot => self.snapshot_max_age,
+            MavenFileClass::Release => self.release_max_age,
+        };
+        seconds.map(Duration::from_secs)
+    }
+    pub fn not_found_ttl(&self) -> Duration {
+        Duration::from_secs(self.not_found_ttl)
+    }
+}
+/// The classes of files with their own max age
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MavenFileClass {
+    Metadata,
+    Snapshot,
+    Release,
+}
+impl From<&StoragePath> for MavenFileClass {
+    fn from(path: &StoragePath) -> Self {
+        let path = path.to_string();
+        let file_name = path.rsplit('/').next().unwrap_or(&path);
+        if file_name.starts_with("maven-metadata.xml") {
+            MavenFileClass::Metadata
+        } else if path.contains("-SNAPSHOT") {
+            MavenFileClass::Snapshot
+        } else {
+            MavenFileClass::Release
+        }
+    }
+}
+/// Paths that were recently not found on any upstream route.
+///
+/// Prevents every build from requesting missing files from all routes.
+#[derive(Debug, Default)]
+pub struct NotFoundCache {
+    entries: Mutex<HashMap<String, Instant>>,
+}
+impl NotFoundCache {
+    /// Returns true if the path was not found within the ttl
+    pub fn is_missing(&self, path: &StoragePath, ttl: Duration) -> bool {
+        let key = path.to_string();
+        let mut entries = self.entries.lock();
+        match entries.get(&key) {
+            Some(missed_at) if missed_at.elapsed() < ttl => true,
+            Some(_) => {
+                entries.remove(&key);
+                false
+            }
+            None => false,
+        }
+    }
+    pub fn insert(&self, path: &StoragePath, ttl: Duration) {
+        if ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        if entries.len() >= NOT_FOUND_CACHE_PRUNE_SIZE {
+            entries.retain(|_, missed_at| missed_at.elapsed() < ttl);
+            trace!(remaining = entries.len(), "Pruned not found cache");
+        }
+        entries.insert(path.to_string(), Instant::now());
+    }
+    pub fn remove(&self, path: &StoragePath) {
+        self.entries.lock().remove(&path.to_string());
+    }
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn file_classes() {
+        let class = |path: &str| MavenFileClass::from(&StoragePath::from(path));
+        assert_eq!(
+            class("dev/kingtux/tms/maven-metadata.xml"),
+            MavenFileClass::Metadata
+        );
+        assert_eq!(
+            class("dev/kingtux/tms/maven-metadata.xml.sha1"),
+            MavenFileClass::Metadata
+        );
+        assert_eq!(
+            class("dev/kingtux/tms/1.0.0-SNAPSHOT/tms-1.0.0-20240101.120000-1.jar"),
+            MavenFileClass::Snapshot
+        );
+        assert_eq!(
+            class("dev/kingtux/tms/1.0.0/tms-1.0.0.jar"),
+            MavenFileClass::Release
+        );
+    }
+    #[test]
+    fn not_found_cache() {
+        let cache = NotFoundCache::default();
+        let path = StoragePath::from("dev/kingtux/missing.jar");
+        let ttl = Duration::from_secs(60);
+        assert!(!cache.is_missing(&path, ttl));
+        cache.insert(&path, ttl);
+        assert!(cache.is_missing(&path, ttl));
+        assert!(!cache.is_missing(&path, Duration::ZERO));
+        cache.insert(&path, ttl);
+        cache.remove(&path);
+        assert!(!cache.is_missing(&path, ttl));
+    }
+}
@@ -0,0 +1,188 @@
+use std::time::{Duration, Instant};
+
+use ahash::HashMap;
+use nr_core::storage::StoragePath;
+use parking_lot::Mutex;
+use tracing::{debug, warn};
+
+/// The number of projects that the route is remembered for
+const ROUTE_SELECTION_CAPACITY: usize = 4096;
+/// How long a route is skipped after failing. Multiplied by the number of consecutive failures
+const UNHEALTHY_ROUTE_COOLDOWN: Duration = Duration::from_secs(30);
+/// The most consecutive failures counted towards the cooldown
+const MAX_COUNTED_FAILURES: u32 = 10;
+
+#[derive(Debug)]
+struct SelectedRoute {
+    route: String,
+    last_used: Instant,
+}
+#[derive(Debug, Default)]
+struct RouteHealth {
+    consecutive_failures: u32,
+    unhealthy_until: Option<Instant>,
+}
+impl RouteHealth {
+    fn is_healthy(&self) -> bool {
+        self.unhealthy_until
+            .is_none_or(|unhealthy_until| unhealthy_until <= Instant::now())
+    }
+}
+/// Remembers which route served each project and which routes are currently failing.
+///
+/// Routes are identified by their url so the cache survives the routes being rebuilt on reload.
+#[derive(Debug, Default)]
+pub struct RouteSelectionCache {
+    selected: Mutex<HashMap<String, SelectedRoute>>,
+    health: Mutex<HashMap<String, RouteHealth>>,
+}
+impl RouteSelectionCache {
+    /// The key used to group paths. `groupId/artifactId` for files inside of a project
+    ///
+    /// `dev/kingtux/tms/1.0.0/tms-1.0.0.jar` and `dev/kingtux/tms/maven-metadata.xml` both become `dev/kingtux/tms`
+    pub fn project_prefix(path: &StoragePath) -> Option<String> {
+        let path = path.to_string();
+        let mut components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        // Remove the file name
+        components.pop()?;
+        if components
+            .last()
+            .is_some_and(|component| component.starts_with(|c: char| c.is_ascii_digit()))
+        {
+            // Remove the version directory
+            components.pop();
+        }
+        if components.len() < 2 {
+            return None;
+        }
+        Some(components.join("/"))
+    }
+    /// Orders the routes so the route that last served the project is first.
+    ///
+    /// Unhealthy routes are skipped unless every route is unhealthy.
+    pub fn order_routes<T>(
+        &self,
+        prefix: Option<&str>,
+        routes: Vec<T>,
+        route_key: impl Fn(&T) -> String,
+    ) -> Vec<T> {
+        let preferred = prefix.and_then(|prefix| {
+            self.selected
+                .lock()
+                .get(prefix)
+                .map(|selected| selected.route.clone())
+        });
+        let (mut healthy, unhealthy): (Vec<T>, Vec<T>) = {
+            let health = self.health.lock();
+            routes.into_iter().partition(|route| {
+                health
+                    .get(&route_key(route))
+                    .is_none_or(RouteHealth::is_healthy)
+            })
+        };
+        if healthy.is_empty() {
+            debug!("All routes are unhealthy. Trying all of them");
+            healthy = unhealthy;
+        }
+        if let Some(index) = preferred.and_then(|preferred| {
+            healthy
+                .iter()
+                .position(|route| route_key(route) == preferred)
+        }) {
+            let route = healthy.remove(index);
+            healthy.insert(0, route);
+        }
+        healthy
+    }
+    /// Records that the route served a file for the project
+    pub fn record_selection(&self, prefix: &str, route: &str) {
+        let mut selected = self.selected.lock();
+        if selected.len() >= ROUTE_SELECTION_CAPACITY && !selected.contains_key(prefix) {
+            let oldest = selected
+                .iter()
+                .min_by_key(|(_, selected)| selected.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                selected.remove(&oldest);
+            }
+        }
+        selected.insert(
+            prefix.to_owned(),
+            SelectedRoute {
+                route: route.to_owned(),
+                last_used: Instant::now(),
+            },
+        );
+    }
+    /// The route responded. Even if it did not have the file
+    pub fn record_success(&self, route: &str) {
+        self.health.lock().remove(route);
+    }
+    /// The route returned a server error or could not be reached
+    pub fn record_failure(&self, route: &str) {
+        let mut health = self.health.lock();
+        let route_health = health.entry(route.to_owned()).or_default();
+        route_health.consecutive_failures =
+            (route_health.consecutive_failures + 1).min(MAX_COUNTED_FAILURES);
+        let cooldown = UNHEALTHY_ROUTE_COOLDOWN * route_health.consecutive_failures;
+        route_health.unhealthy_until = Some(Instant::now() + cooldown);
+        warn!(
+            ?route,
+            failures = route_health.consecutive_failures,
+            ?cooldown,
+            "Route marked as unhealthy"
+        );
+    }
+    /// Forgets everything. Used when the routes change
+    pub fn clear(&self) {
+        self.selected.lock().clear();
+        self.health.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn project_prefixes() {
+        let prefix = |path: &str| RouteSelectionCache::project_prefix(&StoragePath::from(path));
+        assert_eq!(
+            prefix("dev/kingtux/tms/1.0.0/tms-1.0.0.jar").as_deref(),
+            Some("dev/kingtux/tms")
+        );
+        assert_eq!(
+            prefix("dev/kingtux/tms/maven-metadata.xml").as_deref(),
+            Some("dev/kingtux/tms")
+        );
+        assert_eq!(
+            prefix("dev/kingtux/tms/1.0.0-SNAPSHOT/maven-metadata.xml").as_deref(),
+            Some("dev/kingtux/tms")
+        );
+        assert_eq!(prefix("dev/file.txt"), None);
+    }
+    #[test]
+    fn preferred_route_first() {
+        let cache = RouteSelectionCache::default();
+        let routes = vec!["central", "google", "jitpack"];
+        cache.record_selection("dev/kingtux/tms", "jitpack");
+        let ordered =
+            cache.order_routes(Some("dev/kingtux/tms"), routes.clone(), |r| r.to_string());
+        assert_eq!(ordered, vec!["jitpack", "central", "google"]);
+        let ordered = cache.order_routes(Some("dev/other/lib"), routes, |r| r.to_string());
+        assert_eq!(ordered, vec!["central", "google", "jitpack"]);
+    }
+    #[test]
+    fn unhealthy_routes_skipped() {
+        let cache = RouteSelectionCache::default();
+        let routes = vec!["central", "google"];
+        cache.record_failure("central");
+        let ordered = cache.order_routes(None, routes.clone(), |r| r.to_string());
+        assert_eq!(ordered, vec!["google"]);
+        cache.record_failure("google");
+        let ordered = cache.order_routes(None, routes.clone(), |r| r.to_string());
+        assert_eq!(ordered, vec!["central", "google"]);
+        cache.record_success("central");
+        let ordered = cache.order_routes(None, routes, |r| r.to_string());
+        assert_eq!(ordered, vec!["central"]);
+    }
+}
@@ -4,18 +4,26 @@ use maven_rs::pom::Pom;
 use nr_core::{
     database::entities::project::{
         DBProject, NewProject, NewProjectMember, ProjectDBType,
-        versions::{DBProjectVersion, NewVersion, UpdateProjectVersion},
+        versions::{
+            DBProjectVersion, NewVersion, UpdateProjectVersion,
+            dependencies::{DBProjectVersionDependency, NewProjectVersionDependency},
+        },
     },
-    repository::project::{ReleaseType, VersionData},
+    repository::project::{ReleaseType, VersionData, VersionVariant},
     storage::{FileTypeCheck, StoragePath},
     user::permissions::{HasPermissions, RepositoryActions},
 };
 
 use nr_storage::Storage;
-use tracing::{Level, error, event, info, instrument, trace};
+use tracing::{Level, debug, error, event, info, instrument, trace, warn};
 use uuid::Uuid;
 
-use super::{MavenError, RepoResponse, RepositoryAuthentication, RepositoryHandlerError};
+use super::{
+    MavenError, RepoResponse, RepositoryAuthentication, RepositoryHandlerError,
+    gradle_module::{GRADLE_MODULE_EXTENSION, GradleModule},
+    hosted_repository_ids,
+    pom::{MAX_PARENT_DEPTH, PomMetadata},
+};
 use crate::{repository::Repository, utils::bad_request::BadRequestErrors};
 
 /// Utilities for Maven Repositories
@@ -85,12 +93,150 @@ pub trait MavenRepositoryExt: Repository + Debug {
         let pom: maven_rs::pom::Pom = maven_rs::quick_xml::de::from_str(&pom_file)?;
         Ok(pom)
     }
-    #[instrument]
+    /// Reads a POM from the storage of this repository. For proxies this is the cache
+    async fn read_stored_pom(&self, path: &StoragePath) -> Result<Option<PomMetadata>, MavenError> {
+        let Some((reader, meta)) = self
+            .get_storage()
+            .open_file(self.id(), path)
+            .await?
+            .and_then(|file| file.file())
+        else {
+            return Ok(None);
+        };
+        let content = reader
+            .read_to_vec(meta.file_type.file_size as usize)
+            .await?;
+        PomMetadata::parse(&content).map(Some)
+    }
+    /// Reads the variants of a Gradle Module Metadata file from the storage of this repository
+    async fn read_module_variants(
+        &self,
+        path: &StoragePath,
+    ) -> Result<Option<Vec<VersionVariant>>, MavenError> {
+        let Some((reader, meta)) = self
+            .get_storage()
+            .open_file(self.id(), path)
+            .await?
+            .and_then(|file| file.file())
+        else {
+            return Ok(None);
+        };
+        let content = reader
+            .read_to_vec(meta.file_type.file_size as usize)
+            .await?;
+        let module = GradleModule::parse(&content)?;
+        Ok(Some(module.version_variants()))
+    }
+    /// Stores the variants of a Gradle Module Metadata file on its version.
+    ///
+    /// If the version does not exist yet the variants are read when the POM is uploaded
+    #[instrument(skip(module_file))]
+    async fn post_module_upload(
+        &self,
+        module_path: &StoragePath,
+        module_file: &[u8],
+    ) -> Result<(), MavenError> {
+        let module = GradleModule::parse(module_file)?;
+        let version_directory = module_path.clone().parent();
+        let site = self.site();
+        let database = &site.database;
+        let Some(version) = DBProjectVersion::find_by_version_directory(
+            &version_directory.to_string(),
+            self.id(),
+            database,
+        )
+        .await?
+        else {
+            debug!(?version_directory, "No version for the Gradle Module yet");
+            return Ok(());
+        };
+        let mut extra = version.extra.0;
+        extra.variants = module.version_variants();
+        debug!(
+            variants = extra.variants.len(),
+            "Saving Gradle Module Variants"
+        );
+        let update = UpdateProjectVersion {
+            extra: Some(extra),
+            ..Default::default()
+        };
+        update.update(version.id, database).await?;
+        Ok(())
+    }
+    /// Parses a POM and applies its parents.
+    ///
+    /// Parent POMs that exist in this repository are used for inherited values and properties.
+    /// Missing or broken parents are skipped
+    #[instrument(skip(pom_file))]
+    async fn resolve_pom(&self, pom_file: &[u8]) -> Result<PomMetadata, MavenError> {
+        let pom = PomMetadata::parse(pom_file)?;
+        let mut parents = Vec::new();
+        let mut next_parent = pom.parent.clone();
+        while let Some(parent) = next_parent.take() {
+            if parents.len() >= MAX_PARENT_DEPTH {
+                warn!(?parent, "Parent POM depth exceeded");
+                break;
+            }
+            let Some(path) = parent.pom_path() else {
+                break;
+            };
+            match self.read_stored_pom(&path).await {
+                Ok(Some(parent_pom)) => {
+                    next_parent = parent_pom.parent.clone();
+                    parents.push(parent_pom);
+                }
+                Ok(None) => {
+                    debug!(?path, "Parent POM is not in the repository");
+                }
+                Err(error) => {
+                    warn!(?error, ?path, "Failed to read parent POM");
+                }
+            }
+        }
+        Ok(pom.resolve(parents))
+    }
+    /// Stores the dependencies of the version.
+    ///
+    /// Dependencies hosted by any Maven hosted repository are linked to their project. A project in this repository is preferred
+    #[instrument(skip(pom))]
+    async fn save_dependencies(
+        &self,
+        version_id: Uuid,
+        pom: &PomMetadata,
+    ) -> Result<(), MavenError> {
+        let site = self.site();
+        let database = &site.database;
+        let hosted_repositories = hosted_repository_ids(&site);
+        let dependencies: Vec<_> = pom
+            .dependencies()
+            .into_iter()
+            .map(|dependency| NewProjectVersionDependency {
+                group_id: dependency.group_id,
+                artifact_id: dependency.artifact_id,
+                version_range: dependency.version,
+                scope: dependency.scope,
+                optional: dependency.optional,
+            })
+            .collect();
+        debug!(count = dependencies.len(), "Saving Dependencies");
+        DBProjectVersionDependency::replace_for_version(
+            version_id,
+            &dependencies,
+            self.id(),
+            &hosted_repositories,
+            database,
+        )
+        .await?;
+        Ok(())
+    }
+    #[instrument(skip(pom_file))]
     async fn post_pom_upload_inner(
         &self,
         pom_directory: StoragePath,
         publisher: Option<i32>,
         pom: Pom,
+        pom_file: &[u8],
+        proxied_from: Option<String>,
     ) -> Result<(), MavenError> {
         let group_id = pom
             .get_group_id()
@@ -113,6 +259,15 @@ pub trait MavenRepositoryExt: Repository + Debug {
                 info!(?db_project, "No publisher provided for project");
             }
             info!(?project, "Created Project");
+            if proxied_from.is_none() {
+                DBProjectVersionDependency::link_to_project(
+                    project.id,
+                    group_id,
+                    &pom.artifact_id,
+                    &self.site().database,
+                )
+                .await?;
+            }
             (project.id, project_directory)
         };
         let mut repository_meta = self
@@ -132,14 +287,59 @@ pub trait MavenRepositoryExt: Repository + Debug {
             .put_repository_meta(self.id(), &project_dir, repository_meta)
             .await?;
 
-        self.add_or_update_version(version_directory, project_id, publisher, pom)
+        let resolved = match self.resolve_pom(pom_file).await {
+            Ok(resolved) => Some(resolved),
+            Err(error) => {
+                warn!(
+                    ?error,
+                    "Failed to read the project information from the POM"
+                );
+                None
+            }
+        };
+        let mut extra = match &resolved {
+            Some(resolved) => resolved.version_data(),
+            None => VersionData {
+                description: pom.description.clone(),
+                ..Default::default()
+            },
+        };
+        extra.proxied_from = proxied_from;
+        let module_path = pom_directory
+            .to_string()
+            .strip_suffix(".pom")
+            .map(|path| StoragePath::from(format!("{path}{GRADLE_MODULE_EXTENSION}")));
+        if let Some(module_path) = module_path {
+            match self.read_module_variants(&module_path).await {
+                Ok(Some(variants)) => extra.variants = variants,
+                Ok(None) => {}
+                Err(error) => {
+                    warn!(?error, ?module_path, "Failed to read the Gradle Module");
+                }
+            }
+        }
+        let version_id = self
+            .add_or_update_version(version_directory, project_id, publisher, pom, extra)
             .await?;
+        if let Some(resolved) = resolved {
+            self.save_dependencies(version_id, &resolved).await?;
+        }
         Ok(())
     }
 
-    async fn post_pom_upload(&self, pom_directory: StoragePath, publisher: Option<i32>, pom: Pom) {
+    /// Creates or updates the project and version for the POM.
+    ///
+    /// `pom_file` is the raw POM. `proxied_from` is the name of the upstream route if the POM was downloaded by a proxy
+    async fn post_pom_upload(
+        &self,
+        pom_directory: StoragePath,
+        publisher: Option<i32>,
+        pom: Pom,
+        pom_file: &[u8],
+        proxied_from: Option<String>,
+    ) {
         match self
-            .post_pom_upload_inner(pom_directory, publisher, pom)
+            .post_pom_upload_inner(pom_directory, publisher, pom, pom_file, proxied_from)
             .await
         {
             Ok(()) => {}
@@ -154,7 +354,8 @@ pub trait MavenRepositoryExt: Repository + Debug {
         project_id: Uuid,
         publisher: Option<i32>,
         pom: Pom,
-    ) -> Result<(), MavenError> {
+        extra: VersionData,
+    ) -> Result<Uuid, MavenError> {
         let version = pom
             .get_version()
             .ok_or(MavenError::MissingFromPom("version"))?;
@@ -165,7 +366,7 @@ pub trait MavenRepositoryExt: Repository + Debug {
         )
         .await?;
         let version_id = if let Some(version) = db_version {
-            let update = pom_to_update_db_project_version(pom)?;
+            let update = pom_to_update_db_project_version(pom, extra)?;
             update.update(version.id, &self.site().database).await?;
             version
         } else {
@@ -173,7 +374,8 @@ pub trait MavenRepositoryExt: Repository + Debug {
                 project_id,
                 version_directory.clone(),
                 publisher,
-                pom.clone(),
+                pom,
+                extra,
             )?;
             let db_version = version.insert(&self.site().database).await?;
             info!(?db_version, "Created Version");
@@ -197,7 +399,7 @@ pub trait MavenRepositoryExt: Repository + Debug {
         self.get_storage()
             .put_repository_meta(self.id(), &version_directory, repository_meta)
             .await?;
-        Ok(())
+        Ok(version_id.id)
     }
 }
 pub fn pom_to_db_project(
@@ -223,15 +425,12 @@ pub fn pom_to_db_project_version(
     version_path: StoragePath,
     publisher: Option<i32>,
     pom: Pom,
+    extra: VersionData,
 ) -> Result<NewVersion, MavenError> {
     let version = pom
         .get_version()
         .ok_or(MavenError::MissingFromPom("version"))
         .map(|x| x.to_owned())?;
-    let version_data = VersionData {
-        description: pom.description,
-        ..Default::default()
-    };
 
     let release_type = ReleaseType::release_type_from_version(&version);
     let version = NewVersion {
@@ -241,19 +440,20 @@ pub fn pom_to_db_project_version(
         version_path: version_path.to_string(),
         version_page: None,
         release_type,
-        extra: version_data,
+        extra,
     };
 
     Ok(version)
 }
 
-pub fn pom_to_update_db_project_version(pom: Pom) -> Result<UpdateProjectVersion, MavenError> {
+pub fn pom_to_update_db_project_version(
+    pom: Pom,
+    extra: VersionData,
+) -> Result<UpdateProjectVersion, MavenError> {
     let release_type = pom
         .get_version()
         .ok_or(MavenError::MissingFromPom("version"))
         .map(ReleaseType::release_type_from_version)?;
-
-    let extra = pom_to_version_extra_data(pom)?;
     let result = UpdateProjectVersion {
         release_type: Some(release_type),
         extra: Some(extra),
@@ -261,10 +461,3 @@ pub fn pom_to_update_db_project_version(pom: Pom) -> Result<UpdateProjectVersion
     };
     Ok(result)
 }
-pub fn pom_to_version_extra_data(pom: Pom) -> Result<VersionData, MavenError> {
-    let extra = VersionData {
-        description: pom.description,
-        ..Default::default()
-    };
-    Ok(extra)
-}
@@ -0,0 +1,246 @@
+//! The order Maven sorts versions in.
+//!
+//! Follows `org.apache.maven.artifact.versioning.ComparableVersion`.
+//! [Documentation](https://maven.apache.org/pom.html#version-order-specification)
+use std::cmp::Ordering;
+
+/// Known qualifiers from oldest to newest. Unknown qualifiers come after all of them
+const QUALIFIERS: [&str; 7] = ["alpha", "beta", "milestone", "rc", "snapshot", "", "sp"];
+/// The position of a release in [QUALIFIERS]
+const RELEASE_QUALIFIER_INDEX: usize = 5;
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    /// The digits without leading zeros
+    Int(String),
+    Qualifier(String),
+    List(Vec<Item>),
+}
+impl Item {
+    fn int(digits: &str) -> Self {
+        Item::Int(digits.trim_start_matches('0').to_owned())
+    }
+    fn qualifier(value: &str, followed_by_digit: bool) -> Self {
+        let value = match value {
+            "a" if followed_by_digit => "alpha",
+            "b" if followed_by_digit => "beta",
+            "m" if followed_by_digit => "milestone",
+            "ga" | "final" | "release" => "",
+            "cr" => "rc",
+            value => value,
+        };
+        Item::Qualifier(value.to_owned())
+    }
+    fn parse(value: &str, is_digit: bool) -> Self {
+        if is_digit {
+            Item::int(value)
+        } else {
+            Item::qualifier(value, false)
+        }
+    }
+    /// `0`, a release qualifier and empty lists are ignored at the end of a version
+    fn is_null(&self) -> bool {
+        match self {
+            Item::Int(digits) => digits.is_empty(),
+            Item::Qualifier(value) => value.is_empty(),
+            Item::List(items) => items.is_empty(),
+        }
+    }
+    fn comparable_qualifier(value: &str) -> String {
+        match QUALIFIERS.iter().position(|qualifier| *qualifier == value) {
+            Some(index) => index.to_string(),
+            None => format!("{}-{}", QUALIFIERS.len(), value),
+        }
+    }
+    /// Compares the item to a missing item
+    fn compare_to_none(&self) -> Ordering {
+        match self {
+            Item::Int(digits) if digits.is_empty() => Ordering::Equal,
+            Item::Int(_) => Ordering::Greater,
+            Item::Qualifier(value) => {
+                Self::comparable_qualifier(value).cmp(&RELEASE_QUALIFIER_INDEX.to_string())
+            }
+            Item::List(items) => items.first().map_or(Ordering::Equal, Item::compare_to_none),
+        }
+    }
+    fn compare(&self, other: Option<&Item>) -> Ordering {
+        let Some(other) = other else {
+            return self.compare_to_none();
+        };
+        match (self, other) {
+            (Item::Int(left), Item::Int(right)) => {
+                left.len().cmp(&right.len()).then_with(|| left.cmp(right))
+            }
+            (Item::Int(_), _) => Ordering::Greater,
+            (Item::Qualifier(_), Item::Int(_)) => Ordering::Less,
+            (Item::Qualifier(left), Item::Qualifier(right)) => {
+                Self::comparable_qualifier(left).cmp(&Self::comparable_qualifier(right))
+            }
+            (Item::Qualifier(_), Item::List(_)) => Ordering::Less,
+            (Item::List(_), Item::Int(_)) => Ordering::Less,
+            (Item::List(_), Item::Qualifier(_)) => Ordering::Greater,
+            (Item::List(left), Item::List(right)) => {
+                let length = left.len().max(right.len());
+                for index in 0..length {
+                    let result = match (left.get(index), right.get(index)) {
+                        (Some(left), right) => left.compare(right),
+                        (None, Some(right)) => right.compare(None).reverse(),
+                        (None, None) => Ordering::Equal,
+                    };
+                    if result != Ordering::Equal {
+                        return result;
+                    }
+                }
+                Ordering::Equal
+            }
+        }
+    }
+}
+/// Removes the null items at the end of the list. Stops at the first item that is not null or a list
+fn normalize(items: &mut Vec<Item>) {
+    let mut index = items.len();
+    while index > 0 {
+        index -= 1;
+        if items[index].is_null() {
+            items.remove(index);
+        } else if !matches!(items[index], Item::List(_)) {
+            break;
+        }
+    }
+}
+/// A version parsed the way Maven compares them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MavenVersion(Item);
+impl MavenVersion {
+    pub fn parse(version: &str) -> Self {
+        let version = version.to_lowercase();
+        // Every `-` and change between digits and letters starts a list inside the current one
+        let mut lists: Vec<Vec<Item>> = vec![Vec::new()];
+        let mut is_digit = false;
+        let mut start = 0;
+        for (index, c) in version.char_indices() {
+            let list = lists.last_mut().expect("There is always a list");
+            match c {
+                '.' | '-' => {
+                    if index == start {
+                        list.push(Item::Int(String::new()));
+                    } else {
+                        list.push(Item::parse(&version[start..index], is_digit));
+                    }
+                    start = index + 1;
+                    if c == '-' {
+                        lists.push(Vec::new());
+                    }
+                }
+                c if c.is_ascii_digit() => {
+                    if !is_digit && index > start {
+                        list.push(Item::qualifier(&version[start..index], true));
+                        start = index;
+                        lists.push(Vec::new());
+                    }
+                    is_digit = true;
+                }
+                _ => {
+                    if is_digit && index > start {
+                        list.push(Item::int(&version[start..index]));
+                        start = index;
+                        lists.push(Vec::new());
+                    }
+                    is_digit = false;
+                }
+            }
+        }
+        if version.len() > start {
+            lists
+                .last_mut()
+                .expect("There is always a list")
+                .push(Item::parse(&version[start..], is_digit));
+        }
+        let mut items = Vec::new();
+        while let Some(mut list) = lists.pop() {
+            if let Some(child) = items.pop() {
+                list.push(child);
+            }
+            normalize(&mut list);
+            items.push(Item::List(list));
+        }
+        Self(items.pop().unwrap_or(Item::List(Vec::new())))
+    }
+}
+impl PartialOrd for MavenVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for MavenVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.compare(Some(&other.0))
+    }
+}
+/// Compares two versions the way Maven does
+pub fn compare_versions(left: &str, right: &str) -> Ordering {
+    MavenVersion::parse(left).cmp(&MavenVersion::parse(right))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn assert_order(versions: &[&str]) {
+        for pair in versions.windows(2) {
+            assert_eq!(
+                compare_versions(pair[0], pair[1]),
+                Ordering::Less,
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+            assert_eq!(
+                compare_versions(pair[1], pair[0]),
+                Ordering::Greater,
+                "{} > {}",
+                pair[1],
+                pair[0]
+            );
+        }
+    }
+    #[test]
+    fn version_order() {
+        assert_order(&[
+            "1",
+            "1.0.1",
+            "1.1",
+            "1.2",
+            "1.9",
+            "1.10",
+            "2.0-alpha-1",
+            "2.0-alpha-2",
+            "2.0-beta",
+            "2.0-milestone-1",
+            "2.0-rc1",
+            "2.0-SNAPSHOT",
+            "2.0",
+            "2.0-sp1",
+            "2.0-foo",
+            "2.0.1",
+        ]);
+    }
+    #[test]
+    fn equal_versions() {
+        for (left, right) in [
+            ("1", "1.0"),
+            ("1.0", "1.0.0"),
+            ("1.0-ga", "1.0"),
+            ("1.0-final", "1"),
+            ("1.0-cr1", "1.0-rc1"),
+            ("1.0a1", "1.0-alpha-1"),
+            ("1.0-ALPHA", "1.0-alpha"),
+            ("1.01", "1.1"),
+        ] {
+            assert_eq!(
+                compare_versions(left, right),
+                Ordering::Equal,
+                "{} == {}",
+                left,
+                right
+            );
+        }
+    }
+}
@@ -24,6 +24,7 @@ pub use repo_http::*;
 pub mod commands;
 pub mod maven;
 pub mod npm;
+pub mod proxy;
 mod repo_type;
 pub use repo_type::*;
 use uuid::Uuid;
@@ -0,0 +1,55 @@
+//! `npm audit` against the advisories imported with `nitro_repo import-advisories`.
+//!
+//! Hosted and proxy repositories answer from the same local advisory database
+use ahash::{HashMap, HashMapExt};
+use nr_core::database::entities::advisory::DBSecurityAdvisory;
+use semver::Version;
+use sqlx::PgPool;
+use tracing::{debug, instrument};
+
+use super::{
+    NPMRegistryError,
+    types::audit::{
+        BulkAdvisory, BulkAdvisoryRequest, BulkAdvisoryResponse, NPM_ECOSYSTEM, is_affected,
+    },
+};
+use crate::{
+    repository::{RepoResponse, RepositoryRequest},
+    utils::ResponseBuilder,
+};
+/// Responds with the advisories that affect the installed versions.
+///
+/// Packages without advisories are left out of the response
+#[instrument(name = "npm_bulk_advisories", skip(database))]
+pub async fn bulk_advisories(
+    database: &PgPool,
+    request: RepositoryRequest,
+) -> Result<RepoResponse, NPMRegistryError> {
+    let body = request.body.body_as_bytes().await?;
+    let installed: BulkAdvisoryRequest = serde_json::from_slice(&body)?;
+    let package_names: Vec<String> = installed.keys().cloned().collect();
+    let advisories =
+        DBSecurityAdvisory::find_by_packages(NPM_ECOSYSTEM, &package_names, database).await?;
+    debug!(
+        packages = package_names.len(),
+        advisories = advisories.len(),
+        "Checking installed versions"
+    );
+    let mut response: BulkAdvisoryResponse = HashMap::new();
+    for advisory in &advisories {
+        let Some(versions) = installed.get(&advisory.package_name) else {
+            continue;
+        };
+        let affects_installed = versions
+            .iter()
+            .filter_map(|version| Version::parse(version).ok())
+            .any(|version| is_affected(&advisory.affected.0, &version));
+        if affects_installed {
+            response
+                .entry(advisory.package_name.clone())
+                .or_default()
+                .push(BulkAdvisory::from(advisory));
+        }
+    }
+    Ok(ResponseBuilder::ok().json(&response).into())
+}
@@ -1,12 +1,48 @@
+use chrono::Duration;
 use nr_core::repository::config::{ConfigDescription, RepositoryConfigError, RepositoryConfigType};
 use schemars::{JsonSchema, schema_for};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use super::proxy::{NPMProxyConfig, NPMProxyRepositoryRoute};
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", content = "config")]
 pub enum NPMRegistryConfig {
-    Hosted,
+    /// The config is None for repositories created before it existed
+    Hosted(Option<NPMHostedConfig>),
+    Proxy(NPMProxyConfig),
+}
+impl NPMRegistryConfig {
+    pub fn is_same_type(&self, other: &NPMRegistryConfig) -> bool {
+        matches!(
+            (self, other),
+            (NPMRegistryConfig::Hosted(_), NPMRegistryConfig::Hosted(_))
+                | (NPMRegistryConfig::Proxy(_), NPMRegistryConfig::Proxy(_))
+        )
+    }
+}
+/// Settings for hosted NPM Registries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct NPMHostedConfig {
+    /// How many seconds after publishing a version can be unpublished. Null allows unpublishing at any time
+    #[schemars(title = "Unpublish Window")]
+    pub unpublish_window: Option<u64>,
+}
+impl Default for NPMHostedConfig {
+    fn default() -> Self {
+        // The same as the public NPM Registry
+        Self {
+            unpublish_window: Some(72 * 60 * 60),
+        }
+    }
+}
+impl NPMHostedConfig {
+    pub fn unpublish_window(&self) -> Option<Duration> {
+        self.unpublish_window
+            .map(|seconds| Duration::seconds(seconds as i64))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -30,10 +66,32 @@ impl RepositoryConfigType for NPMRegistryConfigType {
         Ok(())
     }
     fn validate_change(&self, old: Value, new: Value) -> Result<(), RepositoryConfigError> {
+        let new: NPMRegistryConfig = serde_json::from_value(new)?;
+        let old: NPMRegistryConfig = serde_json::from_value(old)?;
+        if !old.is_same_type(&new) {
+            return Err(RepositoryConfigError::InvalidChange(
+                "npm",
+                "Cannot change the type of NPM Registry",
+            ));
+        }
         Ok(())
     }
+    /// Proxy routes can contain credentials. These are redacted
+    fn sanitize_for_public_view(
+        &self,
+        config: Value,
+    ) -> Result<Option<Value>, RepositoryConfigError> {
+        let mut config: NPMRegistryConfig = serde_json::from_value(config)?;
+        if let NPMRegistryConfig::Proxy(proxy) = &mut config {
+            proxy
+                .routes
+                .iter_mut()
+                .for_each(NPMProxyRepositoryRoute::sanitize_for_public_view);
+        }
+        Ok(Some(serde_json::to_value(config)?))
+    }
     fn default(&self) -> Result<Value, RepositoryConfigError> {
-        let config = NPMRegistryConfig::Hosted;
+        let config = NPMRegistryConfig::Hosted(Some(NPMHostedConfig::default()));
         Ok(serde_json::to_value(config).unwrap())
     }
     fn get_description(&self) -> ConfigDescription {
@@ -41,7 +99,7 @@ impl RepositoryConfigType for NPMRegistryConfigType {
             name: "NPM Registry Config",
             description: Some("Handles the type of NPM Registry"),
             documentation_link: None,
-            ..Default::default()
+            has_public_view: true,
         }
     }
 }
@@ -1,13 +1,21 @@
+use super::login::web_login::{self, WEB_LOGIN_DONE_PATH, WEB_LOGIN_PATH, WebLogins};
 use super::types::{
     NPM_COMMAND_HEADER, NpmRegistryPackageResponse,
-    request::{GetPath, InvalidNPMCommand, NPMCommand, PublishVersion},
+    audit::BULK_ADVISORY_PATH,
+    dist_tags::{DistTagPath, LATEST_TAG, publish_moves_latest},
+    request::{GetPath, InvalidNPMCommand, NPMCommand, PublishVersion, RevisionPath},
+    tokens::{REVOKE_TOKEN_PATH, TOKENS_PATH, WHOAMI_PATH},
 };
-use super::utils::{NpmRegistryExt, npm_time};
+use super::utils::{NpmRegistryExt, npm_revision, npm_time};
 use crate::{
     app::NitroRepo,
     repository::{
-        RepoResponse, Repository, RepositoryFactoryError, RepositoryRequest,
-        npm::{NPMRegistryConfigType, NPMRegistryError, types::PublishRequest},
+        FileRequestHeaders, RepoResponse, Repository, RepositoryFactoryError, RepositoryRequest,
+        npm::{
+            NPMHostedConfig, NPMRegistryConfig, NPMRegistryConfigType, NPMRegistryError,
+            types::PublishRequest,
+        },
+        open_file_for_request,
         utils::RepositoryExt,
     },
     utils::ResponseBuilder,
@@ -17,14 +25,21 @@ use axum::response::{IntoResponse, Response};
 use derive_more::derive::Deref;
 use http::{StatusCode, header::CONTENT_TYPE};
 use nr_core::{
-    database::entities::{project::versions::DBProjectVersion, repository::DBRepository},
+    database::entities::{
+        project::{dist_tags::DBProjectDistTag, versions::DBProjectVersion},
+        repository::{DBRepository, DBRepositoryConfig},
+    },
     repository::config::RepositoryConfigType,
     storage::StoragePath,
     user::permissions::RepositoryActions,
 };
 use nr_storage::{DynStorage, FileContent, Storage};
+use parking_lot::RwLock;
 use std::sync::Arc;
 use tracing::{debug, info, instrument, warn};
+mod search;
+mod tokens;
+mod unpublish;
 
 #[derive(derive_more::Debug)]
 pub struct NpmRegistryInner {
@@ -33,6 +48,9 @@ pub struct NpmRegistryInner {
     pub storage: DynStorage,
     pub id: uuid::Uuid,
     pub repository: DBRepository,
+    pub config: RwLock<NPMHostedConfig>,
+    /// Logins started with `npm login --auth-type=web`
+    pub web_logins: WebLogins,
 }
 #[derive(Debug, Clone, Deref)]
 pub struct NPMHostedRegistry(Arc<NpmRegistryInner>);
@@ -41,12 +59,15 @@ impl NPMHostedRegistry {
         site: NitroRepo,
         storage: DynStorage,
         repository: DBRepository,
+        config: NPMHostedConfig,
     ) -> Result<Self, RepositoryFactoryError> {
         Ok(Self(Arc::new(NpmRegistryInner {
             site,
             storage,
             id: repository.id,
             repository,
+            config: RwLock::new(config),
+            web_logins: WebLogins::default(),
         })))
     }
     #[instrument]
@@ -68,6 +89,7 @@ impl NPMHostedRegistry {
             name,
             attachments,
             versions,
+            dist_tags,
             other,
         }: PublishRequest = serde_json::from_str(&body)?;
         if versions.len() != 1 {
@@ -86,8 +108,40 @@ impl NPMHostedRegistry {
         let mut version_path = project_path.clone();
         version_path.push_mut(&version);
 
-        self.create_or_update_version(user.id, &version_path, &project, &data)
+        let version_id = self
+            .create_or_update_version(user.id, &version_path, &project, &data)
             .await?;
+        for (tag, tagged_version) in dist_tags {
+            if tagged_version != version {
+                warn!(
+                    ?tag,
+                    ?tagged_version,
+                    "Ignoring dist-tag for another version"
+                );
+                continue;
+            }
+            if tag == LATEST_TAG {
+                // Sent by every publish without `--tag`. Lower versions and prereleases do not become latest
+                let versions =
+                    DBProjectVersion::get_all_versions(project.id, self.site.as_ref()).await?;
+                let moves_latest = publish_moves_latest(
+                    &version,
+                    versions
+                        .iter()
+                        .filter(|version| version.yanked_at.is_none())
+                        .map(|version| version.version.as_str()),
+                );
+                if moves_latest {
+                    // The highest release is computed. Removes a tag pinned to an older version
+                    DBProjectDistTag::delete(project.id, &tag, &self.site.database).await?;
+                } else {
+                    debug!(?version, "Published version is not the highest release");
+                }
+                continue;
+            }
+            info!(?tag, ?version, "Setting dist-tag");
+            DBProjectDistTag::set(project.id, &tag, version_id, &self.site.database).await?;
+        }
 
         for (file, attachment) in attachments.into_iter() {
             info!(?file, ?attachment, "Saving Attachment");
@@ -107,6 +161,104 @@ impl NPMHostedRegistry {
 
         Ok(ResponseBuilder::no_content().empty().into())
     }
+    fn project_not_found(name: &str) -> RepoResponse {
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(format!("Project {} not found in repository", name).into())
+            .into()
+    }
+    async fn dist_tags_response(
+        &self,
+        project_id: uuid::Uuid,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        let versions = DBProjectVersion::get_all_versions(project_id, self.site.as_ref()).await?;
+        let dist_tags = self.dist_tags(project_id, &versions).await?;
+        Ok(ResponseBuilder::ok().json(&dist_tags).into())
+    }
+    /// `npm dist-tag ls`
+    #[instrument]
+    async fn get_dist_tags(&self, path: DistTagPath) -> Result<RepoResponse, NPMRegistryError> {
+        let Some(project) = self.get_project_from_key(&path.name).await? else {
+            return Ok(Self::project_not_found(&path.name));
+        };
+        self.dist_tags_response(project.id).await
+    }
+    /// `npm dist-tag add`. Sets a single tag or every tag in the body with POST
+    #[instrument]
+    async fn set_dist_tags(
+        &self,
+        path: DistTagPath,
+        request: RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        if request
+            .authentication
+            .get_user_if_has_action(RepositoryActions::Write, self.id, self.site.as_ref())
+            .await?
+            .is_none()
+        {
+            info!("No acceptable user authentication provided");
+            return Ok(RepoResponse::unauthorized());
+        }
+        let Some(project) = self.get_project_from_key(&path.name).await? else {
+            return Ok(Self::project_not_found(&path.name));
+        };
+        let tags: Vec<(String, String)> = match path.tag {
+            Some(tag) => vec![(tag, request.body.body_as_json::<String>().await?)],
+            None => request
+                .body
+                .body_as_json::<HashMap<String, String>>()
+                .await?
+                .into_iter()
+                .collect(),
+        };
+        for (tag, version) in tags {
+            let Some(db_version) = self.get_project_version(project.id, &version).await? else {
+                return Ok(Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(format!("Version {} not found in project {}", version, path.name).into())
+                    .into());
+            };
+            info!(?tag, ?version, "Setting dist-tag");
+            DBProjectDistTag::set(project.id, &tag, db_version.id, &self.site.database).await?;
+        }
+        self.dist_tags_response(project.id).await
+    }
+    /// `npm dist-tag rm`. Removing `latest` goes back to picking the highest release
+    #[instrument]
+    async fn delete_dist_tag(
+        &self,
+        path: DistTagPath,
+        request: RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        if request
+            .authentication
+            .get_user_if_has_action(RepositoryActions::Write, self.id, self.site.as_ref())
+            .await?
+            .is_none()
+        {
+            info!("No acceptable user authentication provided");
+            return Ok(RepoResponse::unauthorized());
+        }
+        let Some(project) = self.get_project_from_key(&path.name).await? else {
+            return Ok(Self::project_not_found(&path.name));
+        };
+        let Some(tag) = path.tag else {
+            return Ok(RepoResponse::basic_text_response(
+                StatusCode::BAD_REQUEST,
+                "A tag is required",
+            ));
+        };
+        if !DBProjectDistTag::delete(project.id, &tag, &self.site.database).await?
+            && tag != LATEST_TAG
+        {
+            return Ok(RepoResponse::basic_text_response(
+                StatusCode::NOT_FOUND,
+                format!("Tag {} not found", tag),
+            ));
+        }
+        info!(?tag, "Removed dist-tag");
+        self.dist_tags_response(project.id).await
+    }
 }
 impl NpmRegistryExt for NPMHostedRegistry {}
 impl RepositoryExt for NPMHostedRegistry {}
@@ -145,6 +297,28 @@ impl Repository for NPMHostedRegistry {
     fn is_active(&self) -> bool {
         true
     }
+    #[instrument(fields(repository_type = "npm/hosted"))]
+    async fn reload(&self) -> Result<(), RepositoryFactoryError> {
+        let Some(npm_config_db) = DBRepositoryConfig::<NPMRegistryConfig>::get_config(
+            self.id,
+            NPMRegistryConfigType::get_type_static(),
+            self.site.as_ref(),
+        )
+        .await?
+        else {
+            return Err(RepositoryFactoryError::MissingConfig(
+                NPMRegistryConfigType::get_type_static(),
+            ));
+        };
+        let NPMRegistryConfig::Hosted(config) = npm_config_db.value.0 else {
+            return Err(RepositoryFactoryError::InvalidConfig(
+                NPMRegistryConfigType::get_type_static(),
+                "Expected Hosted Config".into(),
+            ));
+        };
+        *self.config.write() = config.unwrap_or_default();
+        Ok(())
+    }
     async fn handle_get(
         &self,
         request: RepositoryRequest,
@@ -152,11 +326,23 @@ impl Repository for NPMHostedRegistry {
         let headers = request.headers();
         let path_as_string = request.path.to_string();
         debug!(?headers, ?path_as_string, "Handling NPM GET request");
+        if let Some(dist_tag_path) = DistTagPath::parse(&request.path) {
+            return self.get_dist_tags(dist_tag_path).await;
+        }
+        if let Some(done_id) = path_as_string.strip_prefix(WEB_LOGIN_DONE_PATH) {
+            return web_login::poll_login(self, done_id, request).await;
+        }
+        if path_as_string == WHOAMI_PATH {
+            return self.whoami(&request).await;
+        } else if path_as_string == TOKENS_PATH {
+            return self.list_tokens(&request).await;
+        }
         let get_path = match GetPath::try_from(request.path.clone()) {
             Ok(ok) => ok,
             Err(err) => return Ok(err.into_response().into()),
         };
         match get_path {
+            GetPath::Search => self.search(&request).await,
             GetPath::GetPackageInfo { name } => {
                 let Some(project) = self.get_project_from_key(&name).await? else {
                     return Ok(Response::builder()
@@ -167,7 +353,7 @@ impl Repository for NPMHostedRegistry {
                 debug!(?project, "Got project");
                 let versions =
                     DBProjectVersion::get_all_versions(project.id, self.site.as_ref()).await?;
-                let mut dist_tags = HashMap::new();
+                let dist_tags = self.dist_tags(project.id, &versions).await?;
                 let mut times = HashMap::new();
                 times.insert(
                     "created".to_owned(),
@@ -177,9 +363,6 @@ impl Repository for NPMHostedRegistry {
                     "modified".to_owned(),
                     npm_time::format_date_time(&project.updated_at),
                 );
-                if let Some(latest) = versions.first() {
-                    dist_tags.insert("latest".to_string(), latest.version.clone());
-                }
                 let mut versions_map = HashMap::new();
                 for version in versions {
                     times.insert(
@@ -202,6 +385,7 @@ impl Repository for NPMHostedRegistry {
                 }
                 let project_response = NpmRegistryPackageResponse {
                     id: project.key.clone(),
+                    rev: npm_revision(&project.updated_at),
                     name: project.name.clone(),
                     description: project.description.clone(),
                     dist_tags,
@@ -268,7 +452,13 @@ impl Repository for NPMHostedRegistry {
                 storage_path.push_mut(&file);
                 debug!(?storage_path, "Getting file");
                 let storage = self.get_storage();
-                let file = storage.open_file(self.id, &storage_path).await?;
+                let file = open_file_for_request(
+                    &storage,
+                    self.id,
+                    &storage_path,
+                    &FileRequestHeaders::from(headers),
+                )
+                .await?;
                 Ok(RepoResponse::from(file))
             }
             _ => Ok(Response::builder()
@@ -289,8 +479,12 @@ impl Repository for NPMHostedRegistry {
         );
         if path_as_string.starts_with(r#"-/user/org.couchdb.user:"#) {
             return super::login::couch_db::perform_login(self, request).await;
-        } else if path_as_string.eq("-/v1/login") {
-            return super::login::web_login::perform_login(self, request).await;
+        } else if path_as_string.eq(WEB_LOGIN_PATH) {
+            return web_login::perform_login(self, request).await;
+        } else if let Some(dist_tag_path) = DistTagPath::parse(&request.path) {
+            return self.set_dist_tags(dist_tag_path, request).await;
+        } else if let Some(RevisionPath::Package { name }) = RevisionPath::parse(&request.path) {
+            return self.handle_packument_update(&name, request, true).await;
         }
         let Some(user) = request
             .authentication
@@ -312,6 +506,51 @@ impl Repository for NPMHostedRegistry {
 
         match command_header {
             NPMCommand::Publish => self.handle_publish(request).await,
+            NPMCommand::Unpublish | NPMCommand::Deprecate => {
+                let name = request.path.to_string();
+                self.handle_packument_update(&name, request, false).await
+            }
+        }
+    }
+    async fn handle_post(
+        &self,
+        request: RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        let path_as_string = request.path.to_string();
+        if path_as_string == WEB_LOGIN_PATH {
+            return web_login::perform_login(self, request).await;
+        } else if path_as_string == TOKENS_PATH {
+            return self.create_token(request).await;
+        } else if path_as_string == BULK_ADVISORY_PATH {
+            return super::audit::bulk_advisories(&self.site.database, request).await;
+        }
+        let Some(dist_tag_path) = DistTagPath::parse(&request.path) else {
+            return Ok(RepoResponse::unsupported_method_response(
+                request.parts.method,
+                self.get_type(),
+            ));
+        };
+        self.set_dist_tags(dist_tag_path, request).await
+    }
+    async fn handle_delete(
+        &self,
+        request: RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        if let Some(key) = request.path.to_string().strip_prefix(REVOKE_TOKEN_PATH) {
+            return self.revoke_token(key, &request).await;
+        }
+        if let Some(dist_tag_path) = DistTagPath::parse(&request.path) {
+            return self.delete_dist_tag(dist_tag_path, request).await;
+        }
+        match RevisionPath::parse(&request.path) {
+            Some(RevisionPath::Package { name }) => self.unpublish_package(&name, request).await,
+            Some(RevisionPath::Tarball { name, file }) => {
+                self.delete_tarball(&name, &file, request).await
+            }
+            None => Ok(RepoResponse::unsupported_method_response(
+                request.parts.method,
+                self.get_type(),
+            )),
         }
     }
 }
@@ -0,0 +1,102 @@
+use ahash::{HashMap, HashMapExt};
+use axum::{extract::Query, response::IntoResponse};
+use chrono::Local;
+use nr_core::database::entities::{
+    project::search::DBProjectSearchResult,
+    user::{UserSafeData, UserType},
+};
+use tracing::{debug, instrument, warn};
+
+use super::NPMHostedRegistry;
+use crate::{
+    repository::{
+        RepoResponse, RepositoryRequest,
+        npm::{
+            NPMRegistryError,
+            types::{
+                request::PublishVersion,
+                search::{
+                    SearchObject, SearchPackage, SearchQuery, SearchResponse, SearchScore,
+                    SearchText, SearchUser,
+                },
+            },
+            utils::npm_time,
+        },
+    },
+    utils::ResponseBuilder,
+};
+impl NPMHostedRegistry {
+    /// `npm search`. Matches the latest version of every project. See [nr_core::database::entities::project::search::ProjectSearch]
+    #[instrument]
+    pub(super) async fn search(
+        &self,
+        request: &RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        let query = match Query::<SearchQuery>::try_from_uri(&request.parts.uri) {
+            Ok(Query(query)) => query,
+            Err(rejection) => return Ok(rejection.into_response().into()),
+        };
+        let text = SearchText::parse(&query.text);
+        debug!(?text, "Searching");
+        let database = &self.site.database;
+        let results = text
+            .into_project_search(&query)
+            .search(self.id, database)
+            .await?;
+        // Every row carries the total. A page past the end has no rows to read it from
+        let total = results
+            .first()
+            .map(|result| result.total as usize)
+            .unwrap_or_default();
+        let mut publishers: HashMap<i32, Option<SearchUser>> = HashMap::new();
+        let mut objects = Vec::new();
+        for result in results {
+            let DBProjectSearchResult {
+                version,
+                project_key,
+                relevance,
+                ..
+            } = result;
+            let Some(publish) = version
+                .extra
+                .0
+                .extra
+                .clone()
+                .and_then(|extra| serde_json::from_value::<PublishVersion>(extra).ok())
+            else {
+                warn!(?version.version, ?project_key, "Invalid NPM Project");
+                continue;
+            };
+            let mut package =
+                SearchPackage::new(&publish, npm_time::format_date_time(&version.created_at));
+            if let Some(publisher) = version.publisher {
+                package.publisher = match publishers.get(&publisher) {
+                    Some(user) => user.clone(),
+                    None => {
+                        let user =
+                            UserSafeData::get_by_id(publisher, database)
+                                .await?
+                                .map(|user| SearchUser {
+                                    username: user.username.to_string(),
+                                    email: user.email.to_string(),
+                                });
+                        publishers.insert(publisher, user.clone());
+                        user
+                    }
+                };
+            }
+            package.maintainers.extend(package.publisher.clone());
+            objects.push(SearchObject {
+                package,
+                score: SearchScore::new(relevance, publish.deprecated.is_some()),
+                search_score: relevance,
+            });
+        }
+        let response = SearchResponse {
+            objects,
+            total,
+            time: npm_time::format_date_time(&Local::now().fixed_offset()),
+        };
+        Ok(ResponseBuilder::ok().json(&response).into())
+    }
+}
@@ -0,0 +1,134 @@
+use http::StatusCode;
+use nr_core::database::entities::user::auth_token::{NewRepositoryToken, RepositoryAuthToken};
+use tracing::{debug, info, instrument};
+
+use super::NPMHostedRegistry;
+use crate::{
+    app::authentication::{AuthenticationError, verify_login},
+    repository::{
+        RepoResponse, RepositoryRequest,
+        npm::{
+            NPMRegistryError,
+            types::tokens::{
+                CreateTokenRequest, NpmToken, NpmTokenList, WhoamiResponse, matches_token,
+            },
+        },
+    },
+    utils::ResponseBuilder,
+};
+impl NPMHostedRegistry {
+    /// `npm whoami`
+    #[instrument]
+    pub(super) async fn whoami(
+        &self,
+        request: &RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        let Some(user) = request.authentication.get_user() else {
+            return Ok(RepoResponse::unauthorized());
+        };
+        let response = WhoamiResponse {
+            username: user.username.to_string(),
+        };
+        Ok(ResponseBuilder::ok().json(&response).into())
+    }
+    /// `npm token list`. Only tokens scoped to this repository are listed
+    #[instrument]
+    pub(super) async fn list_tokens(
+        &self,
+        request: &RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        let Some(user) = request.authentication.get_user() else {
+            return Ok(RepoResponse::unauthorized());
+        };
+        let tokens =
+            RepositoryAuthToken::get_by_user_and_repository(user.id, self.id, &self.site.database)
+                .await?;
+        let response = NpmTokenList::from(tokens.iter().map(NpmToken::from).collect::<Vec<_>>());
+        Ok(ResponseBuilder::ok().json(&response).into())
+    }
+    /// `npm token create`. The user has to confirm their password.
+    ///
+    /// The token is scoped to this repository. `--read-only` tokens can only read
+    #[instrument]
+    pub(super) async fn create_token(
+        &self,
+        request: RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        let Some(user) = request.authentication.get_user().cloned() else {
+            return Ok(RepoResponse::unauthorized());
+        };
+        let source = match request.user_agent_as_string()? {
+            Some(user_agent) => format!("NPM CLI Token Create ({})", user_agent),
+            None => "NPM CLI Token Create".to_owned(),
+        };
+        let body = request.body.body_as_bytes().await?;
+        let create_request: CreateTokenRequest = serde_json::from_slice(&body)?;
+        let cidr_whitelist = match create_request.cidr_whitelist() {
+            Ok(cidr_whitelist) => cidr_whitelist,
+            Err(err) => {
+                return Ok(RepoResponse::basic_text_response(
+                    StatusCode::BAD_REQUEST,
+                    err.to_string(),
+                ));
+            }
+        };
+        match verify_login(
+            user.username.to_string(),
+            &create_request.password,
+            &self.site.database,
+        )
+        .await
+        {
+            Ok(verified) if verified.id == user.id => {}
+            Ok(_) | Err(AuthenticationError::Unauthorized) => {
+                debug!(?user.id, "Password did not match");
+                return Ok(RepoResponse::unauthorized());
+            }
+            Err(err) => return Err(err.into()),
+        }
+        let (_, token) =
+            NewRepositoryToken::new(user.id, source, self.id, create_request.actions())
+                .with_cidr_whitelist(cidr_whitelist.clone())
+                .insert(self.site.as_ref())
+                .await?;
+        info!(?user.id, readonly = create_request.readonly, "Created NPM token");
+        let response = NpmToken::created(token, cidr_whitelist, create_request.readonly);
+        Ok(ResponseBuilder::ok().json(&response).into())
+    }
+    /// `npm token revoke`. The key can be the start of the key or the plain token
+    #[instrument(skip(key))]
+    pub(super) async fn revoke_token(
+        &self,
+        key: &str,
+        request: &RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        let Some(user) = request.authentication.get_user() else {
+            return Ok(RepoResponse::unauthorized());
+        };
+        let tokens =
+            RepositoryAuthToken::get_by_user_and_repository(user.id, self.id, &self.site.database)
+                .await?;
+        let matching: Vec<_> = tokens
+            .iter()
+            .filter(|token| matches_token(&token.token, key))
+            .collect();
+        let token = match matching.as_slice() {
+            [token] => token,
+            [] => {
+                return Ok(RepoResponse::basic_text_response(
+                    StatusCode::NOT_FOUND,
+                    "Token not found",
+                ));
+            }
+            _ => {
+                return Ok(RepoResponse::basic_text_response(
+                    StatusCode::BAD_REQUEST,
+                    "Key matches more than one token",
+                ));
+            }
+        };
+        token.token.delete(&self.site.database).await?;
+        info!(?user.id, token_id = token.token.id, "Revoked NPM token");
+        Ok(ResponseBuilder::no_content().empty().into())
+    }
+}
@@ -0,0 +1,226 @@
+use chrono::Local;
+use http::StatusCode;
+use nr_core::{
+    database::entities::project::{
+        DBProject,
+        versions::{DBProjectVersion, UpdateProjectVersion},
+    },
+    storage::StoragePath,
+    user::permissions::RepositoryActions,
+};
+use nr_storage::Storage;
+use tracing::{debug, info, instrument, warn};
+
+use super::NPMHostedRegistry;
+use crate::{
+    repository::{
+        RepoResponse, RepositoryRequest,
+        npm::{
+            NPMRegistryError,
+            types::{
+                PackumentUpdate,
+                request::{PublishVersion, extract_version_from_file},
+            },
+        },
+        utils::RepositoryExt,
+    },
+    utils::ResponseBuilder,
+};
+
+/// The response the public registry sends for writes
+fn ok_response() -> RepoResponse {
+    ResponseBuilder::ok()
+        .json(&serde_json::json!({ "ok": true }))
+        .into()
+}
+impl NPMHostedRegistry {
+    /// Returns a response if the version is outside of the unpublish window
+    fn check_unpublish_window(&self, version: &DBProjectVersion) -> Option<RepoResponse> {
+        let window = self.config.read().unpublish_window()?;
+        let age = Local::now().fixed_offset() - version.created_at;
+        if age <= window {
+            return None;
+        }
+        Some(RepoResponse::basic_text_response(
+            StatusCode::FORBIDDEN,
+            format!(
+                "Version {} was published more than {} hours ago and can no longer be unpublished",
+                version.version,
+                window.num_hours()
+            ),
+        ))
+    }
+    /// Deletes the version and its files
+    async fn remove_version(&self, version: &DBProjectVersion) -> Result<(), NPMRegistryError> {
+        DBProjectVersion::delete_by_id(version.id, &self.site.database).await?;
+        self.storage
+            .delete_file(self.id, &StoragePath::from(version.path.as_str()))
+            .await?;
+        info!(?version.version, ?version.project_id, "Unpublished Version");
+        Ok(())
+    }
+    /// Deletes the project if the last version was unpublished
+    async fn remove_project_if_empty(&self, project: &DBProject) -> Result<(), NPMRegistryError> {
+        let versions = DBProjectVersion::get_all_versions(project.id, self.site.as_ref()).await?;
+        if !versions.is_empty() {
+            return Ok(());
+        }
+        DBProject::delete_by_id(project.id, &self.site.database).await?;
+        self.storage
+            .delete_file(self.id, &StoragePath::from(project.path.as_str()))
+            .await?;
+        info!(?project.key, "Unpublished Project");
+        Ok(())
+    }
+    /// Handles the packument npm sends back.
+    ///
+    /// `npm deprecate` changes the `deprecated` message of versions.
+    /// `npm unpublish` removes versions. Removal is only allowed if `allow_removal` is set. The versions must be inside of the unpublish window
+    #[instrument]
+    pub(super) async fn handle_packument_update(
+        &self,
+        name: &str,
+        request: RepositoryRequest,
+        allow_removal: bool,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        if request
+            .authentication
+            .get_user_if_has_action(RepositoryActions::Write, self.id, self.site.as_ref())
+            .await?
+            .is_none()
+        {
+            info!("No acceptable user authentication provided");
+            return Ok(RepoResponse::unauthorized());
+        }
+        let Some(project) = self.get_project_from_key(name).await? else {
+            return Ok(Self::project_not_found(name));
+        };
+        let update: PackumentUpdate = request.body.body_as_json().await?;
+        debug!(?update.name, versions = update.versions.len(), "Handling Packument Update");
+        let versions = DBProjectVersion::get_all_versions(project.id, self.site.as_ref()).await?;
+        let (kept, removed): (Vec<_>, Vec<_>) = versions
+            .into_iter()
+            .partition(|version| update.versions.contains_key(&version.version));
+        if allow_removal {
+            if let Some(response) = removed
+                .iter()
+                .find_map(|version| self.check_unpublish_window(version))
+            {
+                return Ok(response);
+            }
+            for version in &removed {
+                self.remove_version(version).await?;
+            }
+        } else if !removed.is_empty() {
+            debug!(
+                count = removed.len(),
+                "Ignoring versions missing from the update"
+            );
+        }
+        for version in kept {
+            let deprecated = update
+                .versions
+                .get(&version.version)
+                .and_then(|update| update.deprecated.clone())
+                .filter(|message| !message.is_empty());
+            self.set_deprecated(version, deprecated).await?;
+        }
+        self.remove_project_if_empty(&project).await?;
+        Ok(ok_response())
+    }
+    /// Stores the deprecation message on the version. Served with the version in packuments
+    async fn set_deprecated(
+        &self,
+        version: DBProjectVersion,
+        deprecated: Option<String>,
+    ) -> Result<(), NPMRegistryError> {
+        let mut extra = version.extra.0;
+        let Some(version_json) = extra.extra.take() else {
+            warn!(?version.version, "Invalid NPM Project");
+            return Ok(());
+        };
+        let mut publish_version: PublishVersion = serde_json::from_value(version_json)?;
+        if publish_version.deprecated == deprecated {
+            return Ok(());
+        }
+        info!(?version.version, ?deprecated, "Setting deprecation");
+        publish_version.deprecated = deprecated;
+        extra.extra = Some(serde_json::to_value(&publish_version)?);
+        let update = UpdateProjectVersion {
+            extra: Some(extra),
+            ..Default::default()
+        };
+        update.update(version.id, &self.site.database).await?;
+        Ok(())
+    }
+    /// `npm unpublish {package} --force`. Every version must be inside of the unpublish window
+    #[instrument]
+    pub(super) async fn unpublish_package(
+        &self,
+        name: &str,
+        request: RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        if request
+            .authentication
+            .get_user_if_has_action(RepositoryActions::Write, self.id, self.site.as_ref())
+            .await?
+            .is_none()
+        {
+            info!("No acceptable user authentication provided");
+            return Ok(RepoResponse::unauthorized());
+        }
+        let Some(project) = self.get_project_from_key(name).await? else {
+            return Ok(Self::project_not_found(name));
+        };
+        let versions = DBProjectVersion::get_all_versions(project.id, self.site.as_ref()).await?;
+        if let Some(response) = versions
+            .iter()
+            .find_map(|version| self.check_unpublish_window(version))
+        {
+            return Ok(response);
+        }
+        for version in &versions {
+            self.remove_version(version).await?;
+        }
+        self.remove_project_if_empty(&project).await?;
+        Ok(ok_response())
+    }
+    /// The last step of `npm unpublish {package}@{version}`.
+    ///
+    /// The version was already removed by the packument update. If it still exists it is removed
+    #[instrument]
+    pub(super) async fn delete_tarball(
+        &self,
+        name: &str,
+        file: &str,
+        request: RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        if request
+            .authentication
+            .get_user_if_has_action(RepositoryActions::Write, self.id, self.site.as_ref())
+            .await?
+            .is_none()
+        {
+            info!("No acceptable user authentication provided");
+            return Ok(RepoResponse::unauthorized());
+        }
+        let Some(project) = self.get_project_from_key(name).await? else {
+            // The last version was unpublished with the packument update
+            return Ok(ok_response());
+        };
+        let version = extract_version_from_file(file).ok_or(NPMRegistryError::InvalidGetRequest)?;
+        if let Some(version) = self.get_project_version(project.id, &version).await? {
+            if let Some(response) = self.check_unpublish_window(&version) {
+                return Ok(response);
+            }
+            self.remove_version(&version).await?;
+            self.remove_project_if_empty(&project).await?;
+        } else {
+            let mut path = StoragePath::from(project.path.as_str());
+            path.push_mut(&version);
+            path.push_mut(file);
+            self.storage.delete_file(self.id, &path).await?;
+        }
+        Ok(ok_response())
+    }
+}
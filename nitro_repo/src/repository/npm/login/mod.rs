@@ -1,7 +1,7 @@
 use axum::response::{IntoResponse, Response};
 use couch_db::CouchDBLoginResponse;
 use derive_more::derive::From;
-use http::StatusCode;
+use http::{StatusCode, header::CONTENT_TYPE};
 
 use crate::repository::RepoResponse;
 pub mod couch_db;
@@ -10,7 +10,9 @@ pub mod web_login;
 #[derive(Debug, From)]
 pub enum LoginResponse {
     ValidCouchDBLogin(CouchDBLoginResponse),
-    UnsupportedLogin,
+    /// The response to polling the done url of an approved web login. The body is the same as a CouchDB login
+    #[from(skip)]
+    ValidWebLogin(CouchDBLoginResponse),
 }
 
 impl IntoResponse for LoginResponse {
@@ -20,9 +22,10 @@ impl IntoResponse for LoginResponse {
                 .status(StatusCode::CREATED)
                 .body(serde_json::to_string(&login).unwrap().into())
                 .unwrap(),
-            LoginResponse::UnsupportedLogin => Response::builder()
-                .status(StatusCode::IM_A_TEAPOT)
-                .body("Unsupported Login Type".into())
+            LoginResponse::ValidWebLogin(login) => Response::builder()
+                .status(StatusCode::OK)
+                .header(CONTENT_TYPE, "application/json")
+                .body(serde_json::to_string(&login).unwrap().into())
                 .unwrap(),
         }
     }
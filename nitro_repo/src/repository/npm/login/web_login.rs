@@ -1,20 +1,235 @@
-use serde::{Deserialize, Serialize};
+//! `npm login --auth-type=web`. The default since npm 9
+//!
+//! 1. The CLI sends `POST -/v1/login`. The response contains a `loginUrl` and a `doneUrl`
+//! 2. The user opens the `loginUrl` and approves the login with their session
+//! 3. The CLI polls the `doneUrl`. Until the login is approved it responds with `202 Accepted`.
+//!    After that it responds with a new token for the repository
+use std::time::{Duration, Instant};
 
-use crate::repository::{
-    RepoResponse, RepositoryRequest,
-    npm::{NPMRegistryError, utils::NpmRegistryExt},
+use ahash::HashMap;
+use http::{StatusCode, header::RETRY_AFTER};
+use nr_core::{
+    database::entities::user::auth_token::NewRepositoryToken, user::permissions::RepositoryActions,
 };
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument, trace};
+use uuid::Uuid;
 
-use super::LoginResponse;
+use super::{LoginResponse, couch_db::CouchDBLoginResponse};
+use crate::{
+    repository::{
+        RepoResponse, RepositoryRequest,
+        npm::{NPMRegistryError, hosted::NPMHostedRegistry, utils::NpmRegistryExt},
+    },
+    utils::ResponseBuilder,
+};
+pub const WEB_LOGIN_PATH: &str = "-/v1/login";
+/// Followed by the done id
+pub const WEB_LOGIN_DONE_PATH: &str = "-/v1/done/";
+/// How long the user has to approve a login
+pub const WEB_LOGIN_TTL: Duration = Duration::from_secs(10 * 60);
+/// Seconds the CLI waits before polling the done url again
+const RETRY_AFTER_SECONDS: &str = "5";
+/// The body npm sends to start the login. Older versions send an empty body
+#[derive(Debug, Default, Deserialize)]
+pub struct WebLoginRequest {
+    #[serde(default)]
+    pub hostname: Option<String>,
+}
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct WebLoginResponse {
     pub done_url: String,
     pub login_url: String,
 }
+/// A login waiting to be approved.
+///
+/// The `login_id` is in the url the user opens. The `done_id` is only known to the CLI. So seeing the login url is not enough to get the token
+#[derive(Debug, Clone)]
+pub struct PendingWebLogin {
+    pub login_id: Uuid,
+    pub done_id: Uuid,
+    /// The hostname of the computer npm is running on
+    pub hostname: Option<String>,
+    /// The user that approved the login
+    pub approved_by: Option<i32>,
+    pub created: Instant,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebLoginState {
+    Pending,
+    Approved(i32),
+    /// The login never existed or it expired
+    Missing,
+}
+/// The pending logins of a repository. Kept in memory
+#[derive(Debug)]
+pub struct WebLogins {
+    ttl: Duration,
+    /// Keyed by the done id
+    logins: Mutex<HashMap<Uuid, PendingWebLogin>>,
+}
+impl Default for WebLogins {
+    fn default() -> Self {
+        Self::new(WEB_LOGIN_TTL)
+    }
+}
+impl WebLogins {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            logins: Mutex::default(),
+        }
+    }
+    pub fn start(&self, hostname: Option<String>) -> PendingWebLogin {
+        let login = PendingWebLogin {
+            login_id: Uuid::new_v4(),
+            done_id: Uuid::new_v4(),
+            hostname,
+            approved_by: None,
+            created: Instant::now(),
+        };
+        let mut logins = self.logins.lock();
+        logins.retain(|_, login| login.created.elapsed() < self.ttl);
+        trace!(pending = logins.len(), "Starting web login");
+        logins.insert(login.done_id, login.clone());
+        login
+    }
+    /// The login the user is approving. None if it expired
+    pub fn get_by_login_id(&self, login_id: Uuid) -> Option<PendingWebLogin> {
+        self.logins
+            .lock()
+            .values()
+            .find(|login| login.login_id == login_id && login.created.elapsed() < self.ttl)
+            .cloned()
+    }
+    /// Returns false if the login does not exist, expired or was already approved
+    pub fn approve(&self, login_id: Uuid, user_id: i32) -> bool {
+        let mut logins = self.logins.lock();
+        let Some(login) = logins.values_mut().find(|login| {
+            login.login_id == login_id
+                && login.approved_by.is_none()
+                && login.created.elapsed() < self.ttl
+        }) else {
+            return false;
+        };
+        login.approved_by = Some(user_id);
+        true
+    }
+    /// Approved and expired logins are removed
+    pub fn poll(&self, done_id: Uuid) -> WebLoginState {
+        let mut logins = self.logins.lock();
+        let Some(login) = logins.get(&done_id) else {
+            return WebLoginState::Missing;
+        };
+        if login.created.elapsed() >= self.ttl {
+            logins.remove(&done_id);
+            return WebLoginState::Missing;
+        }
+        match login.approved_by {
+            Some(user_id) => {
+                logins.remove(&done_id);
+                WebLoginState::Approved(user_id)
+            }
+            None => WebLoginState::Pending,
+        }
+    }
+}
+/// Starts the login. Required route is `-/v1/login`
+#[instrument(name = "npm_web_login")]
 pub async fn perform_login(
-    repository: &impl NpmRegistryExt,
+    repository: &NPMHostedRegistry,
+    request: RepositoryRequest,
+) -> Result<RepoResponse, NPMRegistryError> {
+    let headers = request.headers().clone();
+    let body = request.body.body_as_bytes().await?;
+    let login_request: WebLoginRequest = if body.is_empty() {
+        WebLoginRequest::default()
+    } else {
+        serde_json::from_slice(&body)?
+    };
+    let login = repository.web_logins.start(login_request.hostname);
+    debug!(?login.hostname, "Started web login");
+    let response = WebLoginResponse {
+        login_url: format!(
+            "{}/npm/login/{}/{}",
+            repository.site_url(&headers),
+            repository.id,
+            login.login_id
+        ),
+        done_url: format!(
+            "{}/{}{}",
+            repository.registry_url(&headers),
+            WEB_LOGIN_DONE_PATH,
+            login.done_id
+        ),
+    };
+    Ok(ResponseBuilder::ok().json(&response).into())
+}
+/// Polled by the CLI. Required route is `-/v1/done/{done_id}`
+#[instrument(name = "npm_web_login_done")]
+pub async fn poll_login(
+    repository: &NPMHostedRegistry,
+    done_id: &str,
     request: RepositoryRequest,
 ) -> Result<RepoResponse, NPMRegistryError> {
-    // TODO: Implement Web Login
-    Ok(LoginResponse::UnsupportedLogin.into())
+    let Ok(done_id) = Uuid::parse_str(done_id) else {
+        return Ok(RepoResponse::basic_text_response(
+            StatusCode::NOT_FOUND,
+            "Login not found",
+        ));
+    };
+    let user_id = match repository.web_logins.poll(done_id) {
+        WebLoginState::Pending => {
+            return Ok(ResponseBuilder::default()
+                .status(StatusCode::ACCEPTED)
+                .header(RETRY_AFTER, RETRY_AFTER_SECONDS)
+                .json(&serde_json::json!({}))
+                .into());
+        }
+        WebLoginState::Missing => {
+            return Ok(RepoResponse::basic_text_response(
+                StatusCode::NOT_FOUND,
+                "Login not found or expired",
+            ));
+        }
+        WebLoginState::Approved(user_id) => user_id,
+    };
+    let source = match request.user_agent_as_string()? {
+        Some(user_agent) => format!("NPM CLI Web Login ({})", user_agent),
+        None => "NPM CLI Web Login".to_owned(),
+    };
+    let (_, token) =
+        NewRepositoryToken::new(user_id, source, repository.id, RepositoryActions::all())
+            .insert(repository.site.as_ref())
+            .await?;
+    info!(?user_id, "Web login completed");
+    Ok(LoginResponse::ValidWebLogin(CouchDBLoginResponse::from(token)).into())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn login_flow() {
+        let logins = WebLogins::default();
+        let login = logins.start(Some("dev-machine".to_owned()));
+        assert_eq!(logins.poll(login.done_id), WebLoginState::Pending);
+        // The done id can not be used to approve
+        assert!(!logins.approve(login.done_id, 1));
+        assert!(logins.get_by_login_id(login.login_id).is_some());
+        assert!(logins.approve(login.login_id, 1));
+        assert!(!logins.approve(login.login_id, 2));
+        assert_eq!(logins.poll(login.done_id), WebLoginState::Approved(1));
+        // The token is only issued once
+        assert_eq!(logins.poll(login.done_id), WebLoginState::Missing);
+    }
+    #[test]
+    fn expired_logins() {
+        let logins = WebLogins::new(Duration::ZERO);
+        let login = logins.start(None);
+        assert!(logins.get_by_login_id(login.login_id).is_none());
+        assert!(!logins.approve(login.login_id, 1));
+        assert_eq!(logins.poll(login.done_id), WebLoginState::Missing);
+    }
 }
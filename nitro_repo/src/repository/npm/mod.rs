@@ -16,8 +16,10 @@ use nr_storage::DynStorage;
 use tracing::debug;
 use types::InvalidNPMPackageName;
 
+pub mod audit;
 pub mod hosted;
 pub mod login;
+pub mod proxy;
 pub mod types;
 pub mod utils;
 use crate::{
@@ -34,6 +36,7 @@ pub use configs::*;
 #[repository_handler(error=NPMRegistryError)]
 pub enum NPMRegistry {
     Hosted(hosted::NPMHostedRegistry),
+    Proxy(proxy::NPMProxyRegistry),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -187,10 +190,17 @@ impl RepositoryType for NpmRegistryType {
             };
             let npm_config = npm_config_db.value.0;
             match npm_config {
-                NPMRegistryConfig::Hosted => {
-                    let maven_hosted = NPMHostedRegistry::load(website, storage, repo).await?;
+                NPMRegistryConfig::Hosted(config) => {
+                    let maven_hosted =
+                        NPMHostedRegistry::load(website, storage, repo, config.unwrap_or_default())
+                            .await?;
                     Ok(NPMRegistry::Hosted(maven_hosted).into())
                 }
+                NPMRegistryConfig::Proxy(config) => {
+                    let npm_proxy =
+                        proxy::NPMProxyRegistry::load(website, storage, repo, config).await?;
+                    Ok(NPMRegistry::Proxy(npm_proxy).into())
+                }
             }
         })
     }
@@ -0,0 +1,719 @@
+//! Proxies an upstream NPM Registry. Such as https://registry.npmjs.org
+//!
+//! Packuments are cached as the upstream sent them and revalidated after their max age.
+//! The tarball URLs are pointed at this registry when the packument is served. Tarballs never change so they are cached forever.
+use std::sync::Arc;
+
+use bytes::Bytes;
+use derive_more::derive::Deref;
+use http::{
+    StatusCode,
+    header::{ACCEPT, IF_MODIFIED_SINCE, IF_NONE_MATCH},
+};
+use nr_core::{
+    database::entities::repository::{DBRepository, DBRepositoryConfig},
+    repository::{Visibility, config::RepositoryConfigType, proxy_url::ProxyURL},
+    storage::StoragePath,
+};
+use nr_storage::{DynStorage, FileContent, Storage};
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+use super::{
+    NPMRegistryConfig, NPMRegistryConfigType, NPMRegistryError, audit,
+    types::audit::BULK_ADVISORY_PATH, utils::NpmRegistryExt,
+};
+use crate::{
+    app::NitroRepo,
+    repository::{
+        FileRequestHeaders, RepoResponse, Repository, RepositoryFactoryError, RepositoryRequest,
+        open_file_for_request,
+        proxy::{
+            ProxyCacheMeta, ProxyClientError, ProxyCredentials, ProxyHttpSettings, is_url_within,
+        },
+    },
+    utils::ResponseBuilder,
+};
+/// Where the packument of a package is cached. Next to the `-` directory of the tarballs
+const PACKUMENT_FILE: &str = "packument.json";
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct NPMProxyConfig {
+    pub routes: Vec<NPMProxyRepositoryRoute>,
+    #[serde(default)]
+    pub cache: NPMProxyCacheConfig,
+}
+impl NPMProxyConfig {
+    pub fn sort(&mut self) {
+        self.routes.sort_by(|a, b| match (a.priority, b.priority) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+    }
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct NPMProxyRepositoryRoute {
+    pub url: ProxyURL,
+    pub name: Option<String>,
+    /// If Null then it will be the lowest priority
+    pub priority: Option<i32>,
+    /// Credentials used to authenticate with the upstream registry
+    #[serde(default)]
+    pub credentials: Option<ProxyCredentials>,
+    #[serde(default)]
+    pub http: ProxyHttpSettings,
+}
+impl NPMProxyRepositoryRoute {
+    /// Removes any secrets so the route can be shown to the public
+    pub fn sanitize_for_public_view(&mut self) {
+        self.credentials = self.credentials.as_ref().map(ProxyCredentials::redacted);
+        self.http = self.http.redacted();
+    }
+}
+/// How long cached packuments are served before they are revalidated with the upstream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct NPMProxyCacheConfig {
+    /// Seconds. Null means packuments are never revalidated
+    #[schemars(title = "Packument Max Age")]
+    pub packument_max_age: Option<u64>,
+}
+impl Default for NPMProxyCacheConfig {
+    fn default() -> Self {
+        Self {
+            packument_max_age: Some(5 * 60),
+        }
+    }
+}
+impl NPMProxyCacheConfig {
+    pub fn packument_max_age(&self) -> Option<std::time::Duration> {
+        self.packument_max_age.map(std::time::Duration::from_secs)
+    }
+}
+/// A route and the HTTP client used to make requests to it.
+#[derive(Debug, Clone)]
+pub struct NPMProxyRoute {
+    pub config: NPMProxyRepositoryRoute,
+    pub client: reqwest::Client,
+}
+/// The response of a single route
+#[derive(Debug)]
+enum RouteResponse {
+    Found {
+        bytes: Bytes,
+        cache_meta: ProxyCacheMeta,
+    },
+    NotModified,
+    Missing,
+    /// The route responded with a server error
+    Unavailable(StatusCode),
+}
+impl NPMProxyRoute {
+    /// Builds the clients for all routes. Sorted by priority
+    pub fn from_config(mut config: NPMProxyConfig) -> Result<Vec<Self>, ProxyClientError> {
+        config.sort();
+        config
+            .routes
+            .into_iter()
+            .map(|route| {
+                let client = route.http.build_client(route.credentials.as_ref())?;
+                Ok(NPMProxyRoute {
+                    config: route,
+                    client,
+                })
+            })
+            .collect()
+    }
+    /// The name of the route or the url if it does not have one
+    pub fn display_name(&self) -> String {
+        self.config
+            .name
+            .clone()
+            .unwrap_or_else(|| self.config.url.to_string())
+    }
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.config.url, path.trim_start_matches('/'))
+    }
+    /// Registries expect the `/` of scoped packages to be encoded
+    fn packument_url(&self, name: &str) -> String {
+        self.url(&name.replace('/', "%2f"))
+    }
+    async fn send(
+        request: reqwest::RequestBuilder,
+        validators: Option<&ProxyCacheMeta>,
+    ) -> Result<RouteResponse, reqwest::Error> {
+        let mut request = request;
+        if let Some(validators) = validators {
+            if let Some(etag) = &validators.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(RouteResponse::NotModified);
+        }
+        if response.status().is_server_error() {
+            warn!(?response, "Upstream returned a server error");
+            return Ok(RouteResponse::Unavailable(response.status()));
+        }
+        if !response.status().is_success() {
+            debug!(?response, "Upstream does not have the file");
+            return Ok(RouteResponse::Missing);
+        }
+        let cache_meta = ProxyCacheMeta::from_response_headers(response.headers());
+        let bytes = response.bytes().await?;
+        Ok(RouteResponse::Found { bytes, cache_meta })
+    }
+    async fn request_packument(
+        &self,
+        name: &str,
+        validators: Option<&ProxyCacheMeta>,
+    ) -> Result<RouteResponse, reqwest::Error> {
+        let request = self
+            .client
+            .get(self.packument_url(name))
+            .header(ACCEPT, "application/json");
+        Self::send(request, validators).await
+    }
+    async fn request_url(&self, url: &str) -> Result<RouteResponse, reqwest::Error> {
+        Self::send(self.client.get(url), None).await
+    }
+}
+/// The paths a NPM client requests from a proxy
+///
+/// - `{package}` - The packument
+/// - `{package}/{version}` - A single version of the packument
+/// - `{package}/-/{file}` - A tarball. Scoped packages published with npm use `@{scope}/{package}/-/@{scope}/{file}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NPMProxyPath {
+    Packument { name: String },
+    Version { name: String, version: String },
+    Tarball { name: String, file: String },
+}
+impl NPMProxyPath {
+    pub fn parse(path: &StoragePath) -> Option<Self> {
+        let path = path.to_string();
+        let components: Vec<&str> = path
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .collect();
+        let (name, rest) = if components.first()?.starts_with('@') {
+            if components.len() < 2 {
+                return None;
+            }
+            (
+                format!("{}/{}", components[0], components[1]),
+                &components[2..],
+            )
+        } else {
+            (components[0].to_owned(), &components[1..])
+        };
+        match rest {
+            [] => Some(NPMProxyPath::Packument { name }),
+            [version] => Some(NPMProxyPath::Version {
+                name,
+                version: (*version).to_owned(),
+            }),
+            ["-", file] => Some(NPMProxyPath::Tarball {
+                name,
+                file: (*file).to_owned(),
+            }),
+            ["-", scope, file] if scope.starts_with('@') => Some(NPMProxyPath::Tarball {
+                name,
+                file: (*file).to_owned(),
+            }),
+            _ => None,
+        }
+    }
+}
+pub fn packument_path(name: &str) -> StoragePath {
+    StoragePath::from(format!("{name}/{PACKUMENT_FILE}"))
+}
+pub fn tarball_path(name: &str, file: &str) -> StoragePath {
+    StoragePath::from(format!("{name}/-/{file}"))
+}
+/// Points the tarball of every version at this registry. `registry_url` is the URL of the repository
+pub fn rewrite_tarballs(packument: &mut Value, registry_url: &str, name: &str) {
+    let Some(versions) = packument.get_mut("versions").and_then(Value::as_object_mut) else {
+        return;
+    };
+    for version in versions.values_mut() {
+        let Some(dist) = version.get_mut("dist").and_then(Value::as_object_mut) else {
+            continue;
+        };
+        let Some(file) = dist
+            .get("tarball")
+            .and_then(Value::as_str)
+            .and_then(|tarball| tarball.rsplit('/').next())
+            .map(ToOwned::to_owned)
+        else {
+            continue;
+        };
+        dist.insert(
+            "tarball".to_owned(),
+            Value::String(format!("{registry_url}/{name}/-/{file}")),
+        );
+    }
+}
+/// The URL the upstream published the tarball at
+fn upstream_tarball_url(packument: &Value, file: &str) -> Option<String> {
+    packument
+        .get("versions")?
+        .as_object()?
+        .values()
+        .filter_map(|version| version.get("dist")?.get("tarball")?.as_str())
+        .find(|tarball| tarball.rsplit('/').next() == Some(file))
+        .map(ToOwned::to_owned)
+}
+#[derive(derive_more::Debug)]
+pub struct NPMProxyInner {
+    #[debug(skip)]
+    pub site: NitroRepo,
+    pub storage: DynStorage,
+    pub id: Uuid,
+    pub repository: DBRepository,
+    pub routes: RwLock<Vec<NPMProxyRoute>>,
+    pub cache: RwLock<NPMProxyCacheConfig>,
+}
+#[derive(Debug, Clone, Deref)]
+pub struct NPMProxyRegistry(Arc<NPMProxyInner>);
+impl NPMProxyRegistry {
+    pub async fn load(
+        site: NitroRepo,
+        storage: DynStorage,
+        repository: DBRepository,
+        config: NPMProxyConfig,
+    ) -> Result<Self, RepositoryFactoryError> {
+        let cache = config.cache.clone();
+        let routes = NPMProxyRoute::from_config(config).map_err(|err| {
+            RepositoryFactoryError::InvalidConfig(
+                NPMRegistryConfigType::get_type_static(),
+                err.to_string(),
+            )
+        })?;
+        Ok(Self(Arc::new(NPMProxyInner {
+            site,
+            storage,
+            id: repository.id,
+            repository,
+            routes: RwLock::new(routes),
+            cache: RwLock::new(cache),
+        })))
+    }
+    async fn read_cached(&self, path: &StoragePath) -> Result<Option<Vec<u8>>, NPMRegistryError> {
+        let Some((reader, meta)) = self
+            .storage
+            .open_file(self.id, path)
+            .await?
+            .and_then(|file| file.file())
+        else {
+            return Ok(None);
+        };
+        let content = reader
+            .read_to_vec(meta.file_type.file_size as usize)
+            .await?;
+        Ok(Some(content))
+    }
+    async fn save_cached(
+        &self,
+        path: &StoragePath,
+        bytes: Bytes,
+        cache_meta: &ProxyCacheMeta,
+    ) -> Result<(), NPMRegistryError> {
+        self.storage
+            .save_file(self.id, FileContent::Bytes(bytes), path)
+            .await?;
+        self.put_cache_meta(path, cache_meta).await
+    }
+    async fn put_cache_meta(
+        &self,
+        path: &StoragePath,
+        cache_meta: &ProxyCacheMeta,
+    ) -> Result<(), NPMRegistryError> {
+        let mut meta = self
+            .storage
+            .get_repository_meta(self.id, path)
+            .await?
+            .unwrap_or_default();
+        cache_meta.apply_to(&mut meta);
+        self.storage
+            .put_repository_meta(self.id, path, meta)
+            .await?;
+        Ok(())
+    }
+    /// The packument as the upstream sent it.
+    ///
+    /// A cached packument is revalidated once it is older than the max age.
+    /// If no route can be reached the cached packument is served
+    #[instrument(skip(self), fields(nr.repository.id = %self.id, nr.repository.name = %self.repository.name))]
+    async fn load_packument(&self, name: &str) -> Result<Option<Value>, NPMRegistryError> {
+        let path = packument_path(name);
+        let cached = match self.storage.get_file_information(self.id, &path).await? {
+            Some(file) => {
+                let meta = self
+                    .storage
+                    .get_repository_meta(self.id, &path)
+                    .await?
+                    .unwrap_or_default();
+                Some((ProxyCacheMeta::from(&meta), file.modified))
+            }
+            None => None,
+        };
+        let max_age = self.cache.read().packument_max_age();
+        let expired = cached
+            .as_ref()
+            .is_none_or(|(cache_meta, modified)| cache_meta.is_expired(max_age, *modified));
+        if expired {
+            let validators = cached.as_ref().map(|(cache_meta, _)| cache_meta);
+            let routes = self.routes.read().clone();
+            for route in routes {
+                match route.request_packument(name, validators).await {
+                    Ok(RouteResponse::Found { bytes, cache_meta }) => {
+                        let packument: Value = match serde_json::from_slice(&bytes) {
+                            Ok(packument) => packument,
+                            Err(err) => {
+                                warn!(?err, route = %route.display_name(), "Upstream sent an invalid packument");
+                                continue;
+                            }
+                        };
+                        self.save_cached(&path, bytes, &cache_meta).await?;
+                        return Ok(Some(packument));
+                    }
+                    Ok(RouteResponse::NotModified) => {
+                        debug!(?name, "Cached packument is still current");
+                        if let Some((mut cache_meta, _)) = cached.clone() {
+                            cache_meta.mark_checked();
+                            self.put_cache_meta(&path, &cache_meta).await?;
+                        }
+                        break;
+                    }
+                    Ok(RouteResponse::Missing) => {}
+                    Ok(RouteResponse::Unavailable(status)) => {
+                        debug!(?status, route = %route.display_name(), "Route is unavailable");
+                    }
+                    Err(err) => {
+                        error!(?err, route = %route.display_name(), "Failed to send request");
+                    }
+                }
+            }
+            if cached.is_some() {
+                debug!(?name, "Serving cached packument");
+            }
+        }
+        let Some(content) = self.read_cached(&path).await? else {
+            return Ok(None);
+        };
+        Ok(Some(serde_json::from_slice(&content)?))
+    }
+    /// Downloads the tarball into the cache. Returns false if no route has it
+    #[instrument(skip(self), fields(nr.repository.id = %self.id, nr.repository.name = %self.repository.name))]
+    async fn download_tarball(&self, name: &str, file: &str) -> Result<bool, NPMRegistryError> {
+        let published_url = self
+            .load_packument(name)
+            .await?
+            .and_then(|packument| upstream_tarball_url(&packument, file));
+        let routes = self.routes.read().clone();
+        for route in routes {
+            // The tarball is requested from the URL in the packument if it belongs to the route. So credentials are only sent to their route
+            let url = published_url
+                .clone()
+                .filter(|url| is_url_within(url, route.config.url.as_str()))
+                .unwrap_or_else(|| route.url(&format!("{name}/-/{file}")));
+            match route.request_url(&url).await {
+                Ok(RouteResponse::Found { bytes, cache_meta }) => {
+                    info!(?url, "Downloaded tarball");
+                    self.save_cached(&tarball_path(name, file), bytes, &cache_meta)
+                        .await?;
+                    return Ok(true);
+                }
+                Ok(response) => {
+                    debug!(?response, ?url, "Route does not have the tarball");
+                }
+                Err(err) => {
+                    error!(?err, ?url, "Failed to send request");
+                }
+            }
+        }
+        Ok(false)
+    }
+    fn not_found(message: String) -> RepoResponse {
+        RepoResponse::basic_text_response(StatusCode::NOT_FOUND, message)
+    }
+}
+impl NpmRegistryExt for NPMProxyRegistry {}
+impl Repository for NPMProxyRegistry {
+    type Error = NPMRegistryError;
+    fn get_storage(&self) -> DynStorage {
+        self.0.storage.clone()
+    }
+    fn site(&self) -> NitroRepo {
+        self.0.site.clone()
+    }
+    fn get_type(&self) -> &'static str {
+        "npm"
+    }
+    fn full_type(&self) -> &'static str {
+        "npm/proxy"
+    }
+    fn config_types(&self) -> Vec<&str> {
+        vec![NPMRegistryConfigType::get_type_static()]
+    }
+    fn name(&self) -> String {
+        self.0.repository.name.to_string()
+    }
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn visibility(&self) -> Visibility {
+        Visibility::Public
+    }
+    fn is_active(&self) -> bool {
+        true
+    }
+    #[instrument(fields(repository_type = "npm/proxy"))]
+    async fn reload(&self) -> Result<(), RepositoryFactoryError> {
+        let Some(npm_config_db) = DBRepositoryConfig::<NPMRegistryConfig>::get_config(
+            self.id,
+            NPMRegistryConfigType::get_type_static(),
+            self.site.as_ref(),
+        )
+        .await?
+        else {
+            return Err(RepositoryFactoryError::MissingConfig(
+                NPMRegistryConfigType::get_type_static(),
+            ));
+        };
+        let NPMRegistryConfig::Proxy(config) = npm_config_db.value.0 else {
+            return Err(RepositoryFactoryError::InvalidConfig(
+                NPMRegistryConfigType::get_type_static(),
+                "Expected Proxy Config".into(),
+            ));
+        };
+        let cache = config.cache.clone();
+        let routes = NPMProxyRoute::from_config(config).map_err(|err| {
+            RepositoryFactoryError::InvalidConfig(
+                NPMRegistryConfigType::get_type_static(),
+                err.to_string(),
+            )
+        })?;
+        *self.routes.write() = routes;
+        *self.cache.write() = cache;
+        Ok(())
+    }
+    async fn handle_get(
+        &self,
+        request: RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        let headers = request.headers();
+        let Some(path) = NPMProxyPath::parse(&request.path) else {
+            return Ok(Self::not_found("Not Found".to_owned()));
+        };
+        debug!(?path, "Handling NPM Proxy GET request");
+        match path {
+            NPMProxyPath::Packument { name } => {
+                let Some(mut packument) = self.load_packument(&name).await? else {
+                    return Ok(Self::not_found(format!("Project {} not found", name)));
+                };
+                rewrite_tarballs(&mut packument, &self.registry_url(headers), &name);
+                Ok(ResponseBuilder::ok().json(&packument).into())
+            }
+            NPMProxyPath::Version { name, version } => {
+                let Some(mut packument) = self.load_packument(&name).await? else {
+                    return Ok(Self::not_found(format!("Project {} not found", name)));
+                };
+                rewrite_tarballs(&mut packument, &self.registry_url(headers), &name);
+                let Some(version_data) = packument
+                    .get_mut("versions")
+                    .and_then(|versions| versions.get_mut(&version))
+                    .map(Value::take)
+                else {
+                    return Ok(Self::not_found(format!(
+                        "Version {} not found in project {}",
+                        version, name
+                    )));
+                };
+                Ok(ResponseBuilder::ok().json(&version_data).into())
+            }
+            NPMProxyPath::Tarball { name, file } => {
+                let path = tarball_path(&name, &file);
+                let file_headers = FileRequestHeaders::from(headers);
+                if let Some(cached) =
+                    open_file_for_request(&self.storage, self.id, &path, &file_headers).await?
+                {
+                    return Ok(RepoResponse::from(Some(cached)));
+                }
+                if !self.download_tarball(&name, &file).await? {
+                    return Ok(Self::not_found(format!("File {} not found", file)));
+                }
+                let file =
+                    open_file_for_request(&self.storage, self.id, &path, &file_headers).await?;
+                Ok(RepoResponse::from(file))
+            }
+        }
+    }
+    /// Only `npm audit` is supported. It is answered from the local advisory database instead of the upstream registries
+    async fn handle_post(
+        &self,
+        request: RepositoryRequest,
+    ) -> Result<RepoResponse, NPMRegistryError> {
+        if request.path.to_string() != BULK_ADVISORY_PATH {
+            return Ok(RepoResponse::unsupported_method_response(
+                request.parts.method,
+                self.get_type(),
+            ));
+        }
+        audit::bulk_advisories(&self.site.database, request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, extract::Path, http::HeaderMap as AxumHeaders, routing::get};
+    use serde_json::json;
+
+    use super::*;
+    fn packument() -> Value {
+        json!({
+            "name": "left-pad",
+            "versions": {
+                "1.3.0": {
+                    "name": "left-pad",
+                    "version": "1.3.0",
+                    "dist": {
+                        "tarball": "https://registry.npmjs.org/left-pad/-/left-pad-1.3.0.tgz"
+                    }
+                }
+            }
+        })
+    }
+    #[test]
+    fn parse_paths() {
+        let parse = |path: &str| NPMProxyPath::parse(&StoragePath::from(path));
+        assert_eq!(
+            parse("left-pad"),
+            Some(NPMProxyPath::Packument {
+                name: "left-pad".to_owned()
+            })
+        );
+        assert_eq!(
+            parse("@types/node/20.0.0"),
+            Some(NPMProxyPath::Version {
+                name: "@types/node".to_owned(),
+                version: "20.0.0".to_owned()
+            })
+        );
+        assert_eq!(
+            parse("@types/node/-/node-20.0.0.tgz"),
+            Some(NPMProxyPath::Tarball {
+                name: "@types/node".to_owned(),
+                file: "node-20.0.0.tgz".to_owned()
+            })
+        );
+        assert_eq!(
+            parse("@types/node/-/@types/node-20.0.0.tgz"),
+            Some(NPMProxyPath::Tarball {
+                name: "@types/node".to_owned(),
+                file: "node-20.0.0.tgz".to_owned()
+            })
+        );
+        assert_eq!(parse("left-pad/1.3.0/extra"), None);
+    }
+    #[test]
+    fn rewrite() {
+        let mut packument = packument();
+        assert_eq!(
+            upstream_tarball_url(&packument, "left-pad-1.3.0.tgz").as_deref(),
+            Some("https://registry.npmjs.org/left-pad/-/left-pad-1.3.0.tgz")
+        );
+        rewrite_tarballs(
+            &mut packument,
+            "https://repo.example.com/repositories/primary/npm",
+            "left-pad",
+        );
+        assert_eq!(
+            packument["versions"]["1.3.0"]["dist"]["tarball"],
+            "https://repo.example.com/repositories/primary/npm/left-pad/-/left-pad-1.3.0.tgz"
+        );
+    }
+    /// Starts a registry that has `left-pad` and revalidates with the etag `"v1"`
+    async fn mock_registry() -> NPMProxyRoute {
+        let router = Router::new()
+            .route(
+                "/{name}",
+                get(
+                    |Path(name): Path<String>, headers: AxumHeaders| async move {
+                        if name != "left-pad" {
+                            return (StatusCode::NOT_FOUND, AxumHeaders::new(), String::new());
+                        }
+                        if headers
+                            .get(IF_NONE_MATCH)
+                            .is_some_and(|etag| etag == "\"v1\"")
+                        {
+                            return (StatusCode::NOT_MODIFIED, AxumHeaders::new(), String::new());
+                        }
+                        let mut response_headers = AxumHeaders::new();
+                        response_headers.insert(http::header::ETAG, "\"v1\"".parse().unwrap());
+                        (StatusCode::OK, response_headers, packument().to_string())
+                    },
+                ),
+            )
+            .route(
+                "/broken/{name}",
+                get(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        let config = NPMProxyConfig {
+            routes: vec![NPMProxyRepositoryRoute {
+                url: ProxyURL::try_from(format!("http://{address}")).unwrap(),
+                name: None,
+                priority: None,
+                credentials: None,
+                http: ProxyHttpSettings::default(),
+            }],
+            cache: NPMProxyCacheConfig::default(),
+        };
+        NPMProxyRoute::from_config(config).unwrap().remove(0)
+    }
+    #[tokio::test]
+    async fn mock_registry_requests() {
+        let route = mock_registry().await;
+        let RouteResponse::Found { bytes, cache_meta } =
+            route.request_packument("left-pad", None).await.unwrap()
+        else {
+            panic!("Expected the packument");
+        };
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body, packument());
+        assert_eq!(cache_meta.etag.as_deref(), Some("\"v1\""));
+        assert!(matches!(
+            route
+                .request_packument("left-pad", Some(&cache_meta))
+                .await
+                .unwrap(),
+            RouteResponse::NotModified
+        ));
+        assert!(matches!(
+            route.request_packument("missing", None).await.unwrap(),
+            RouteResponse::Missing
+        ));
+        assert!(matches!(
+            route
+                .request_url(&route.url("broken/left-pad"))
+                .await
+                .unwrap(),
+            RouteResponse::Unavailable(StatusCode::SERVICE_UNAVAILABLE)
+        ));
+    }
+}
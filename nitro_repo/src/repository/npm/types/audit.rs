@@ -0,0 +1,146 @@
+//! Types for `npm audit`. npm posts the installed versions of every package to `-/npm/v1/security/advisories/bulk`
+//!
+//! [Documentation](https://github.com/npm/registry/blob/main/docs/audit/bulk-advisories.md)
+use ahash::HashMap;
+use nr_core::database::entities::advisory::{AffectedVersions, DBSecurityAdvisory};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+pub const BULK_ADVISORY_PATH: &str = "-/npm/v1/security/advisories/bulk";
+/// The OSV ecosystem of npm packages
+pub const NPM_ECOSYSTEM: &str = "npm";
+/// Package name to the installed versions
+pub type BulkAdvisoryRequest = HashMap<String, Vec<String>>;
+/// Package name to the advisories that affect at least one of the installed versions
+pub type BulkAdvisoryResponse = HashMap<String, Vec<BulkAdvisory>>;
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BulkAdvisory {
+    pub id: i32,
+    pub url: String,
+    pub title: String,
+    pub severity: String,
+    /// A npm semver range
+    pub vulnerable_versions: String,
+    pub cwe: Vec<String>,
+    pub cvss: BulkAdvisoryCvss,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BulkAdvisoryCvss {
+    /// Nitro Repo does not calculate the score. npm only displays it
+    pub score: f64,
+    #[serde(rename = "vectorString")]
+    pub vector_string: Option<String>,
+}
+impl From<&DBSecurityAdvisory> for BulkAdvisory {
+    fn from(advisory: &DBSecurityAdvisory) -> Self {
+        Self {
+            id: advisory.id,
+            url: advisory.url.clone().unwrap_or_default(),
+            title: advisory.title.clone(),
+            severity: advisory.severity.clone(),
+            vulnerable_versions: vulnerable_versions(&advisory.affected.0),
+            cwe: advisory.cwe.clone(),
+            cvss: BulkAdvisoryCvss {
+                score: 0.0,
+                vector_string: advisory.cvss_vector.clone(),
+            },
+        }
+    }
+}
+/// `0` is used by OSV as the start of all versions
+fn lower_bound(introduced: Option<&str>) -> Option<&str> {
+    introduced.filter(|introduced| *introduced != "0")
+}
+/// The affected versions as a npm semver range. Such as `>=1.0.0 <1.2.3 || 2.0.0`
+pub fn vulnerable_versions(affected: &AffectedVersions) -> String {
+    let mut ranges: Vec<String> = affected
+        .ranges
+        .iter()
+        .map(|range| {
+            let mut parts = Vec::new();
+            match lower_bound(range.introduced.as_deref()) {
+                Some(introduced) => parts.push(format!(">={introduced}")),
+                None => parts.push(">=0.0.0".to_owned()),
+            }
+            if let Some(fixed) = &range.fixed {
+                parts.push(format!("<{fixed}"));
+            } else if let Some(last_affected) = &range.last_affected {
+                parts.push(format!("<={last_affected}"));
+            }
+            parts.join(" ")
+        })
+        .collect();
+    ranges.extend(affected.versions.iter().cloned());
+    ranges.join(" || ")
+}
+/// Compares the version with semver. Bounds that are not valid semver never match
+pub fn is_affected(affected: &AffectedVersions, version: &Version) -> bool {
+    if affected
+        .versions
+        .iter()
+        .filter_map(|affected| Version::parse(affected).ok())
+        .any(|affected| affected == *version)
+    {
+        return true;
+    }
+    affected.ranges.iter().any(|range| {
+        let after_introduced = match lower_bound(range.introduced.as_deref()) {
+            Some(introduced) => {
+                Version::parse(introduced).is_ok_and(|introduced| *version >= introduced)
+            }
+            None => true,
+        };
+        let before_end = match (&range.fixed, &range.last_affected) {
+            (Some(fixed), _) => Version::parse(fixed).is_ok_and(|fixed| *version < fixed),
+            (None, Some(last_affected)) => {
+                Version::parse(last_affected).is_ok_and(|last_affected| *version <= last_affected)
+            }
+            (None, None) => true,
+        };
+        after_introduced && before_end
+    })
+}
+#[cfg(test)]
+mod tests {
+    use nr_core::database::entities::advisory::AffectedRange;
+
+    use super::*;
+    fn affected() -> AffectedVersions {
+        AffectedVersions {
+            ranges: vec![
+                AffectedRange {
+                    introduced: Some("0".to_owned()),
+                    fixed: Some("1.2.3".to_owned()),
+                    last_affected: None,
+                },
+                AffectedRange {
+                    introduced: Some("2.0.0".to_owned()),
+                    fixed: None,
+                    last_affected: Some("2.1.0".to_owned()),
+                },
+            ],
+            versions: vec!["3.0.0-beta.1".to_owned()],
+        }
+    }
+    #[test]
+    fn range_string() {
+        assert_eq!(
+            vulnerable_versions(&affected()),
+            ">=0.0.0 <1.2.3 || >=2.0.0 <=2.1.0 || 3.0.0-beta.1"
+        );
+    }
+    #[test]
+    fn affected_versions() {
+        let affected = affected();
+        let check = |version: &str| is_affected(&affected, &Version::parse(version).unwrap());
+        assert!(check("0.1.0"));
+        assert!(check("1.2.2"));
+        assert!(!check("1.2.3"));
+        assert!(!check("1.9.0"));
+        assert!(check("2.0.0"));
+        assert!(check("2.1.0"));
+        assert!(!check("2.1.1"));
+        assert!(check("3.0.0-beta.1"));
+        assert!(!check("3.0.0"));
+    }
+}
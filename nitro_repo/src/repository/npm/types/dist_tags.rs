@@ -0,0 +1,135 @@
+use nr_core::storage::StoragePath;
+use semver::Version;
+
+/// The tag every NPM client installs by default
+pub const LATEST_TAG: &str = "latest";
+/// Routes for managing dist-tags
+///
+/// - `-/package/{package}/dist-tags` - All tags of the package
+/// - `-/package/{package}/dist-tags/{tag}` - A single tag
+///
+/// Scoped packages are sent as `@{scope}%2f{package}`. The path is already decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistTagPath {
+    pub name: String,
+    pub tag: Option<String>,
+}
+impl DistTagPath {
+    pub fn parse(path: &StoragePath) -> Option<Self> {
+        let path = path.to_string();
+        let path = path.strip_prefix("-/package/")?;
+        let components: Vec<&str> = path
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .collect();
+        let (name, rest) = if components.first()?.starts_with('@') {
+            if components.len() < 2 {
+                return None;
+            }
+            (
+                format!("{}/{}", components[0], components[1]),
+                &components[2..],
+            )
+        } else {
+            (components[0].to_owned(), &components[1..])
+        };
+        match rest {
+            ["dist-tags"] => Some(Self { name, tag: None }),
+            ["dist-tags", tag] => Some(Self {
+                name,
+                tag: Some((*tag).to_owned()),
+            }),
+            _ => None,
+        }
+    }
+}
+/// The version `latest` points to if it was not set by hand.
+///
+/// The highest version that is not a prerelease. If every version is a prerelease the highest prerelease is used.
+/// Versions that are not valid semver are only used if no version is valid semver
+pub fn latest_version<'a>(versions: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let mut latest_release: Option<(Version, &str)> = None;
+    let mut latest_prerelease: Option<(Version, &str)> = None;
+    let mut first_invalid = None;
+    for version in versions {
+        let Ok(parsed) = Version::parse(version) else {
+            first_invalid = first_invalid.or(Some(version));
+            continue;
+        };
+        let latest = if parsed.pre.is_empty() {
+            &mut latest_release
+        } else {
+            &mut latest_prerelease
+        };
+        if latest.as_ref().is_none_or(|(current, _)| parsed > *current) {
+            *latest = Some((parsed, version));
+        }
+    }
+    latest_release
+        .or(latest_prerelease)
+        .map(|(_, version)| version)
+        .or(first_invalid)
+}
+/// If a publish that sent the `latest` tag moves `latest` to the published version.
+///
+/// `npm publish` sends `latest` unless `--tag` is passed. So it is only honored if the published version is the [latest_version] of `versions`.
+/// `versions` includes the published version
+pub fn publish_moves_latest<'a>(
+    published: &str,
+    versions: impl IntoIterator<Item = &'a str>,
+) -> bool {
+    latest_version(versions) == Some(published)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse_paths() {
+        assert_eq!(
+            DistTagPath::parse(&StoragePath::from("-/package/mylib/dist-tags")),
+            Some(DistTagPath {
+                name: "mylib".to_owned(),
+                tag: None
+            })
+        );
+        assert_eq!(
+            DistTagPath::parse(&StoragePath::from("-/package/@nr/mylib/dist-tags/beta")),
+            Some(DistTagPath {
+                name: "@nr/mylib".to_owned(),
+                tag: Some("beta".to_owned())
+            })
+        );
+        assert_eq!(DistTagPath::parse(&StoragePath::from("mylib/1.0.0")), None);
+        assert_eq!(
+            DistTagPath::parse(&StoragePath::from("-/package/mylib/access")),
+            None
+        );
+    }
+    #[test]
+    fn latest() {
+        assert_eq!(
+            latest_version(["1.0.0", "2.0.0-beta.1", "1.10.0", "1.2.0"]),
+            Some("1.10.0")
+        );
+        assert_eq!(
+            latest_version(["1.0.0-alpha", "1.0.0-beta"]),
+            Some("1.0.0-beta")
+        );
+        assert_eq!(latest_version(["not-semver", "1.0.0"]), Some("1.0.0"));
+        assert_eq!(latest_version(["not-semver"]), Some("not-semver"));
+        assert_eq!(latest_version([]), None);
+    }
+    #[test]
+    fn publishing_lower_versions_keeps_latest() {
+        let mut versions = Vec::new();
+        let mut latest = None;
+        for published in ["2.0.0", "1.2.9", "3.0.0-beta.1"] {
+            versions.push(published);
+            if publish_moves_latest(published, versions.iter().copied()) {
+                latest = Some(published);
+            }
+        }
+        assert_eq!(latest, Some("2.0.0"));
+        assert_eq!(latest_version(versions), Some("2.0.0"));
+    }
+}
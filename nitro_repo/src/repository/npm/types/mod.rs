@@ -1,4 +1,8 @@
+pub mod audit;
+pub mod dist_tags;
 pub mod request;
+pub mod search;
+pub mod tokens;
 
 use ahash::HashMap;
 use chrono::{DateTime, FixedOffset};
@@ -26,6 +30,9 @@ pub struct RegistryResponse {
 pub struct NpmRegistryPackageResponse {
     #[serde(rename = "_id")]
     pub id: String,
+    /// npm sends the revision back when unpublishing
+    #[serde(rename = "_rev")]
+    pub rev: String,
     pub name: String,
     pub description: Option<String>,
     #[serde(rename = "dist-tags")]
@@ -33,6 +40,21 @@ pub struct NpmRegistryPackageResponse {
     pub versions: HashMap<String, PublishVersion>,
     pub time: HashMap<String, String>,
 }
+/// The packument npm sends back with `npm unpublish` and `npm deprecate`.
+///
+/// Only the parts that are used are parsed
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackumentUpdate {
+    pub name: String,
+    #[serde(default)]
+    pub versions: HashMap<String, PackumentVersionUpdate>,
+}
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackumentVersionUpdate {
+    /// An empty message removes the deprecation
+    #[serde(default)]
+    pub deprecated: Option<String>,
+}
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NPMRegistryPackageTime {
     pub created: String,
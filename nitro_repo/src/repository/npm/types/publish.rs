@@ -49,6 +49,9 @@ impl PublishAttachment {
 pub struct PublishRequest {
     pub name: String,
     pub versions: HashMap<String, PublishVersion>,
+    /// The tags to point at the published version. `npm publish --tag` sets this
+    #[serde(default, rename = "dist-tags")]
+    pub dist_tags: HashMap<String, String>,
     #[serde(flatten)]
     pub other: HashMap<String, Value>,
     #[serde(rename = "_attachments")]
@@ -16,12 +16,16 @@ use uuid::Uuid;
 
 use crate::repository::{maven::get_release_type, npm::NPMRegistryError};
 
-use super::NPMPackageName;
+use super::{NPMPackageName, search::SEARCH_PATH};
 
 #[derive(Debug, Display, EnumString)]
 pub enum NPMCommand {
     #[strum(serialize = "publish")]
     Publish,
+    #[strum(serialize = "unpublish")]
+    Unpublish,
+    #[strum(serialize = "deprecate")]
+    Deprecate,
 }
 impl TryFrom<&HeaderValue> for NPMCommand {
     type Error = InvalidNPMCommand;
@@ -65,6 +69,9 @@ pub struct PublishVersion {
     pub secret_node_version: String,
     #[serde(rename = "_npmVersion")]
     pub hidden_npm_version: String,
+    /// Set by `npm deprecate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
 }
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct PublishDist {
@@ -247,6 +254,9 @@ impl TryFrom<StoragePath> for GetPath {
 
     fn try_from(value: StoragePath) -> Result<Self, Self::Error> {
         let as_string = value.to_string();
+        if as_string.trim_end_matches('/') == SEARCH_PATH {
+            return Ok(GetPath::Search);
+        }
         let components: Vec<_> = value.into();
         if as_string.starts_with('@') {
             GetPath::scoped_package_call(components)
@@ -255,6 +265,46 @@ impl TryFrom<StoragePath> for GetPath {
         }
     }
 }
+/// Paths used by `npm unpublish`. The revision is ignored
+///
+/// - `{package}/-rev/{rev}` - Unpublish the package. PUT with the versions to keep or DELETE for everything
+/// - `{package}/-/{file}/-rev/{rev}` - Delete a tarball
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevisionPath {
+    Package { name: String },
+    Tarball { name: String, file: String },
+}
+impl RevisionPath {
+    /// npm builds the tarball path from the tarball URL. So it may start with `repositories/{storage}/{repository}`
+    pub fn parse(path: &StoragePath) -> Option<Self> {
+        let path = path.to_string();
+        let (path, _rev) = path.split_once("/-rev/")?;
+        let components: Vec<&str> = path
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .collect();
+        match components.iter().position(|component| *component == "-") {
+            Some(index) => {
+                let file = components[index + 1..].last()?;
+                Some(RevisionPath::Tarball {
+                    name: package_name_from_end(&components[..index])?,
+                    file: (*file).to_owned(),
+                })
+            }
+            None => Some(RevisionPath::Package {
+                name: package_name_from_end(&components)?,
+            }),
+        }
+    }
+}
+/// The package name at the end of the components. Scoped packages take two components
+fn package_name_from_end(components: &[&str]) -> Option<String> {
+    let name = components.last()?;
+    if components.len() >= 2 && components[components.len() - 2].starts_with('@') {
+        return Some(format!("{}/{}", components[components.len() - 2], name));
+    }
+    Some((*name).to_owned())
+}
 pub fn extract_version_from_file(file: &str) -> Option<String> {
     let parts: Vec<_> = file.split('-').collect();
     if let Some(version) = parts.last() {
@@ -268,7 +318,42 @@ pub fn extract_version_from_file(file: &str) -> Option<String> {
 pub mod tests {
     use nr_core::storage::StoragePath;
 
-    use super::GetPath;
+    use super::{GetPath, RevisionPath};
+    #[test]
+    pub fn revision_paths() {
+        let tests = vec![
+            (
+                "mylib/-rev/1-abc",
+                Some(RevisionPath::Package {
+                    name: "mylib".to_string(),
+                }),
+            ),
+            (
+                "@nr/mylib/-rev/undefined",
+                Some(RevisionPath::Package {
+                    name: "@nr/mylib".to_string(),
+                }),
+            ),
+            (
+                "repositories/storage/npm/@nr/mylib/-/@nr/mylib-1.0.0.tgz/-rev/1-abc",
+                Some(RevisionPath::Tarball {
+                    name: "@nr/mylib".to_string(),
+                    file: "mylib-1.0.0.tgz".to_string(),
+                }),
+            ),
+            (
+                "mylib/-/mylib-1.0.0.tgz/-rev/1-abc",
+                Some(RevisionPath::Tarball {
+                    name: "mylib".to_string(),
+                    file: "mylib-1.0.0.tgz".to_string(),
+                }),
+            ),
+            ("mylib/1.0.0", None),
+        ];
+        for (path, expected) in tests {
+            assert_eq!(RevisionPath::parse(&StoragePath::from(path)), expected);
+        }
+    }
     #[test]
     pub fn tests() {
         let tests = vec![
@@ -309,6 +394,7 @@ pub mod tests {
                     file: "npm-check-updates-11.0.3.tgz".to_string(),
                 },
             ),
+            (StoragePath::from("-/v1/search"), GetPath::Search),
         ];
         for (path, expected) in tests {
             let get_path = GetPath::try_from(path).unwrap();
@@ -0,0 +1,241 @@
+//! Types for the v1 search API. `npm search` calls `-/v1/search?text={text}&size={size}&from={from}`
+//!
+//! [Documentation](https://github.com/npm/registry/blob/main/docs/REGISTRY-API.md#get-v1search)
+use ahash::HashMap;
+use nr_core::database::entities::project::search::ProjectSearch;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::request::PublishVersion;
+
+pub const SEARCH_PATH: &str = "-/v1/search";
+pub const DEFAULT_SEARCH_SIZE: usize = 20;
+/// The same limit as the public NPM Registry
+pub const MAX_SEARCH_SIZE: usize = 250;
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    pub text: String,
+    pub size: Option<usize>,
+    pub from: Option<usize>,
+}
+impl SearchQuery {
+    pub fn size(&self) -> usize {
+        self.size
+            .unwrap_or(DEFAULT_SEARCH_SIZE)
+            .clamp(1, MAX_SEARCH_SIZE)
+    }
+    /// The number of matches to skip. Sent as `from`
+    pub fn offset(&self) -> usize {
+        self.from.unwrap_or_default()
+    }
+}
+/// The parsed `text` of a search.
+///
+/// Supports the `keywords:{a},{b}` and `scope:{scope}` qualifiers. Other qualifiers are ignored.
+/// The terms and qualifiers are lowercase
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchText {
+    pub terms: Vec<String>,
+    pub keywords: Vec<String>,
+    pub scope: Option<String>,
+}
+impl SearchText {
+    pub fn parse(text: &str) -> Self {
+        let mut search = SearchText::default();
+        for part in text.split_whitespace() {
+            let part = part.to_lowercase();
+            match part.split_once(':') {
+                Some(("keywords", keywords)) => search.keywords.extend(
+                    keywords
+                        .split(',')
+                        .filter(|keyword| !keyword.is_empty())
+                        .map(ToOwned::to_owned),
+                ),
+                Some(("scope", scope)) => {
+                    search.scope = Some(scope.trim_start_matches('@').to_owned());
+                }
+                Some((_, _)) => {}
+                None => search.terms.push(part),
+            }
+        }
+        search
+    }
+    /// The search that is run against the database
+    pub fn into_project_search(self, query: &SearchQuery) -> ProjectSearch {
+        ProjectSearch {
+            terms: self.terms,
+            keywords: self.keywords,
+            scope: self.scope,
+            limit: query.size() as i64,
+            offset: query.offset() as i64,
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResponse {
+    pub objects: Vec<SearchObject>,
+    pub total: usize,
+    pub time: String,
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchObject {
+    pub package: SearchPackage,
+    pub score: SearchScore,
+    #[serde(rename = "searchScore")]
+    pub search_score: f64,
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchPackage {
+    pub name: String,
+    /// `unscoped` for packages without a scope
+    pub scope: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub keywords: Vec<String>,
+    pub date: String,
+    pub links: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<SearchUser>,
+    pub maintainers: Vec<SearchUser>,
+}
+impl SearchPackage {
+    /// Builds the package from the version `latest` points to
+    pub fn new(version: &PublishVersion, date: String) -> Self {
+        let name = version.name.to_string();
+        let scope = version
+            .name
+            .scope
+            .clone()
+            .unwrap_or_else(|| "unscoped".to_owned());
+        Self {
+            scope,
+            version: version.version.clone(),
+            description: version.description().map(ToOwned::to_owned),
+            keywords: version.keywords(),
+            date,
+            links: version.links(),
+            author: version.extra.get("author").cloned(),
+            publisher: None,
+            maintainers: Vec::new(),
+            name,
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchUser {
+    pub username: String,
+    pub email: String,
+}
+/// Nitro Repo does not track the quality, popularity or maintenance of packages.
+///
+/// The final score is the text relevance. Deprecated packages have no maintenance
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchScore {
+    #[serde(rename = "final")]
+    pub final_score: f64,
+    pub detail: SearchScoreDetail,
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchScoreDetail {
+    pub quality: f64,
+    pub popularity: f64,
+    pub maintenance: f64,
+}
+impl SearchScore {
+    pub fn new(relevance: f64, deprecated: bool) -> Self {
+        Self {
+            final_score: relevance,
+            detail: SearchScoreDetail {
+                quality: 1.0,
+                popularity: 0.0,
+                maintenance: if deprecated { 0.0 } else { 1.0 },
+            },
+        }
+    }
+}
+impl PublishVersion {
+    pub fn description(&self) -> Option<&str> {
+        self.extra.get("description").and_then(Value::as_str)
+    }
+    /// `keywords` is an array in the package.json. Some old packages use a comma separated string
+    pub fn keywords(&self) -> Vec<String> {
+        match self.extra.get("keywords") {
+            Some(Value::Array(keywords)) => keywords
+                .iter()
+                .filter_map(Value::as_str)
+                .map(ToOwned::to_owned)
+                .collect(),
+            Some(Value::String(keywords)) => keywords
+                .split(',')
+                .map(str::trim)
+                .filter(|keyword| !keyword.is_empty())
+                .map(ToOwned::to_owned)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+    /// The `homepage`, `repository` and `bugs` links. These can be a string or an object with a url
+    pub fn links(&self) -> HashMap<String, String> {
+        let mut links = HashMap::default();
+        for key in ["homepage", "repository", "bugs"] {
+            let url = match self.extra.get(key) {
+                Some(Value::String(url)) => Some(url.clone()),
+                Some(Value::Object(object)) => object
+                    .get("url")
+                    .and_then(Value::as_str)
+                    .map(ToOwned::to_owned),
+                _ => None,
+            };
+            if let Some(url) = url {
+                links.insert(key.to_owned(), url);
+            }
+        }
+        links
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse_text() {
+        assert_eq!(
+            SearchText::parse("Left Pad keywords:string,util scope:@nr author:someone"),
+            SearchText {
+                terms: vec!["left".to_owned(), "pad".to_owned()],
+                keywords: vec!["string".to_owned(), "util".to_owned()],
+                scope: Some("nr".to_owned()),
+            }
+        );
+    }
+    #[test]
+    fn project_search() {
+        let query = SearchQuery {
+            text: "pad keywords:string".to_owned(),
+            size: Some(10),
+            from: Some(30),
+        };
+        assert_eq!(
+            SearchText::parse(&query.text).into_project_search(&query),
+            ProjectSearch {
+                terms: vec!["pad".to_owned()],
+                keywords: vec!["string".to_owned()],
+                scope: None,
+                limit: 10,
+                offset: 30,
+            }
+        );
+    }
+    #[test]
+    fn size_is_limited() {
+        let query = SearchQuery {
+            size: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(query.size(), MAX_SEARCH_SIZE);
+        assert_eq!(SearchQuery::default().size(), DEFAULT_SEARCH_SIZE);
+    }
+}
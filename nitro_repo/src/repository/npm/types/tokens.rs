@@ -0,0 +1,186 @@
+//! Types for `npm whoami` and `npm token list|create|revoke`
+//!
+//! [Documentation](https://github.com/npm/npm-profile)
+use chrono::Local;
+use nr_core::{
+    database::entities::user::auth_token::{AuthToken, RepositoryAuthToken, hash_token},
+    user::permissions::RepositoryActions,
+    utils::{
+        base64_utils,
+        cidr::{Cidr, InvalidCidr},
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::repository::npm::utils::npm_time;
+
+pub const WHOAMI_PATH: &str = "-/whoami";
+pub const TOKENS_PATH: &str = "-/npm/v1/tokens";
+/// Followed by the key of the token
+pub const REVOKE_TOKEN_PATH: &str = "-/npm/v1/tokens/token/";
+/// The plain token is only known when it is created
+pub const REDACTED_TOKEN: &str = "********";
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WhoamiResponse {
+    pub username: String,
+}
+/// The body of `npm token create`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CreateTokenRequest {
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub readonly: bool,
+    /// npm sends null if no ranges were given
+    #[serde(default)]
+    pub cidr_whitelist: Option<Vec<String>>,
+}
+impl CreateTokenRequest {
+    /// Parses the CIDR whitelist. Ranges are stored normalized
+    pub fn cidr_whitelist(&self) -> Result<Vec<String>, InvalidCidr> {
+        self.cidr_whitelist
+            .iter()
+            .flatten()
+            .map(|cidr| cidr.parse::<Cidr>().map(|cidr| cidr.to_string()))
+            .collect()
+    }
+    pub fn actions(&self) -> Vec<RepositoryActions> {
+        if self.readonly {
+            vec![RepositoryActions::Read]
+        } else {
+            RepositoryActions::all()
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NpmToken {
+    /// The plain token on creation. Otherwise [REDACTED_TOKEN]
+    pub token: String,
+    /// Identifies the token when revoking it
+    pub key: String,
+    pub cidr_whitelist: Option<Vec<String>>,
+    pub readonly: bool,
+    pub created: String,
+    pub updated: String,
+}
+impl NpmToken {
+    pub fn new(token: &AuthToken, actions: &[RepositoryActions]) -> Self {
+        let created = npm_time::format_date_time(&token.created_at);
+        Self {
+            token: REDACTED_TOKEN.to_owned(),
+            key: token_key(&token.token),
+            cidr_whitelist: optional_whitelist(token.cidr_whitelist.clone()),
+            readonly: !actions.contains(&RepositoryActions::Write),
+            updated: created.clone(),
+            created,
+        }
+    }
+    /// The response to `npm token create`. The only time the plain token is sent
+    pub fn created(token: String, cidr_whitelist: Vec<String>, readonly: bool) -> Self {
+        let created = npm_time::format_date_time(&Local::now().fixed_offset());
+        Self {
+            key: token_key(&hash_token(&token)),
+            token,
+            cidr_whitelist: optional_whitelist(cidr_whitelist),
+            readonly,
+            updated: created.clone(),
+            created,
+        }
+    }
+}
+/// npm expects null if the token can be used from anywhere
+fn optional_whitelist(cidr_whitelist: Vec<String>) -> Option<Vec<String>> {
+    if cidr_whitelist.is_empty() {
+        None
+    } else {
+        Some(cidr_whitelist)
+    }
+}
+impl From<&RepositoryAuthToken> for NpmToken {
+    fn from(value: &RepositoryAuthToken) -> Self {
+        NpmToken::new(&value.token, &value.actions)
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NpmTokenList {
+    pub objects: Vec<NpmToken>,
+    pub total: usize,
+    /// npm follows `urls.next` until it is missing. All tokens are returned at once
+    pub urls: Value,
+}
+impl From<Vec<NpmToken>> for NpmTokenList {
+    fn from(objects: Vec<NpmToken>) -> Self {
+        Self {
+            total: objects.len(),
+            objects,
+            urls: Value::Object(Default::default()),
+        }
+    }
+}
+/// The hashed token as hex so it can be used in a url.
+///
+/// npm shows the start of the key as the id of the token
+pub fn token_key(hashed_token: &str) -> String {
+    let bytes = base64_utils::decode(hashed_token).unwrap_or_else(|_| hashed_token.into());
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+/// npm sends the key of the token or the plain token if it was given one that it does not list
+pub fn matches_token(token: &AuthToken, key_or_token: &str) -> bool {
+    if key_or_token.is_empty() {
+        return false;
+    }
+    token_key(&token.token).starts_with(key_or_token) || hash_token(key_or_token) == token.token
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn token(plain: &str) -> AuthToken {
+        AuthToken {
+            id: 1,
+            user_id: 1,
+            name: None,
+            description: None,
+            token: hash_token(plain),
+            active: true,
+            source: "test".to_owned(),
+            expires_at: None,
+            last_used_at: None,
+            last_used_ip: None,
+            cidr_whitelist: vec![],
+            created_at: chrono::Local::now().fixed_offset(),
+        }
+    }
+    #[test]
+    fn key_matching() {
+        let token = token("my-token");
+        let key = token_key(&token.token);
+        assert_eq!(key.len(), 64);
+        assert!(key.chars().all(|c| c.is_ascii_hexdigit()));
+        assert!(matches_token(&token, &key));
+        assert!(matches_token(&token, &key[..7]));
+        assert!(matches_token(&token, "my-token"));
+        assert!(!matches_token(&token, "other-token"));
+        assert!(!matches_token(&token, ""));
+    }
+    #[test]
+    fn create_request() {
+        let request: CreateTokenRequest = serde_json::from_str(
+            r#"{"password":"secret","readonly":true,"cidr_whitelist":["10.0.0.0/8","192.168.1.1"]}"#,
+        )
+        .unwrap();
+        assert_eq!(request.actions(), vec![RepositoryActions::Read]);
+        assert_eq!(
+            request.cidr_whitelist().unwrap(),
+            vec!["10.0.0.0/8".to_owned(), "192.168.1.1/32".to_owned()]
+        );
+        let request: CreateTokenRequest =
+            serde_json::from_str(r#"{"password":"secret","cidr_whitelist":null}"#).unwrap();
+        assert_eq!(request.actions(), RepositoryActions::all());
+        assert!(request.cidr_whitelist().unwrap().is_empty());
+        let request: CreateTokenRequest =
+            serde_json::from_str(r#"{"password":"secret","cidr_whitelist":["not-an-ip"]}"#)
+                .unwrap();
+        assert!(request.cidr_whitelist().is_err());
+    }
+}
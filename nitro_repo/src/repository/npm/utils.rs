@@ -1,12 +1,25 @@
+use ahash::{HashMap, HashMapExt};
+use http::{HeaderMap, header::HOST};
 use nr_core::{
-    database::entities::project::{DBProject, ProjectDBType, versions::DBProjectVersion},
+    database::entities::project::{
+        DBProject, ProjectDBType, dist_tags::DBProjectDistTag, versions::DBProjectVersion,
+    },
     storage::StoragePath,
 };
 use tracing::{info, instrument};
+use uuid::Uuid;
+
+use nr_storage::Storage;
 
 use crate::repository::Repository;
 
-use super::{NPMRegistryError, types::request::PublishVersion};
+use super::{
+    NPMRegistryError,
+    types::{
+        dist_tags::{LATEST_TAG, latest_version},
+        request::PublishVersion,
+    },
+};
 
 pub mod npm_time {
     use chrono::{DateTime, FixedOffset};
@@ -15,7 +28,38 @@ pub mod npm_time {
         date_time.format("%Y-%m-%dT%H:%M:%S.%3fZ").to_string()
     }
 }
+/// npm requires a revision to unpublish. The last time the project changed is used
+pub fn npm_revision(updated_at: &chrono::DateTime<chrono::FixedOffset>) -> String {
+    format!("1-{}", updated_at.timestamp_millis())
+}
 pub trait NpmRegistryExt: Repository {
+    /// The URL of the site. Uses the configured app url. Otherwise the host of the request
+    fn site_url(&self, headers: &HeaderMap) -> String {
+        let site = self.site();
+        let (app_url, is_https) = {
+            let instance = site.instance.lock();
+            (instance.app_url.clone(), instance.is_https)
+        };
+        if !app_url.is_empty() {
+            return app_url.trim_end_matches('/').to_owned();
+        }
+        let host = headers
+            .get(HOST)
+            .and_then(|host| host.to_str().ok())
+            .unwrap_or("localhost");
+        let scheme = if is_https { "https" } else { "http" };
+        format!("{scheme}://{host}")
+    }
+    /// The URL npm uses for this registry. `{site}/repositories/{storage}/{repository}`
+    fn registry_url(&self, headers: &HeaderMap) -> String {
+        let storage = self.get_storage();
+        format!(
+            "{}/repositories/{}/{}",
+            self.site_url(headers),
+            storage.storage_config().storage_config.storage_name,
+            self.name()
+        )
+    }
     #[instrument]
     async fn get_or_create_project(
         &self,
@@ -44,6 +88,7 @@ pub trait NpmRegistryExt: Repository {
             }
         }
     }
+    /// Returns the id of the version
     #[instrument]
     async fn create_or_update_version(
         &self,
@@ -51,7 +96,7 @@ pub trait NpmRegistryExt: Repository {
         save_path: &StoragePath,
         project: &DBProject,
         release: &PublishVersion,
-    ) -> Result<(), NPMRegistryError> {
+    ) -> Result<Uuid, NPMRegistryError> {
         if let Some(version) = DBProjectVersion::find_by_version_and_project(
             &release.version,
             project.id,
@@ -59,17 +104,44 @@ pub trait NpmRegistryExt: Repository {
         )
         .await?
         {
-            return Ok(());
+            return Ok(version.id);
         }
 
         match release.new_version(project.id, save_path.to_string(), publisher) {
             Ok(ok) => {
-                ok.insert(&self.site().database).await?;
-                return Ok(());
+                let version = ok.insert(&self.site().database).await?;
+                return Ok(version.id);
             }
             Err(err) => {
                 return Err(err);
             }
         }
     }
+    /// The dist-tags of the project.
+    ///
+    /// `latest` is the highest release that is not yanked unless it was set by hand
+    #[instrument(skip(versions))]
+    async fn dist_tags(
+        &self,
+        project_id: Uuid,
+        versions: &[DBProjectVersion],
+    ) -> Result<HashMap<String, String>, NPMRegistryError> {
+        let site = self.site();
+        let mut dist_tags = HashMap::new();
+        for tag in DBProjectDistTag::find_by_project(project_id, &site.database).await? {
+            dist_tags.insert(tag.tag, tag.version);
+        }
+        if !dist_tags.contains_key(LATEST_TAG) {
+            let latest = latest_version(
+                versions
+                    .iter()
+                    .filter(|version| version.yanked_at.is_none())
+                    .map(|version| version.version.as_str()),
+            );
+            if let Some(latest) = latest {
+                dist_tags.insert(LATEST_TAG.to_owned(), latest.to_owned());
+            }
+        }
+        Ok(dist_tags)
+    }
 }
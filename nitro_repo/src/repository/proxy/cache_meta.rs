@@ -0,0 +1,110 @@
+//! The cache information stored next to files downloaded from an upstream repository.
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, Local};
+use http::HeaderMap;
+use nr_storage::meta::RepositoryMeta;
+
+/// The ETag the upstream returned for the cached file
+pub static PROXY_ETAG_KEY: &str = "proxy_etag";
+/// The Last-Modified header the upstream returned for the cached file
+pub static PROXY_LAST_MODIFIED_KEY: &str = "proxy_last_modified";
+/// The last time the cached file was downloaded or revalidated against the upstream
+pub static PROXY_CHECKED_AT_KEY: &str = "proxy_checked_at";
+/// The cache information of a proxied file. Stored in [RepositoryMeta::extra_meta]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxyCacheMeta {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub checked_at: Option<DateTime<FixedOffset>>,
+}
+impl ProxyCacheMeta {
+    /// Reads the validators from the response of the upstream
+    pub fn from_response_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned)
+        };
+        Self {
+            etag: header(http::header::ETAG),
+            last_modified: header(http::header::LAST_MODIFIED),
+            checked_at: Some(Local::now().fixed_offset()),
+        }
+    }
+    /// If the file has not been checked within the max age.
+    ///
+    /// `fallback` is used when the file was cached before the checked at time was recorded
+    pub fn is_expired(&self, max_age: Option<Duration>, fallback: DateTime<FixedOffset>) -> bool {
+        let Some(max_age) = max_age else {
+            return false;
+        };
+        let checked_at = self.checked_at.unwrap_or(fallback);
+        let age = Local::now().fixed_offset() - checked_at;
+        age.to_std().is_ok_and(|age| age > max_age)
+    }
+    pub fn mark_checked(&mut self) {
+        self.checked_at = Some(Local::now().fixed_offset());
+    }
+    /// Writes the cache information into the meta. Other values are kept
+    pub fn apply_to(&self, meta: &mut RepositoryMeta) {
+        let mut set = |key: &str, value: Option<String>| match value {
+            Some(value) => meta.insert(key, value),
+            None => {
+                meta.extra_meta.remove(key);
+            }
+        };
+        set(PROXY_ETAG_KEY, self.etag.clone());
+        set(PROXY_LAST_MODIFIED_KEY, self.last_modified.clone());
+        set(
+            PROXY_CHECKED_AT_KEY,
+            self.checked_at.map(|checked_at| checked_at.to_rfc3339()),
+        );
+    }
+}
+impl From<&RepositoryMeta> for ProxyCacheMeta {
+    fn from(meta: &RepositoryMeta) -> Self {
+        Self {
+            etag: meta.get(PROXY_ETAG_KEY).map(ToOwned::to_owned),
+            last_modified: meta.get(PROXY_LAST_MODIFIED_KEY).map(ToOwned::to_owned),
+            checked_at: meta
+                .get(PROXY_CHECKED_AT_KEY)
+                .and_then(|value| DateTime::parse_from_rfc3339(value).ok()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn meta_roundtrip() {
+        let cache_meta = ProxyCacheMeta {
+            etag: Some("\"abc\"".to_owned()),
+            last_modified: None,
+            checked_at: Some(Local::now().fixed_offset()),
+        };
+        let mut meta = RepositoryMeta::default();
+        meta.insert(PROXY_LAST_MODIFIED_KEY, "old");
+        cache_meta.apply_to(&mut meta);
+        assert!(!meta.has_key(PROXY_LAST_MODIFIED_KEY));
+        let from_meta = ProxyCacheMeta::from(&meta);
+        assert_eq!(from_meta.etag, cache_meta.etag);
+        assert_eq!(
+            from_meta.checked_at.map(|date| date.timestamp()),
+            cache_meta.checked_at.map(|date| date.timestamp())
+        );
+    }
+    #[test]
+    fn expiry() {
+        let old = Local::now().fixed_offset() - chrono::Duration::hours(2);
+        let meta = ProxyCacheMeta {
+            checked_at: Some(old),
+            ..Default::default()
+        };
+        assert!(meta.is_expired(Some(Duration::from_secs(60)), old));
+        assert!(!meta.is_expired(Some(Duration::from_secs(3 * 60 * 60)), old));
+        assert!(!meta.is_expired(None, old));
+    }
+}
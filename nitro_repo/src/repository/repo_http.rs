@@ -26,17 +26,27 @@ use bytes::Bytes;
 use derive_more::From;
 use http::{
     HeaderValue, Method, StatusCode,
-    header::{CONTENT_LENGTH, CONTENT_LOCATION, CONTENT_TYPE, ETAG, LAST_MODIFIED, USER_AGENT},
+    header::{
+        ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_LOCATION, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+        LAST_MODIFIED, USER_AGENT,
+    },
     request::Parts,
 };
 use http_body_util::BodyExt;
 use nr_core::storage::{InvalidStoragePath, StoragePath};
-use nr_storage::{FileFileType, FileType, StorageFile, StorageFileMeta, StorageFileReader};
+use nr_storage::{
+    FileFileType, FileRange, FileType, StorageFile, StorageFileMeta, StorageFileReader,
+};
 
 use serde::Deserialize;
-use tracing::{Level, Span, debug, debug_span, error, event, instrument};
+use tower_http::decompression::RequestDecompressionLayer;
+use tracing::{Level, Span, debug, debug_span, error, event, instrument, warn};
+mod directory_listing;
+mod file_request;
 mod header;
 mod repo_auth;
+pub use directory_listing::*;
+pub use file_request::*;
 pub use header::*;
 pub use repo_auth::*;
 
@@ -45,6 +55,8 @@ pub fn repository_router() -> axum::Router<NitroRepo> {
     Router::new()
         .route("/{storage}/{repository}/{*path}", any(handle_repo_request))
         .route_with_tsr("/{storage}/{repository}", any(handle_repo_request))
+        // npm gzips the body of `npm audit`
+        .layer(RequestDecompressionLayer::new())
 }
 
 #[derive(Debug, From)]
@@ -139,18 +151,28 @@ impl IntoResponse for RepositoryRequestError {
 fn response_file(
     meta: StorageFileMeta<FileFileType>,
     content: StorageFileReader,
+    range: Option<FileRange>,
 ) -> Response<Body> {
     let last_modified = date_time_for_header(meta.modified());
-    // TODO: Handle cache control headers
     let FileFileType {
         file_size,
         mime_type,
         file_hash,
     } = meta.file_type();
     let mut response = Response::builder()
-        .status(StatusCode::OK)
-        .header(CONTENT_LENGTH, file_size.to_string())
-        .header(LAST_MODIFIED, last_modified);
+        .header(LAST_MODIFIED, last_modified)
+        .header(ACCEPT_RANGES, "bytes");
+    let content_length = if let Some(range) = range {
+        response = response.status(StatusCode::PARTIAL_CONTENT).header(
+            CONTENT_RANGE,
+            format!("bytes {}-{}/{}", range.start, range.end, file_size),
+        );
+        range.len()
+    } else {
+        response = response.status(StatusCode::OK);
+        *file_size
+    };
+    response = response.header(CONTENT_LENGTH, content_length.to_string());
 
     if let Some(etag) = &file_hash.sha2_256 {
         response = response.header(ETAG, etag);
@@ -159,7 +181,7 @@ fn response_file(
         response = response.header(CONTENT_TYPE, mime_type.to_string());
     }
 
-    let Ok(file_size) = (*file_size).try_into() else {
+    let Ok(content_length) = content_length.try_into() else {
         // So my guess. This software is running on a 32-bit system.
         // A. Why are you still on a 32-bit system?
         // B. How do you have a 4GB file hosted on a 32-bit system?
@@ -170,9 +192,43 @@ fn response_file(
             .into_response();
     };
 
-    let body = Body::new(content.into_body(file_size));
+    let body = Body::new(content.into_body(content_length));
     response.body(body).unwrap()
 }
+fn response_opened_file(file: OpenedFile) -> Response<Body> {
+    match file {
+        OpenedFile::Full(file) => RepoResponse::from(file).into_response_default(),
+        OpenedFile::Partial {
+            range,
+            file: StorageFile::File { meta, content },
+        } => response_file(meta, content, Some(range)),
+        OpenedFile::Partial { range, .. } => {
+            // The path was replaced with a directory after the range was resolved
+            warn!(?range, "Range request resolved to a directory");
+            Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .body(Body::empty())
+                .unwrap()
+        }
+        OpenedFile::NotModified(meta) => {
+            let mut response = Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(LAST_MODIFIED, date_time_for_header(meta.modified()))
+                .header(ACCEPT_RANGES, "bytes");
+            if let FileType::File(FileFileType { file_hash, .. }) = meta.file_type() {
+                if let Some(etag) = &file_hash.sha2_256 {
+                    response = response.header(ETAG, etag);
+                }
+            }
+            response.body(Body::empty()).unwrap()
+        }
+        OpenedFile::RangeNotSatisfiable { file_size } => Response::builder()
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(CONTENT_RANGE, format!("bytes */{}", file_size))
+            .body(Body::empty())
+            .unwrap(),
+    }
+}
 
 #[derive(Debug, From)]
 pub enum RepoResponse {
@@ -186,16 +242,41 @@ impl From<StorageFileMeta<FileType>> for RepoResponse {
     }
 }
 impl RepoResponse {
+    /// Converts the response. Directories are rendered as a listing in the format requested by the client
+    pub fn into_response(
+        self,
+        pages: &RepositoryPages,
+        request: &DirectoryListingRequest,
+    ) -> Response {
+        match self {
+            Self::FileResponse(file) => match *file {
+                StorageFile::Directory { meta, files } => {
+                    let listing = DirectoryListing::new(&request.request_path, &meta, &files);
+                    pages.directory_listing_response(&listing, request.format)
+                }
+                file => RepoResponse::from(file).into_response_default(),
+            },
+            other => other.into_response_default(),
+        }
+    }
     /// Default Response Format
+    ///
+    /// # Note
+    /// Directories can not be rendered without the request. Use [RepoResponse::into_response]
     pub fn into_response_default(self) -> Response {
         match self {
             Self::FileResponse(file) => match *file {
-                StorageFile::Directory { meta, files } => Response::builder()
-                    .status(StatusCode::NOT_IMPLEMENTED)
-                    .header(CONTENT_TYPE, mime::TEXT_HTML.to_string())
-                    .body(Body::from("Build HTML Page listing"))
-                    .unwrap(),
-                StorageFile::File { meta, content } => response_file(meta, content),
+                StorageFile::Directory { meta, .. } => {
+                    error!(
+                        ?meta,
+                        "Directory response was converted without the request"
+                    );
+                    Response::builder()
+                        .status(StatusCode::INTERNAL_SERVER_ERROR)
+                        .body(Body::from("Unable to render directory listing"))
+                        .unwrap()
+                }
+                StorageFile::File { meta, content } => response_file(meta, content, None),
             },
             Self::FileMetaResponse(meta) => {
                 let last_modified = date_time_for_header(meta.modified());
@@ -217,7 +298,9 @@ impl RepoResponse {
                         if let Some(mime_type) = mime_type {
                             response = response.header(CONTENT_TYPE, mime_type.to_string());
                         }
-                        response.header(CONTENT_LENGTH, file_size.to_string())
+                        response
+                            .header(CONTENT_LENGTH, file_size.to_string())
+                            .header(ACCEPT_RANGES, "bytes")
                     }
                 }
                 .body(Body::empty())
@@ -350,6 +433,24 @@ impl From<Option<StorageFile>> for RepoResponse {
     }
 }
 
+impl From<OpenedFile> for RepoResponse {
+    fn from(file: OpenedFile) -> Self {
+        match file {
+            // Directories need the request to be rendered.
+            OpenedFile::Full(file) => RepoResponse::from(file),
+            file => RepoResponse::Other(response_opened_file(file)),
+        }
+    }
+}
+impl From<Option<OpenedFile>> for RepoResponse {
+    fn from(file: Option<OpenedFile>) -> Self {
+        match file {
+            Some(file) => RepoResponse::from(file),
+            None => RepoResponse::basic_text_response(StatusCode::NOT_FOUND, "File not found"),
+        }
+    }
+}
+
 impl From<Option<StorageFileMeta<FileType>>> for RepoResponse {
     fn from(meta: Option<StorageFileMeta<FileType>>) -> Self {
         match meta {
@@ -399,6 +500,7 @@ pub async fn handle_repo_request(
     }
     let method = request.method().clone();
     let (parts, body) = request.into_parts();
+    let listing_request = DirectoryListingRequest::from(&parts);
     let path = path.unwrap_or_default();
     let trace =
         RepositoryRequestTracing::new(&repository, &parent_span, site.repository_metrics.clone());
@@ -434,7 +536,7 @@ pub async fn handle_repo_request(
     };
     let _guard = request_debug.entered();
     match response {
-        Ok(response) => Ok(response.into_response_default()),
+        Ok(response) => Ok(response.into_response(&site.repository_pages, &listing_request)),
         Err(err) => {
             error!(?err, "Failed to handle request");
             Ok(err.into_response())
@@ -0,0 +1,234 @@
+use std::sync::Arc;
+
+use axum::{extract::OriginalUri, response::Response};
+use chrono::{DateTime, FixedOffset};
+use handlebars::{Handlebars, handlebars_helper};
+use http::{header::ACCEPT, request::Parts};
+use nr_core::storage::FileHashes;
+use nr_storage::{DirectoryFileType, FileType, StorageFileMeta};
+use rust_embed::RustEmbed;
+use serde::Serialize;
+use tracing::{error, instrument};
+
+use crate::utils::{ResponseBuilder, header::HeaderMapExt};
+
+#[derive(RustEmbed)]
+#[folder = "$CARGO_MANIFEST_DIR/resources/pages/repository"]
+pub struct RepositoryPageTemplates;
+
+pub const DIRECTORY_LISTING_TEMPLATE: &str = "directory_listing.html";
+
+handlebars_helper!(file_size_helper: |size: u64| format_file_size(size));
+
+/// Pages rendered by the repository handlers.
+///
+/// Uses the template engine shared with the email service.
+#[derive(Debug)]
+pub struct RepositoryPages {
+    handlebars: Arc<Handlebars<'static>>,
+}
+impl RepositoryPages {
+    /// The handlebars instance must have been passed to [RepositoryPages::register_templates]
+    pub fn new(handlebars: Arc<Handlebars<'static>>) -> Self {
+        Self { handlebars }
+    }
+    /// Registers the repository page templates and their helpers
+    pub fn register_templates(
+        handlebars: &mut Handlebars<'static>,
+    ) -> Result<(), handlebars::TemplateError> {
+        handlebars.register_embed_templates::<RepositoryPageTemplates>()?;
+        handlebars.register_helper("file_size", Box::new(file_size_helper));
+        Ok(())
+    }
+    #[instrument(skip(self))]
+    pub fn directory_listing_response(
+        &self,
+        listing: &DirectoryListing,
+        format: DirectoryListingFormat,
+    ) -> Response {
+        match format {
+            DirectoryListingFormat::Json => ResponseBuilder::ok().json(listing),
+            DirectoryListingFormat::Html => {
+                match self.handlebars.render(DIRECTORY_LISTING_TEMPLATE, listing) {
+                    Ok(html) => ResponseBuilder::ok().html(html),
+                    Err(err) => {
+                        error!(?err, "Failed to render directory listing");
+                        ResponseBuilder::internal_server_error()
+                            .body(format!("Failed to render directory listing: {}", err))
+                    }
+                }
+            }
+        }
+    }
+}
+/// The format a directory listing is returned in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DirectoryListingFormat {
+    /// Returned when the client sends `Accept: application/json`
+    Json,
+    #[default]
+    Html,
+}
+/// Information from the request needed to render a directory listing.
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryListingRequest {
+    /// The path of the request as the client sent it. Used to build the links to the entries
+    pub request_path: String,
+    pub format: DirectoryListingFormat,
+}
+impl From<&Parts> for DirectoryListingRequest {
+    fn from(parts: &Parts) -> Self {
+        // Repositories are nested so the uri in the parts does not contain the full path
+        let request_path = parts
+            .extensions
+            .get::<OriginalUri>()
+            .map(|uri| uri.path())
+            .unwrap_or_else(|| parts.uri.path())
+            .to_owned();
+        let format = match parts.headers.get_str_ignore_empty(&ACCEPT) {
+            Some(accept) if accept.contains(mime::APPLICATION_JSON.essence_str()) => {
+                DirectoryListingFormat::Json
+            }
+            _ => DirectoryListingFormat::Html,
+        };
+        Self {
+            request_path,
+            format,
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryListing {
+    pub name: String,
+    /// The path of the directory. Always ends with a `/`
+    pub path: String,
+    /// None if the directory is the root of the repository
+    pub parent: Option<String>,
+    pub file_count: u64,
+    pub modified: DateTime<FixedOffset>,
+    pub entries: Vec<DirectoryListingEntry>,
+}
+impl DirectoryListing {
+    pub fn new(
+        request_path: &str,
+        meta: &StorageFileMeta<DirectoryFileType>,
+        files: &[StorageFileMeta<FileType>],
+    ) -> Self {
+        let path = format!("{}/", request_path.trim_end_matches('/'));
+        let parent = parent_path(&path);
+        let mut entries: Vec<_> = files
+            .iter()
+            .map(|file| DirectoryListingEntry::new(&path, file))
+            .collect();
+        // Directories first then sorted by name. The same way most web servers list directories
+        entries.sort_by(|a, b| {
+            b.is_directory
+                .cmp(&a.is_directory)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Self {
+            name: meta.name.clone(),
+            path,
+            parent,
+            file_count: meta.file_type.file_count,
+            modified: meta.modified,
+            entries,
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryListingEntry {
+    pub name: String,
+    pub href: String,
+    pub is_directory: bool,
+    /// None for directories
+    pub size: Option<u64>,
+    pub modified: DateTime<FixedOffset>,
+    /// None for directories.
+    ///
+    /// Some storages do not include the hashes when listing a directory
+    pub hashes: Option<FileHashes>,
+}
+impl DirectoryListingEntry {
+    fn new(directory_path: &str, file: &StorageFileMeta<FileType>) -> Self {
+        let (is_directory, size, hashes) = match &file.file_type {
+            FileType::File(file_type) => (
+                false,
+                Some(file_type.file_size),
+                Some(file_type.file_hash.clone()),
+            ),
+            FileType::Directory(_) => (true, None, None),
+        };
+        let href = if is_directory {
+            format!("{}{}/", directory_path, file.name)
+        } else {
+            format!("{}{}", directory_path, file.name)
+        };
+        Self {
+            name: file.name.clone(),
+            href,
+            is_directory,
+            size,
+            modified: file.modified,
+            hashes,
+        }
+    }
+}
+/// The parent of a directory path. Both the input and output end with a `/`
+///
+/// The root of the repository is `/{repositories or storages}/{storage}/{repository}/`
+fn parent_path(path: &str) -> Option<String> {
+    let trimmed = path.trim_end_matches('/');
+    let (parent, _) = trimmed.rsplit_once('/')?;
+    // Do not link outside of the repository
+    if parent.matches('/').count() < 3 {
+        return None;
+    }
+    Some(format!("{}/", parent))
+}
+/// Formats a size in bytes using binary units. `1536` becomes `1.5 KiB`
+pub fn format_file_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next_unit in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next_unit;
+    }
+    format!("{:.1} {}", value, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn file_sizes() {
+        assert_eq!(format_file_size(0), "0 B");
+        assert_eq!(format_file_size(1023), "1023 B");
+        assert_eq!(format_file_size(1536), "1.5 KiB");
+        assert_eq!(format_file_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+    #[test]
+    fn parent_paths() {
+        assert_eq!(parent_path("/repositories/storage/repo/"), None);
+        assert_eq!(
+            parent_path("/repositories/storage/repo/com/"),
+            Some("/repositories/storage/repo/".to_owned())
+        );
+        assert_eq!(
+            parent_path("/repositories/storage/repo/com/example/"),
+            Some("/repositories/storage/repo/com/".to_owned())
+        );
+    }
+    #[test]
+    fn templates_register() {
+        let mut handlebars = Handlebars::new();
+        RepositoryPages::register_templates(&mut handlebars).expect("Templates should be valid");
+    }
+}
@@ -0,0 +1,314 @@
+use chrono::{DateTime, FixedOffset};
+use http::{
+    HeaderMap,
+    header::{IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, RANGE},
+};
+use nr_core::storage::{FileTypeCheck, StoragePath};
+use nr_storage::{
+    DynStorage, FileRange, FileType, Storage, StorageError, StorageFile, StorageFileMeta,
+};
+use tracing::{debug, instrument};
+use uuid::Uuid;
+
+use crate::utils::header::{HeaderMapExt, date_time::parse_date_time};
+
+/// The value of a `Range` header.
+///
+/// Only a single byte range is supported. Multiple ranges or invalid values are ignored and the entire file is returned.
+/// [RFC 9110 Section 14.2](https://www.rfc-editor.org/rfc/rfc9110#section-14.2) allows a server to ignore the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestedRange {
+    /// `bytes={start}-` or `bytes={start}-{end}`
+    FromStart { start: u64, end: Option<u64> },
+    /// `bytes=-{length}` The last `length` bytes of the file
+    Suffix(u64),
+}
+impl RequestedRange {
+    pub fn parse(value: &str) -> Option<Self> {
+        let ranges = value.trim().strip_prefix("bytes=")?;
+        if ranges.contains(',') {
+            debug!(?value, "Multiple ranges are not supported. Ignoring");
+            return None;
+        }
+        let (start, end) = ranges.split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+        if start.is_empty() {
+            return end.parse().ok().map(Self::Suffix);
+        }
+        let start: u64 = start.parse().ok()?;
+        let end: Option<u64> = if end.is_empty() {
+            None
+        } else {
+            Some(end.parse().ok()?)
+        };
+        if end.is_some_and(|end| end < start) {
+            return None;
+        }
+        Some(Self::FromStart { start, end })
+    }
+    /// Resolves the range against the size of the file.
+    ///
+    /// Returns None if the range can not be satisfied
+    pub fn resolve(&self, file_size: u64) -> Option<FileRange> {
+        if file_size == 0 {
+            return None;
+        }
+        let last_byte = file_size - 1;
+        match *self {
+            Self::FromStart { start, .. } if start > last_byte => None,
+            Self::FromStart { start, end } => {
+                FileRange::new(start, end.map_or(last_byte, |end| end.min(last_byte)))
+            }
+            Self::Suffix(0) => None,
+            Self::Suffix(length) => FileRange::new(file_size.saturating_sub(length), last_byte),
+        }
+    }
+}
+/// The conditional and range headers of a request for a file.
+#[derive(Debug, Clone, Default)]
+pub struct FileRequestHeaders {
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<DateTime<FixedOffset>>,
+    pub range: Option<RequestedRange>,
+    pub if_range: Option<String>,
+}
+impl From<&HeaderMap> for FileRequestHeaders {
+    fn from(headers: &HeaderMap) -> Self {
+        Self {
+            if_none_match: headers.get_string_ignore_empty(&IF_NONE_MATCH),
+            // Invalid dates are ignored. RFC 9110 Section 13.1.3
+            if_modified_since: headers
+                .get(IF_MODIFIED_SINCE)
+                .and_then(|value| parse_date_time(value).ok()),
+            range: headers
+                .get_str_ignore_empty(&RANGE)
+                .and_then(RequestedRange::parse),
+            if_range: headers.get_string_ignore_empty(&IF_RANGE),
+        }
+    }
+}
+impl FileRequestHeaders {
+    /// No conditional or range headers were sent
+    pub fn is_empty(&self) -> bool {
+        self.if_none_match.is_none() && self.if_modified_since.is_none() && self.range.is_none()
+    }
+    /// Returns true if the copy the client has is still valid.
+    ///
+    /// `If-Modified-Since` is only checked if `If-None-Match` is not present. RFC 9110 Section 13.1.3
+    pub fn is_not_modified(&self, modified: &DateTime<FixedOffset>, etag: Option<&str>) -> bool {
+        if let Some(if_none_match) = &self.if_none_match {
+            return etag_matches(if_none_match, etag, true);
+        }
+        if let Some(since) = &self.if_modified_since {
+            // HTTP Dates do not have sub-second precision
+            return modified.timestamp() <= since.timestamp();
+        }
+        false
+    }
+    /// The range that should be returned. If `If-Range` does not match the current file the range is ignored.
+    pub fn range(
+        &self,
+        modified: &DateTime<FixedOffset>,
+        etag: Option<&str>,
+    ) -> Option<RequestedRange> {
+        let range = self.range?;
+        let Some(if_range) = &self.if_range else {
+            return Some(range);
+        };
+        let matches = match DateTime::parse_from_rfc2822(if_range) {
+            Ok(date) => modified.timestamp() == date.timestamp(),
+            // If-Range requires a strong comparison
+            Err(_) => etag_matches(if_range, etag, false),
+        };
+        if !matches {
+            debug!(?if_range, "If-Range does not match. Ignoring Range");
+        }
+        matches.then_some(range)
+    }
+}
+/// Compares the value of an `If-None-Match` or `If-Range` header with the ETag of the file
+fn etag_matches(header: &str, etag: Option<&str>, allow_weak: bool) -> bool {
+    let Some(etag) = etag else {
+        return false;
+    };
+    header.split(',').map(str::trim).any(|value| {
+        if value == "*" {
+            return allow_weak;
+        }
+        let value = match value.strip_prefix("W/") {
+            Some(_) if !allow_weak => return false,
+            Some(weak) => weak,
+            None => value,
+        };
+        value.trim_matches('"') == etag
+    })
+}
+/// The result of opening a file for a GET request.
+#[derive(Debug)]
+pub enum OpenedFile {
+    /// The entire file or a directory
+    Full(StorageFile),
+    /// Only the requested range of the file
+    Partial { range: FileRange, file: StorageFile },
+    /// The copy the client has is still valid
+    NotModified(StorageFileMeta<FileType>),
+    /// The requested range is not within the file
+    RangeNotSatisfiable { file_size: u64 },
+}
+impl FileTypeCheck for OpenedFile {
+    fn is_directory(&self) -> bool {
+        matches!(self, OpenedFile::Full(file) if file.is_directory())
+    }
+    fn is_file(&self) -> bool {
+        !self.is_directory()
+    }
+}
+impl OpenedFile {
+    /// The number of bytes of the file that will be sent to the client
+    pub fn content_length(&self) -> Option<u64> {
+        match self {
+            OpenedFile::Full(StorageFile::File { meta, .. }) => Some(meta.file_type.file_size),
+            OpenedFile::Partial { range, .. } => Some(range.len()),
+            _ => None,
+        }
+    }
+}
+/// Opens a file respecting the conditional and range headers of the request.
+///
+/// Directories are always opened normally.
+#[instrument(skip(storage))]
+pub async fn open_file_for_request(
+    storage: &DynStorage,
+    repository: Uuid,
+    path: &StoragePath,
+    headers: &FileRequestHeaders,
+) -> Result<Option<OpenedFile>, StorageError> {
+    if headers.is_empty() {
+        return Ok(storage
+            .open_file(repository, path)
+            .await?
+            .map(OpenedFile::Full));
+    }
+    let Some(meta) = storage.get_file_information(repository, path).await? else {
+        return Ok(None);
+    };
+    let FileType::File(file_type) = meta.file_type() else {
+        return Ok(storage
+            .open_file(repository, path)
+            .await?
+            .map(OpenedFile::Full));
+    };
+    let etag = file_type.file_hash.sha2_256.as_deref();
+    if headers.is_not_modified(meta.modified(), etag) {
+        return Ok(Some(OpenedFile::NotModified(meta)));
+    }
+    if let Some(range) = headers.range(meta.modified(), etag) {
+        let Some(range) = range.resolve(file_type.file_size) else {
+            return Ok(Some(OpenedFile::RangeNotSatisfiable {
+                file_size: file_type.file_size,
+            }));
+        };
+        return Ok(storage
+            .open_file_range(repository, path, range)
+            .await?
+            .map(|file| OpenedFile::Partial { range, file }));
+    }
+    Ok(storage
+        .open_file(repository, path)
+        .await?
+        .map(OpenedFile::Full))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use nr_storage::FileRange;
+
+    use super::{FileRequestHeaders, RequestedRange};
+
+    #[test]
+    fn parse_range() {
+        assert_eq!(
+            RequestedRange::parse("bytes=0-499"),
+            Some(RequestedRange::FromStart {
+                start: 0,
+                end: Some(499)
+            })
+        );
+        assert_eq!(
+            RequestedRange::parse("bytes=500-"),
+            Some(RequestedRange::FromStart {
+                start: 500,
+                end: None
+            })
+        );
+        assert_eq!(
+            RequestedRange::parse("bytes=-500"),
+            Some(RequestedRange::Suffix(500))
+        );
+        assert_eq!(RequestedRange::parse("bytes=0-1,5-6"), None);
+        assert_eq!(RequestedRange::parse("bytes=10-5"), None);
+        assert_eq!(RequestedRange::parse("items=0-5"), None);
+    }
+    #[test]
+    fn resolve_range() {
+        let range = |start, end| FileRange::new(start, end);
+        assert_eq!(
+            RequestedRange::parse("bytes=0-499").unwrap().resolve(1000),
+            range(0, 499)
+        );
+        assert_eq!(
+            RequestedRange::parse("bytes=500-").unwrap().resolve(1000),
+            range(500, 999)
+        );
+        assert_eq!(
+            RequestedRange::parse("bytes=900-5000")
+                .unwrap()
+                .resolve(1000),
+            range(900, 999)
+        );
+        assert_eq!(
+            RequestedRange::parse("bytes=-100").unwrap().resolve(1000),
+            range(900, 999)
+        );
+        assert_eq!(
+            RequestedRange::parse("bytes=-5000").unwrap().resolve(1000),
+            range(0, 999)
+        );
+        assert_eq!(
+            RequestedRange::parse("bytes=1000-").unwrap().resolve(1000),
+            None
+        );
+        assert_eq!(RequestedRange::parse("bytes=0-").unwrap().resolve(0), None);
+    }
+    #[test]
+    fn conditionals() {
+        let modified = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        let headers = FileRequestHeaders {
+            if_none_match: Some("\"abc\"".to_owned()),
+            ..Default::default()
+        };
+        assert!(headers.is_not_modified(&modified, Some("abc")));
+        assert!(!headers.is_not_modified(&modified, Some("def")));
+
+        let headers = FileRequestHeaders {
+            if_modified_since: Some(modified),
+            ..Default::default()
+        };
+        assert!(headers.is_not_modified(&modified, None));
+        let later = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:01 GMT").unwrap();
+        assert!(!headers.is_not_modified(&later, None));
+
+        let headers = FileRequestHeaders {
+            range: RequestedRange::parse("bytes=0-1"),
+            if_range: Some("W/\"abc\"".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(headers.range(&modified, Some("abc")), None);
+        let headers = FileRequestHeaders {
+            if_range: Some("abc".to_owned()),
+            ..headers
+        };
+        assert!(headers.range(&modified, Some("abc")).is_some());
+    }
+}
@@ -1,3 +1,5 @@
+use std::net::IpAddr;
+
 use axum::extract::{FromRef, FromRequestParts};
 use http::request::Parts;
 use nr_core::{
@@ -14,7 +16,10 @@ use uuid::Uuid;
 
 use crate::app::{
     NitroRepo,
-    authentication::{AuthenticationError, AuthenticationRaw, session::Session, verify_login},
+    authentication::{
+        AuthenticationError, AuthenticationRaw, get_user_and_auth_token, request_ip_address,
+        session::Session, verify_login,
+    },
 };
 
 #[derive(Clone, Debug, PartialEq, EnumIs)]
@@ -158,7 +163,8 @@ where
         };
         match raw_auth {
             AuthenticationRaw::AuthToken(token) => {
-                let (token, user) = get_by_auth_token(&token, &repo.database).await?;
+                let ip_address = request_ip_address(parts, &repo).await;
+                let (token, user) = get_by_auth_token(&token, ip_address, &repo).await?;
                 Ok(RepositoryAuthentication::Basic(Some(token), user))
             }
             AuthenticationRaw::Session(session) => {
@@ -171,7 +177,8 @@ where
                 match verify_login(username, &password, &repo.database).await {
                     Ok(user) => Ok(RepositoryAuthentication::Basic(None, user)),
                     Err(AuthenticationError::Unauthorized) => {
-                        let (token, user) = get_by_auth_token(&password, &repo.database).await?;
+                        let ip_address = request_ip_address(parts, &repo).await;
+                        let (token, user) = get_by_auth_token(&password, ip_address, &repo).await?;
                         Ok(RepositoryAuthentication::Basic(Some(token), user))
                     }
                     Err(err) => Err(err),
@@ -187,13 +194,9 @@ where
 }
 async fn get_by_auth_token(
     token: &str,
-    database: &PgPool,
+    ip_address: Option<IpAddr>,
+    site: &NitroRepo,
 ) -> Result<(AuthToken, UserSafeData), AuthenticationError> {
-    let token = AuthToken::get_by_token(token, database)
-        .await?
-        .ok_or(AuthenticationError::Unauthorized)?;
-    let user = UserSafeData::get_by_id(token.user_id, database)
-        .await?
-        .ok_or(AuthenticationError::Unauthorized)?;
+    let (user, token) = get_user_and_auth_token(token, ip_address, &site.database).await?;
     Ok((token, user))
 }
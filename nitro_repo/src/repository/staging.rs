@@ -2,17 +2,26 @@ use std::{fmt::Debug, path::PathBuf, sync::Arc};
 
 use axum::response::{IntoResponse, Response};
 use bytes::Bytes;
-use chrono::Duration;
+use chrono::{Duration, Local};
 use derive_more::derive::Deref;
 use http::StatusCode;
-use nr_core::database::entities::stages::{DBStage, NewDBStageFile};
+use nr_core::database::entities::stages::{DBStage, NewDBStage, NewDBStageFile};
 use redb::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use thiserror::Error;
-use tracing::{debug, error, instrument};
+use tokio::task::JoinHandle;
+use tracing::{
+    Instrument, Level, debug, error,
+    field::{Empty, display},
+    info, instrument, span,
+};
 use uuid::Uuid;
 
-use crate::app::{NitroRepo, config::get_current_directory};
+use crate::{
+    app::{NitroRepo, config::get_current_directory},
+    utils::IntoErrorResponse,
+};
 #[derive(Debug, Error)]
 pub enum StagingManagerError {
     #[error("Database Error")]
@@ -30,18 +39,34 @@ impl IntoResponse for StagingManagerError {
             .unwrap()
     }
 }
+impl IntoErrorResponse for StagingManagerError {
+    fn into_response_boxed(self: Box<Self>) -> axum::response::Response {
+        self.into_response()
+    }
+}
 /// Stages are stored locally before being moved to the storage
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct StagingConfig {
     pub staging_dir: PathBuf,
+    /// How old a stage can get before it is deleted
     #[serde(with = "nr_core::utils::duration_serde::as_seconds")]
     pub time_till_cleanup: Duration,
+    /// How often expired stages are deleted
+    #[serde(
+        with = "nr_core::utils::duration_serde::as_seconds",
+        default = "default_stage_cleanup_interval"
+    )]
+    pub cleanup_interval: Duration,
+}
+fn default_stage_cleanup_interval() -> Duration {
+    Duration::minutes(10)
 }
 impl Default for StagingConfig {
     fn default() -> Self {
         Self {
             staging_dir: get_current_directory().join("staging"),
             time_till_cleanup: Duration::hours(1),
+            cleanup_interval: default_stage_cleanup_interval(),
         }
     }
 }
@@ -61,6 +86,30 @@ impl Debug for StagingManager {
 }
 
 impl StagingManager {
+    pub fn new(repository: Uuid, site: NitroRepo) -> Self {
+        Self(Arc::new(StagingManagerInner { repository, site }))
+    }
+    fn stage_directory(&self, stage_id: Uuid) -> PathBuf {
+        self.site
+            .staging_config
+            .staging_dir
+            .join(stage_id.to_string())
+    }
+    #[instrument]
+    pub async fn create_stage(
+        &self,
+        stage_state: Value,
+        created_by: i32,
+    ) -> Result<DBStage, StagingManagerError> {
+        let new_stage = NewDBStage {
+            repository: self.repository,
+            stage_state,
+            created_by,
+        };
+        let stage = new_stage.insert(&self.site.database).await?;
+        debug!(?stage.id, "Created Stage");
+        Ok(stage)
+    }
     pub async fn get_stage(&self, id: Uuid) -> Result<Option<DBStage>, StagingManagerError> {
         let stage = DBStage::get_stage_by_id(id, self.repository, &self.site.database).await?;
         Ok(stage)
@@ -73,11 +122,7 @@ impl StagingManager {
         file_name: String,
         file: Bytes,
     ) -> Result<(), StagingManagerError> {
-        let staging_dir = self
-            .site
-            .staging_config
-            .staging_dir
-            .join(stage_id.to_string());
+        let staging_dir = self.stage_directory(stage_id);
         if !staging_dir.exists() {
             std::fs::create_dir_all(&staging_dir)?;
         }
@@ -93,4 +138,83 @@ impl StagingManager {
         debug!(?new_file, "File added to stage");
         Ok(())
     }
+    /// Reads a file that was added to the stage
+    pub async fn read_file(
+        &self,
+        stage_id: Uuid,
+        file_name: &str,
+    ) -> Result<Option<Bytes>, StagingManagerError> {
+        let file_path = self.stage_directory(stage_id).join(file_name);
+        if !file_path.exists() {
+            return Ok(None);
+        }
+        let file = std::fs::read(file_path)?;
+        Ok(Some(Bytes::from(file)))
+    }
+    /// Deletes the stage and all of its files
+    #[instrument]
+    pub async fn delete_stage(&self, stage: &DBStage) -> Result<(), StagingManagerError> {
+        let staging_dir = self.stage_directory(stage.id);
+        if staging_dir.exists() {
+            std::fs::remove_dir_all(&staging_dir)?;
+        }
+        stage.delete_stage(&self.site.database).await?;
+        debug!(?stage.id, "Deleted Stage");
+        Ok(())
+    }
+}
+/// Deletes every stage older than [StagingConfig::time_till_cleanup] and its files
+///
+/// A stage that fails to delete is logged and retried on the next run.
+/// Returns the number of stages deleted
+async fn delete_expired_stages(site: &NitroRepo) -> Result<usize, StagingManagerError> {
+    let created_before = Local::now().fixed_offset() - site.staging_config.time_till_cleanup;
+    let stages = DBStage::get_stages_created_before(created_before, &site.database).await?;
+    let mut deleted = 0;
+    for stage in &stages {
+        debug!(?stage.id, ?stage.repository, "Deleting expired stage");
+        let result = StagingManager::new(stage.repository, site.clone())
+            .delete_stage(stage)
+            .await;
+        match result {
+            Ok(()) => deleted += 1,
+            Err(err) => {
+                error!(?stage.id, ?stage.repository, "Failed to delete expired stage: {}", err);
+            }
+        }
+    }
+    Ok(deleted)
+}
+/// Periodically deletes stages that were never published or dropped.
+pub fn start_stage_cleaner(site: NitroRepo) -> JoinHandle<()> {
+    let how_often = site
+        .staging_config
+        .cleanup_interval
+        .to_std()
+        .expect("Duration is too large");
+    debug!("Starting Stage Cleaner with interval: {:?}", how_often);
+    tokio::spawn(async move {
+        loop {
+            let span = span!(
+                Level::INFO,
+                "Stage Cleaner",
+                stages.removed = Empty,
+                stage.cleaner.error = Empty
+            );
+            let result = delete_expired_stages(&site).instrument(span.clone()).await;
+            let sleep_for = match result {
+                Ok(value) => {
+                    info!(parent: &span, "Deleted {} expired stages", value);
+                    span.record("stages.removed", value);
+                    how_often
+                }
+                Err(err) => {
+                    error!(parent: &span, "Failed to delete expired stages: {:?}", err);
+                    span.record("stage.cleaner.error", display(err));
+                    how_often / 2
+                }
+            };
+            tokio::time::sleep(sleep_for).await
+        }
+    })
 }
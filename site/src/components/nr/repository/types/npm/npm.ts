@@ -20,19 +20,35 @@ export interface MavenProxyRoute {
   url: string;
   name?: string;
 }
+export interface NPMProxyCacheConfigType {
+  /** Seconds a cached packument is served before it is revalidated. Null never revalidates */
+  packument_max_age?: number | null;
+}
 export interface NPMProxyConfigType {
   routes: MavenProxyRoute[];
+  cache?: NPMProxyCacheConfigType;
 }
 export function defaultProxy(): NPMProxyConfigType {
   return {
     routes: [],
   };
 }
+export interface NPMHostedConfigType {
+  /** Seconds after publishing that a version can be unpublished. Null allows it at any time */
+  unpublish_window?: number | null;
+}
 export type NPMConfigType =
   | {
       type: "Hosted";
+      config?: NPMHostedConfigType | null;
     }
   | {
       type: "Proxy";
       config: NPMProxyConfigType;
     };
+/** A pending `npm login --auth-type=web` */
+export interface NPMWebLoginDetails {
+  repository_id: string;
+  repository_name: string;
+  hostname?: string | null;
+}
@@ -5,6 +5,7 @@ import LoginView from "@/views/LoginView.vue";
 import LogoutView from "@/views/LogoutView.vue";
 
 import RepositoriesView from "@/views/RepositoriesView.vue";
+import NPMWebLoginView from "@/views/npm/NPMWebLoginView.vue";
 import type { Component } from "vue";
 
 import { adminRoutes } from "@/views/admin/adminRoutes";
@@ -53,6 +54,14 @@ const routes = [
     name: "repositories",
     component: RepositoriesView,
   },
+  {
+    path: "/npm/login/:repositoryId/:loginId",
+    name: "npmWebLogin",
+    component: NPMWebLoginView,
+    meta: {
+      requiresAuth: true,
+    },
+  },
   ...repositoryPages,
   ...adminRoutes,
   ...profileRoutes,
@@ -16,6 +16,7 @@ export interface RawProjectVersion {
   project_id: string;
   version: string;
   release_type: string;
+  yanked_at?: string;
 }
 
 export class Project {
@@ -48,10 +49,12 @@ export class ProjectVersion {
   project_id: string;
   version: string;
   release_type: string;
+  yanked_at?: Date;
   constructor(data: RawProjectVersion) {
     this.id = data.id;
     this.project_id = data.project_id;
     this.version = data.version;
     this.release_type = data.release_type;
+    this.yanked_at = data.yanked_at ? new Date(data.yanked_at) : undefined;
   }
 }